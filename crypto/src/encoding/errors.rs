@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum DecodingError {
+    #[error("unexpected end of data: ({0})")]
+    UnexpectedEnd(&'static str),
+
+    #[error("invalid encoding: ({0})")]
+    InvalidEncoding(&'static str),
+
+    #[error("trailing data after the last field")]
+    TrailingData,
+}
@@ -0,0 +1,242 @@
+pub mod errors;
+
+use errors::DecodingError;
+use std::mem::size_of;
+
+// Reader over data types of the SSH wire format (RFC 4251, section 5) used
+// in key and signature blobs and in message payloads. Strings are borrowed
+// from the input.
+pub struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, DecodingError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    // Any non-zero value is true.
+    pub fn read_bool(&mut self) -> Result<bool, DecodingError> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, DecodingError> {
+        let bytes = self.read_bytes(size_of::<u32>())?;
+
+        Ok(u32::from_be_bytes(
+            bytes.try_into().expect("Should not be reachable"),
+        ))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, DecodingError> {
+        let bytes = self.read_bytes(size_of::<u64>())?;
+
+        Ok(u64::from_be_bytes(
+            bytes.try_into().expect("Should not be reachable"),
+        ))
+    }
+
+    pub fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], DecodingError> {
+        if self.data.len() < length {
+            return Err(DecodingError::UnexpectedEnd(
+                "field is longer than the remaining data",
+            ));
+        }
+
+        let (bytes, rest) = self.data.split_at(length);
+        self.data = rest;
+
+        Ok(bytes)
+    }
+
+    pub fn read_string(&mut self) -> Result<&'a [u8], DecodingError> {
+        let length = self.read_u32()? as usize;
+        self.read_bytes(length)
+    }
+
+    pub fn read_utf8(&mut self) -> Result<&'a str, DecodingError> {
+        std::str::from_utf8(self.read_string()?)
+            .map_err(|_| DecodingError::InvalidEncoding("string is not valid UTF-8"))
+    }
+
+    // Multiple precision integer, returned as the big endian magnitude
+    // without leading zeros. Negative numbers are not used by any key
+    // format, so they are rejected.
+    pub fn read_mpint(&mut self) -> Result<&'a [u8], DecodingError> {
+        let bytes = self.read_string()?;
+
+        if bytes.first().is_some_and(|b| b & 0x80 != 0) {
+            return Err(DecodingError::InvalidEncoding("mpint is negative"));
+        }
+
+        let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+        Ok(&bytes[start..])
+    }
+
+    pub fn remaining(&self) -> &'a [u8] {
+        self.data
+    }
+
+    // Make sure the whole input has been consumed.
+    pub fn finish(self) -> Result<(), DecodingError> {
+        if !self.data.is_empty() {
+            return Err(DecodingError::TrailingData);
+        }
+
+        Ok(())
+    }
+}
+
+// Builder of SSH wire format blobs, the counterpart of `Reader`.
+#[derive(Default)]
+pub struct Writer {
+    buff: Vec<u8>,
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn put_u8(&mut self, value: u8) -> &mut Self {
+        self.buff.push(value);
+        self
+    }
+
+    pub fn put_bool(&mut self, value: bool) -> &mut Self {
+        self.put_u8(value as u8)
+    }
+
+    pub fn put_u32(&mut self, value: u32) -> &mut Self {
+        self.buff.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn put_u64(&mut self, value: u64) -> &mut Self {
+        self.buff.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn put_bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.buff.extend_from_slice(bytes);
+        self
+    }
+
+    pub fn put_string(&mut self, bytes: &[u8]) -> &mut Self {
+        self.put_u32(bytes.len() as u32).put_bytes(bytes)
+    }
+
+    // Write a non-negative integer given as big endian bytes, with a zero
+    // byte prepended when the most significant bit is set.
+    pub fn put_mpint(&mut self, magnitude: &[u8]) -> &mut Self {
+        let start = magnitude
+            .iter()
+            .position(|&b| b != 0)
+            .unwrap_or(magnitude.len());
+        let magnitude = &magnitude[start..];
+
+        if magnitude.first().is_some_and(|b| b & 0x80 != 0) {
+            self.put_u32(magnitude.len() as u32 + 1)
+                .put_bytes(&[0])
+                .put_bytes(magnitude)
+        } else {
+            self.put_string(magnitude)
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_fields() {
+        let data = [0, 0, 0, 3, b'a', b'b', b'c', 0, 0, 0, 7, 1];
+        let mut reader = Reader::new(&data);
+
+        assert_eq!(reader.read_utf8().unwrap(), "abc");
+        assert_eq!(reader.read_u32().unwrap(), 7);
+        assert_eq!(reader.remaining(), [1]);
+        assert_eq!(
+            reader.finish().unwrap_err().to_string(),
+            "trailing data after the last field"
+        );
+    }
+
+    #[test]
+    fn test_read_truncated_string() {
+        let data = [0, 0, 0, 4, b'a', b'b'];
+        let err = Reader::new(&data).read_string().unwrap_err().to_string();
+
+        assert_eq!(
+            err,
+            "unexpected end of data: (field is longer than the remaining data)"
+        );
+    }
+
+    #[test]
+    fn test_write_and_read_back() {
+        let mut writer = Writer::new();
+        writer
+            .put_u8(80)
+            .put_string(b"ssh-ed25519")
+            .put_bool(true)
+            .put_u32(42)
+            .put_u64(u64::MAX - 1);
+        let blob = writer.into_bytes();
+        let mut reader = Reader::new(&blob);
+
+        assert_eq!(reader.read_u8().unwrap(), 80);
+        assert_eq!(reader.read_string().unwrap(), b"ssh-ed25519");
+        assert!(reader.read_bool().unwrap());
+        assert_eq!(reader.read_u32().unwrap(), 42);
+        assert_eq!(reader.read_u64().unwrap(), u64::MAX - 1);
+        assert!(reader.finish().is_ok());
+    }
+
+    #[test]
+    fn test_mpint_roundtrip() {
+        // RFC 4251, section 5 examples. Leading zeros of the input are
+        // not written and not returned back.
+        let cases: [(&[u8], &[u8], &[u8]); 4] = [
+            (&[], &[0, 0, 0, 0], &[]),
+            (&[0x80], &[0, 0, 0, 2, 0, 0x80], &[0x80]),
+            (
+                &[0x00, 0x12, 0x34],
+                &[0, 0, 0, 2, 0x12, 0x34],
+                &[0x12, 0x34],
+            ),
+            (
+                &[0x09, 0xa3, 0x78, 0xf9, 0xb2, 0xe3, 0x32, 0xa7],
+                &[0, 0, 0, 8, 0x09, 0xa3, 0x78, 0xf9, 0xb2, 0xe3, 0x32, 0xa7],
+                &[0x09, 0xa3, 0x78, 0xf9, 0xb2, 0xe3, 0x32, 0xa7],
+            ),
+        ];
+
+        for (magnitude, encoded, decoded) in cases {
+            let mut writer = Writer::new();
+            writer.put_mpint(magnitude);
+
+            assert_eq!(writer.into_bytes(), encoded);
+            assert_eq!(Reader::new(encoded).read_mpint().unwrap(), decoded);
+        }
+    }
+
+    #[test]
+    fn test_negative_mpint() {
+        let err = Reader::new(&[0, 0, 0, 1, 0xff])
+            .read_mpint()
+            .unwrap_err()
+            .to_string();
+
+        assert_eq!(err, "invalid encoding: (mpint is negative)");
+    }
+}
@@ -1,10 +1,11 @@
 use super::errors::{DecryptionError, EncryptionError};
-use super::Encryption;
+use super::Cipher;
 pub use aes::cipher::{generic_array::GenericArray, BlockDecrypt, BlockEncrypt, KeyInit};
 use aes::Aes128;
 
+// The key schedule is wiped on drop by the `aes` crate.
 pub struct AES {
-    pub cipher: Aes128,
+    cipher: Aes128,
 }
 
 impl AES {
@@ -23,8 +24,20 @@ impl Default for AES {
     }
 }
 
-impl Encryption for AES {
-    fn encrypt(&self, block: &mut [u8]) -> Result<(), EncryptionError> {
+impl Cipher for AES {
+    fn block_size(&self) -> usize {
+        16
+    }
+
+    fn key_len(&self) -> usize {
+        16
+    }
+
+    fn iv_len(&self) -> usize {
+        0
+    }
+
+    fn encrypt(&mut self, block: &mut [u8]) -> Result<(), EncryptionError> {
         if block.len() != 16 {
             return Err(EncryptionError::IncorrectBlockSize(
                 "block size has to have 16 bytes",
@@ -37,7 +50,7 @@ impl Encryption for AES {
         Ok(())
     }
 
-    fn decrypt(&self, block: &mut [u8]) -> Result<(), DecryptionError> {
+    fn decrypt(&mut self, block: &mut [u8]) -> Result<(), DecryptionError> {
         if block.len() != 16 {
             return Err(DecryptionError::IncorrectBlockSize(
                 "block size has to have 16 bytes",
@@ -57,7 +70,7 @@ mod tests {
 
     #[test]
     fn test_correct_encryption_and_decryption() {
-        let aes = AES::new();
+        let mut aes = AES::new();
         let mut block = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
         let mut expected = [
             101, 159, 42, 191, 197, 188, 7, 8, 251, 150, 231, 164, 74, 249, 213, 149,
@@ -72,7 +85,7 @@ mod tests {
 
     #[test]
     fn test_incorrect_encryption() {
-        let aes = AES::new();
+        let mut aes = AES::new();
 
         let mut block = [42u8; 5];
         let err = aes.encrypt(&mut block).unwrap_err().to_string();
@@ -85,7 +98,7 @@ mod tests {
 
     #[test]
     fn test_incorrect_decryption() {
-        let aes = AES::new();
+        let mut aes = AES::new();
 
         let mut block = [42u8; 5];
         let err = aes.decrypt(&mut block).unwrap_err().to_string();
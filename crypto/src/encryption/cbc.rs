@@ -0,0 +1,231 @@
+use super::errors::{CipherInitError, DecryptionError, EncryptionError};
+use super::Cipher;
+use aes::cipher::{Block, BlockDecrypt, BlockEncrypt, KeyInit};
+use aes::{Aes128, Aes256};
+use des::TdesEde3;
+use zeroize::Zeroize;
+
+// Cipher block chaining as used by aes128-cbc, aes256-cbc and 3des-cbc
+// (RFC 4253). The last ciphertext block of a packet is the IV of the next
+// one, so the chaining state is kept between calls.
+//
+// CBC is only available for peers which do not support anything better and
+// is never negotiated unless explicitly allowed.
+pub struct Cbc<C: BlockEncrypt + BlockDecrypt + KeyInit> {
+    cipher: C,
+    iv: Block<C>,
+}
+
+pub type Aes128Cbc = Cbc<Aes128>;
+pub type Aes256Cbc = Cbc<Aes256>;
+pub type TripleDesCbc = Cbc<TdesEde3>;
+
+impl<C: BlockEncrypt + BlockDecrypt + KeyInit> Cbc<C> {
+    pub fn try_build(key: &[u8], iv: &[u8]) -> Result<Self, CipherInitError> {
+        if iv.len() != C::block_size() {
+            return Err(CipherInitError::IncorrectIvLength(
+                "iv has to be as long as the cipher block",
+            ));
+        }
+
+        let cipher = C::new_from_slice(key).map_err(|_| {
+            CipherInitError::IncorrectKeyLength("key length does not match the cipher")
+        })?;
+
+        Ok(Self {
+            cipher,
+            iv: Block::<C>::clone_from_slice(iv),
+        })
+    }
+}
+
+impl<C: BlockEncrypt + BlockDecrypt + KeyInit> Drop for Cbc<C> {
+    fn drop(&mut self) {
+        self.iv.as_mut_slice().zeroize();
+    }
+}
+
+impl<C: BlockEncrypt + BlockDecrypt + KeyInit> Cipher for Cbc<C> {
+    fn block_size(&self) -> usize {
+        C::block_size()
+    }
+
+    fn key_len(&self) -> usize {
+        C::key_size()
+    }
+
+    fn iv_len(&self) -> usize {
+        C::block_size()
+    }
+
+    fn encrypt(&mut self, buffer: &mut [u8]) -> Result<(), EncryptionError> {
+        if !buffer.len().is_multiple_of(C::block_size()) {
+            return Err(EncryptionError::IncorrectBlockSize(
+                "buffer length has to be a multiple of the cipher block",
+            ));
+        }
+
+        for chunk in buffer.chunks_exact_mut(C::block_size()) {
+            let block = Block::<C>::from_mut_slice(chunk);
+            block
+                .iter_mut()
+                .zip(self.iv.iter())
+                .for_each(|(b, v)| *b ^= v);
+            self.cipher.encrypt_block(block);
+            self.iv.copy_from_slice(block);
+        }
+
+        Ok(())
+    }
+
+    fn decrypt(&mut self, buffer: &mut [u8]) -> Result<(), DecryptionError> {
+        if !buffer.len().is_multiple_of(C::block_size()) {
+            return Err(DecryptionError::IncorrectBlockSize(
+                "buffer length has to be a multiple of the cipher block",
+            ));
+        }
+
+        for chunk in buffer.chunks_exact_mut(C::block_size()) {
+            let block = Block::<C>::from_mut_slice(chunk);
+            let ciphertext = block.clone();
+            self.cipher.decrypt_block(block);
+            block
+                .iter_mut()
+                .zip(self.iv.iter())
+                .for_each(|(b, v)| *b ^= v);
+            self.iv = ciphertext;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // NIST SP 800-38A, F.2 test vectors.
+    const IV: [u8; 16] = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f,
+    ];
+    const PLAINTEXT: [u8; 32] = [
+        0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17,
+        0x2a, 0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c, 0x9e, 0xb7, 0x6f, 0xac, 0x45, 0xaf,
+        0x8e, 0x51,
+    ];
+    const AES128_KEY: [u8; 16] = [
+        0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f,
+        0x3c,
+    ];
+    const AES128_CIPHERTEXT: [u8; 32] = [
+        0x76, 0x49, 0xab, 0xac, 0x81, 0x19, 0xb2, 0x46, 0xce, 0xe9, 0x8e, 0x9b, 0x12, 0xe9, 0x19,
+        0x7d, 0x50, 0x86, 0xcb, 0x9b, 0x50, 0x72, 0x19, 0xee, 0x95, 0xdb, 0x11, 0x3a, 0x91, 0x76,
+        0x78, 0xb2,
+    ];
+
+    #[test]
+    fn test_aes128_cbc_encryption() {
+        let mut cbc = Aes128Cbc::try_build(&AES128_KEY, &IV).unwrap();
+        let mut buffer = PLAINTEXT;
+
+        cbc.encrypt(&mut buffer).unwrap();
+
+        assert_eq!(buffer, AES128_CIPHERTEXT);
+    }
+
+    #[test]
+    fn test_aes256_cbc_encryption() {
+        let key = [
+            0x60, 0x3d, 0xeb, 0x10, 0x15, 0xca, 0x71, 0xbe, 0x2b, 0x73, 0xae, 0xf0, 0x85, 0x7d,
+            0x77, 0x81, 0x1f, 0x35, 0x2c, 0x07, 0x3b, 0x61, 0x08, 0xd7, 0x2d, 0x98, 0x10, 0xa3,
+            0x09, 0x14, 0xdf, 0xf4,
+        ];
+        let expected = [
+            0xf5, 0x8c, 0x4c, 0x04, 0xd6, 0xe5, 0xf1, 0xba, 0x77, 0x9e, 0xab, 0xfb, 0x5f, 0x7b,
+            0xfb, 0xd6, 0x9c, 0xfc, 0x4e, 0x96, 0x7e, 0xdb, 0x80, 0x8d, 0x67, 0x9f, 0x77, 0x7b,
+            0xc6, 0x70, 0x2c, 0x7d,
+        ];
+        let mut cbc = Aes256Cbc::try_build(&key, &IV).unwrap();
+        let mut buffer = PLAINTEXT;
+
+        cbc.encrypt(&mut buffer).unwrap();
+
+        assert_eq!(buffer, expected);
+    }
+
+    #[test]
+    fn test_3des_cbc_encryption() {
+        // Cross-checked against OpenSSL, with the key 0x00..0x17 and the IV
+        // 0xa0..0xa7.
+        let key: Vec<u8> = (0..24).collect();
+        let iv: Vec<u8> = (0xa0..0xa8).collect();
+        let expected = [
+            0x60, 0x9f, 0xd8, 0x04, 0xd5, 0x87, 0xbf, 0xa2, 0x7b, 0xf5, 0xcf, 0x18, 0xaf, 0x76,
+            0xc6, 0xcd, 0xcc, 0xf9, 0x06, 0xd7, 0xd3, 0x2d, 0x74, 0x50, 0x85, 0xd6, 0x67, 0xd6,
+            0xdf, 0xc2, 0x7d, 0xec,
+        ];
+        let mut cbc = TripleDesCbc::try_build(&key, &iv).unwrap();
+        let mut buffer = PLAINTEXT;
+
+        cbc.encrypt(&mut buffer).unwrap();
+
+        assert_eq!(buffer, expected);
+        assert_eq!(cbc.block_size(), 8);
+        assert_eq!(cbc.key_len(), 24);
+    }
+
+    #[test]
+    fn test_iv_is_chained_between_calls() {
+        let mut encryptor = Aes128Cbc::try_build(&AES128_KEY, &IV).unwrap();
+        let mut decryptor = Aes128Cbc::try_build(&AES128_KEY, &IV).unwrap();
+        let mut first: [u8; 16] = PLAINTEXT[..16].try_into().unwrap();
+        let mut second: [u8; 16] = PLAINTEXT[16..].try_into().unwrap();
+
+        encryptor.encrypt(&mut first).unwrap();
+        encryptor.encrypt(&mut second).unwrap();
+
+        assert_eq!(first, AES128_CIPHERTEXT[..16]);
+        assert_eq!(second, AES128_CIPHERTEXT[16..]);
+
+        decryptor.decrypt(&mut first).unwrap();
+        decryptor.decrypt(&mut second).unwrap();
+
+        assert_eq!(first, PLAINTEXT[..16]);
+        assert_eq!(second, PLAINTEXT[16..]);
+    }
+
+    #[test]
+    fn test_incorrect_buffer_length() {
+        let mut cbc = TripleDesCbc::try_build(&[1u8; 24], &[0u8; 8]).unwrap();
+        let mut buffer = [42u8; 12];
+
+        let err = cbc.encrypt(&mut buffer).unwrap_err().to_string();
+
+        assert_eq!(
+            err,
+            "block size has incorrect length: (buffer length has to be a multiple of the cipher block)"
+        );
+    }
+
+    #[test]
+    fn test_incorrect_key_and_iv_length() {
+        let key_err = Aes256Cbc::try_build(&[0u8; 16], &IV)
+            .err()
+            .unwrap()
+            .to_string();
+        let iv_err = TripleDesCbc::try_build(&[0u8; 24], &IV)
+            .err()
+            .unwrap()
+            .to_string();
+
+        assert_eq!(
+            key_err,
+            "key has incorrect length: (key length does not match the cipher)"
+        );
+        assert_eq!(
+            iv_err,
+            "iv has incorrect length: (iv has to be as long as the cipher block)"
+        );
+    }
+}
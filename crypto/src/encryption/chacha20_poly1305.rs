@@ -0,0 +1,302 @@
+use super::errors::{CipherInitError, DecryptionError, EncryptionError};
+use super::AeadCipher;
+use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use chacha20::ChaCha20;
+use poly1305::universal_hash::KeyInit;
+use poly1305::Poly1305;
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+const BLOCK_SIZE: usize = 8;
+const KEY_SIZE: usize = 32;
+const PACKET_LENGTH_SIZE: usize = 4;
+pub const TAG_SIZE: usize = 16;
+
+// chacha20-poly1305@openssh.com as described in OpenSSH PROTOCOL.chacha20poly1305.
+//
+// The 64 bytes of key material are split into two keys: the first half (K_2)
+// encrypts the packet body and keys Poly1305, the second half (K_1) encrypts
+// only the 4 byte packet length. Both instances use the packet sequence number
+// as the nonce. OpenSSH uses the original 64-bit nonce ChaCha20, which is the
+// same as the IETF variant with the nonce prefixed by four zero bytes.
+#[derive(ZeroizeOnDrop)]
+pub struct ChaCha20Poly1305 {
+    main_key: [u8; KEY_SIZE],
+    header_key: [u8; KEY_SIZE],
+}
+
+impl ChaCha20Poly1305 {
+    pub fn try_build(key: &[u8]) -> Result<Self, CipherInitError> {
+        if key.len() != 2 * KEY_SIZE {
+            return Err(CipherInitError::IncorrectKeyLength(
+                "key has to have 64 bytes",
+            ));
+        }
+
+        let mut main_key = [0u8; KEY_SIZE];
+        let mut header_key = [0u8; KEY_SIZE];
+        main_key.copy_from_slice(&key[..KEY_SIZE]);
+        header_key.copy_from_slice(&key[KEY_SIZE..]);
+
+        Ok(Self {
+            main_key,
+            header_key,
+        })
+    }
+
+    fn stream(key: &[u8; KEY_SIZE], sequence_number: u32) -> ChaCha20 {
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(&(sequence_number as u64).to_be_bytes());
+
+        ChaCha20::new(key.into(), &nonce.into())
+    }
+
+    // The Poly1305 key is the first 32 bytes of the K_2 keystream (block 0),
+    // the packet body is encrypted starting from block 1.
+    fn body_cipher(&self, sequence_number: u32) -> (ChaCha20, Poly1305) {
+        let mut cipher = Self::stream(&self.main_key, sequence_number);
+
+        let mut poly_key = poly1305::Key::default();
+        cipher.apply_keystream(&mut poly_key);
+        cipher.seek(64);
+
+        let mac = Poly1305::new(&poly_key);
+        poly_key.as_mut_slice().zeroize();
+
+        (cipher, mac)
+    }
+}
+
+impl AeadCipher for ChaCha20Poly1305 {
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn key_len(&self) -> usize {
+        2 * KEY_SIZE
+    }
+
+    fn iv_len(&self) -> usize {
+        0
+    }
+
+    fn tag_len(&self) -> usize {
+        TAG_SIZE
+    }
+
+    // Decrypt only the packet length, so the reader knows how many bytes
+    // belong to the rest of the packet. The length is still authenticated
+    // later as a part of `open`.
+    fn decrypt_length(
+        &self,
+        sequence_number: u32,
+        length: &[u8],
+    ) -> Result<[u8; PACKET_LENGTH_SIZE], DecryptionError> {
+        let mut decrypted: [u8; PACKET_LENGTH_SIZE] = length.try_into().map_err(|_| {
+            DecryptionError::IncorrectBlockSize("packet length has to have 4 bytes")
+        })?;
+
+        Self::stream(&self.header_key, sequence_number).apply_keystream(&mut decrypted);
+
+        Ok(decrypted)
+    }
+
+    // Encrypt the whole packet in place, starting with the 4 byte length,
+    // and return the Poly1305 tag computed over the ciphertext.
+    fn seal(
+        &mut self,
+        sequence_number: u32,
+        packet: &mut [u8],
+    ) -> Result<Vec<u8>, EncryptionError> {
+        if packet.len() < PACKET_LENGTH_SIZE {
+            return Err(EncryptionError::IncorrectBlockSize(
+                "packet is shorter than its length field",
+            ));
+        }
+
+        let (length, body) = packet.split_at_mut(PACKET_LENGTH_SIZE);
+        Self::stream(&self.header_key, sequence_number).apply_keystream(length);
+
+        let (mut cipher, mac) = self.body_cipher(sequence_number);
+        cipher.apply_keystream(body);
+
+        Ok(mac.compute_unpadded(packet).to_vec())
+    }
+
+    // Verify the tag over the still encrypted packet (including the length)
+    // and only then decrypt it in place.
+    fn open(
+        &mut self,
+        sequence_number: u32,
+        packet: &mut [u8],
+        tag: &[u8],
+    ) -> Result<(), DecryptionError> {
+        if packet.len() < PACKET_LENGTH_SIZE {
+            return Err(DecryptionError::IncorrectBlockSize(
+                "packet is shorter than its length field",
+            ));
+        }
+
+        let (mut cipher, mac) = self.body_cipher(sequence_number);
+        let expected_tag = mac.compute_unpadded(packet);
+
+        if !bool::from(expected_tag.as_slice().ct_eq(tag)) {
+            return Err(DecryptionError::AuthenticationFailed);
+        }
+
+        let (length, body) = packet.split_at_mut(PACKET_LENGTH_SIZE);
+        Self::stream(&self.header_key, sequence_number).apply_keystream(length);
+        cipher.apply_keystream(body);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Known answer vector for sequence number 7 and the key 0x00..0x3f.
+    // PROTOCOL.chacha20poly1305 has no test vectors, this one was computed
+    // with the ChaCha20 and Poly1305 primitives of the Python cryptography
+    // package (OpenSSL), following the key split of the document.
+    const SEQUENCE_NUMBER: u32 = 7;
+    const PLAINTEXT: [u8; 20] = [
+        0x00, 0x00, 0x00, 0x10, 0x05, 0x14, 0x70, 0x61, 0x72, 0x75, 0x73, 0x74, 0x69, 0x6b, 0x6f,
+        0xaa, 0xaa, 0xaa, 0xaa, 0xaa,
+    ];
+    const CIPHERTEXT: [u8; 20] = [
+        0xa3, 0x9a, 0xfc, 0xba, 0x2d, 0x52, 0x65, 0x22, 0x3c, 0xf6, 0x59, 0x2a, 0x05, 0x06, 0xd4,
+        0x5a, 0x7d, 0x25, 0x79, 0x86,
+    ];
+    const TAG: [u8; 16] = [
+        0x83, 0x11, 0x22, 0x44, 0x06, 0xa7, 0x44, 0xaf, 0xe7, 0xb6, 0xe5, 0x07, 0x8b, 0x16, 0xae,
+        0x84,
+    ];
+
+    fn key() -> Vec<u8> {
+        (0..64).collect()
+    }
+
+    #[test]
+    fn test_encrypt_packet() {
+        let mut cipher = ChaCha20Poly1305::try_build(&key()).unwrap();
+        let mut packet = PLAINTEXT;
+
+        let tag = cipher.seal(SEQUENCE_NUMBER, &mut packet).unwrap();
+
+        assert_eq!(packet, CIPHERTEXT);
+        assert_eq!(tag, TAG);
+    }
+
+    #[test]
+    fn test_decrypt_length() {
+        let cipher = ChaCha20Poly1305::try_build(&key()).unwrap();
+
+        let length = cipher
+            .decrypt_length(SEQUENCE_NUMBER, &CIPHERTEXT[..4])
+            .unwrap();
+
+        assert_eq!(length, [0, 0, 0, 0x10]);
+    }
+
+    #[test]
+    fn test_length_keystream_matches_rfc_8439() {
+        // RFC 8439, A.1 test vector #1: all zero key, nonce and counter.
+        let cipher = ChaCha20Poly1305::try_build(&[0u8; 64]).unwrap();
+
+        let length = cipher.decrypt_length(0, &[0u8; 4]).unwrap();
+
+        assert_eq!(length, [0x76, 0xb8, 0xe0, 0xad]);
+    }
+
+    #[test]
+    fn test_body_keystream_matches_rfc_8439() {
+        // RFC 8439, A.1 test vectors #1 and #2: all zero key and nonce, the
+        // length uses block 0 of K_1 and the body starts at block 1 of K_2.
+        let mut cipher = ChaCha20Poly1305::try_build(&[0u8; 64]).unwrap();
+        let mut packet = [0u8; 4 + 64];
+
+        cipher.seal(0, &mut packet).unwrap();
+
+        assert_eq!(packet[..4], [0x76, 0xb8, 0xe0, 0xad]);
+        assert_eq!(
+            packet[4..],
+            [
+                0x9f, 0x07, 0xe7, 0xbe, 0x55, 0x51, 0x38, 0x7a, 0x98, 0xba, 0x97, 0x7c, 0x73, 0x2d,
+                0x08, 0x0d, 0xcb, 0x0f, 0x29, 0xa0, 0x48, 0xe3, 0x65, 0x69, 0x12, 0xc6, 0x53, 0x3e,
+                0x32, 0xee, 0x7a, 0xed, 0x29, 0xb7, 0x21, 0x76, 0x9c, 0xe6, 0x4e, 0x43, 0xd5, 0x71,
+                0x33, 0xb0, 0x74, 0xd8, 0x39, 0xd5, 0x31, 0xed, 0x1f, 0x28, 0x51, 0x0a, 0xfb, 0x45,
+                0xac, 0xe1, 0x0a, 0x1f, 0x4b, 0x79, 0x4d, 0x6f,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_poly1305_key_matches_rfc_8439() {
+        // RFC 8439, A.4 test vector #2: K_2 ending with 0x01 and the nonce
+        // 0x02, which is sequence number 2 in the OpenSSH nonce layout.
+        let mut key = [0u8; 64];
+        key[31] = 1;
+        let one_time_key = [
+            0xec, 0xfa, 0x25, 0x4f, 0x84, 0x5f, 0x64, 0x74, 0x73, 0xd3, 0xcb, 0x14, 0x0d, 0xa9,
+            0xe8, 0x76, 0x06, 0xcb, 0x33, 0x06, 0x6c, 0x44, 0x7b, 0x87, 0xbc, 0x26, 0x66, 0xdd,
+            0xe3, 0xfb, 0xb7, 0x39,
+        ];
+        let mut cipher = ChaCha20Poly1305::try_build(&key).unwrap();
+        let mut packet = PLAINTEXT;
+
+        let tag = cipher.seal(2, &mut packet).unwrap();
+
+        let expected = Poly1305::new(&one_time_key.into()).compute_unpadded(&packet);
+        assert_eq!(tag, expected.as_slice());
+    }
+
+    #[test]
+    fn test_decrypt_packet() {
+        let mut cipher = ChaCha20Poly1305::try_build(&key()).unwrap();
+        let mut packet = CIPHERTEXT;
+
+        cipher.open(SEQUENCE_NUMBER, &mut packet, &TAG).unwrap();
+
+        assert_eq!(packet, PLAINTEXT);
+    }
+
+    #[test]
+    fn test_decrypt_packet_with_wrong_sequence_number() {
+        let mut cipher = ChaCha20Poly1305::try_build(&key()).unwrap();
+        let mut packet = CIPHERTEXT;
+
+        let err = cipher
+            .open(SEQUENCE_NUMBER + 1, &mut packet, &TAG)
+            .unwrap_err()
+            .to_string();
+
+        assert_eq!(err, "authentication tag mismatch");
+        assert_eq!(packet, CIPHERTEXT);
+    }
+
+    #[test]
+    fn test_decrypt_tampered_packet() {
+        let mut cipher = ChaCha20Poly1305::try_build(&key()).unwrap();
+        let mut packet = CIPHERTEXT;
+        packet[10] ^= 1;
+
+        let err = cipher
+            .open(SEQUENCE_NUMBER, &mut packet, &TAG)
+            .unwrap_err()
+            .to_string();
+
+        assert_eq!(err, "authentication tag mismatch");
+    }
+
+    #[test]
+    fn test_incorrect_key_length() {
+        let err = ChaCha20Poly1305::try_build(&[0u8; 32])
+            .err()
+            .unwrap()
+            .to_string();
+
+        assert_eq!(err, "key has incorrect length: (key has to have 64 bytes)");
+    }
+}
@@ -0,0 +1,226 @@
+use super::errors::{CipherInitError, DecryptionError, EncryptionError};
+use super::Cipher;
+use aes::cipher::consts::U16;
+use aes::cipher::{
+    BlockCipher, BlockEncryptMut, BlockSizeUser, KeyIvInit, KeySizeUser, StreamCipher,
+};
+use aes::{Aes128, Aes192, Aes256};
+
+const AES_BLOCK_SIZE: usize = 16;
+
+// RFC 4344 treats the whole IV as a single 128-bit big endian counter,
+// which is incremented once per encrypted block. The keystream position
+// is kept between calls, so consecutive packets continue the same stream.
+pub struct AesCtr<C: BlockEncryptMut + BlockCipher + BlockSizeUser<BlockSize = U16>> {
+    cipher: ctr::Ctr128BE<C>,
+}
+
+pub type Aes128Ctr = AesCtr<Aes128>;
+pub type Aes192Ctr = AesCtr<Aes192>;
+pub type Aes256Ctr = AesCtr<Aes256>;
+
+impl<C> AesCtr<C>
+where
+    C: BlockEncryptMut + BlockCipher + BlockSizeUser<BlockSize = U16>,
+    ctr::Ctr128BE<C>: KeyIvInit + StreamCipher,
+{
+    pub fn try_build(key: &[u8], iv: &[u8]) -> Result<Self, CipherInitError> {
+        if iv.len() != AES_BLOCK_SIZE {
+            return Err(CipherInitError::IncorrectIvLength(
+                "iv has to have 16 bytes",
+            ));
+        }
+
+        let cipher = ctr::Ctr128BE::<C>::new_from_slices(key, iv).map_err(|_| {
+            CipherInitError::IncorrectKeyLength("key length does not match the AES variant")
+        })?;
+
+        Ok(Self { cipher })
+    }
+
+    fn apply_keystream(&mut self, buffer: &mut [u8]) -> bool {
+        if !buffer.len().is_multiple_of(AES_BLOCK_SIZE) {
+            return false;
+        }
+
+        self.cipher.apply_keystream(buffer);
+        true
+    }
+}
+
+impl<C> Cipher for AesCtr<C>
+where
+    C: BlockEncryptMut + BlockCipher + BlockSizeUser<BlockSize = U16>,
+    ctr::Ctr128BE<C>: KeyIvInit + StreamCipher,
+{
+    fn block_size(&self) -> usize {
+        AES_BLOCK_SIZE
+    }
+
+    fn key_len(&self) -> usize {
+        <ctr::Ctr128BE<C> as KeySizeUser>::key_size()
+    }
+
+    fn iv_len(&self) -> usize {
+        AES_BLOCK_SIZE
+    }
+
+    fn encrypt(&mut self, block: &mut [u8]) -> Result<(), EncryptionError> {
+        if !self.apply_keystream(block) {
+            return Err(EncryptionError::IncorrectBlockSize(
+                "buffer length has to be a multiple of 16 bytes",
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn decrypt(&mut self, block: &mut [u8]) -> Result<(), DecryptionError> {
+        if !self.apply_keystream(block) {
+            return Err(DecryptionError::IncorrectBlockSize(
+                "buffer length has to be a multiple of 16 bytes",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // NIST SP 800-38A, F.5 test vectors.
+    const IV: [u8; 16] = [
+        0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa, 0xfb, 0xfc, 0xfd, 0xfe,
+        0xff,
+    ];
+    const PLAINTEXT: [u8; 32] = [
+        0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17,
+        0x2a, 0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c, 0x9e, 0xb7, 0x6f, 0xac, 0x45, 0xaf,
+        0x8e, 0x51,
+    ];
+    const AES128_KEY: [u8; 16] = [
+        0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f,
+        0x3c,
+    ];
+    const AES128_CIPHERTEXT: [u8; 32] = [
+        0x87, 0x4d, 0x61, 0x91, 0xb6, 0x20, 0xe3, 0x26, 0x1b, 0xef, 0x68, 0x64, 0x99, 0x0d, 0xb6,
+        0xce, 0x98, 0x06, 0xf6, 0x6b, 0x79, 0x70, 0xfd, 0xff, 0x86, 0x17, 0x18, 0x7b, 0xb9, 0xff,
+        0xfd, 0xff,
+    ];
+
+    #[test]
+    fn test_aes128_ctr_encryption() {
+        let mut ctr = Aes128Ctr::try_build(&AES128_KEY, &IV).unwrap();
+        let mut buffer = PLAINTEXT;
+
+        ctr.encrypt(&mut buffer).unwrap();
+
+        assert_eq!(buffer, AES128_CIPHERTEXT);
+    }
+
+    #[test]
+    fn test_aes192_ctr_encryption() {
+        let key = [
+            0x8e, 0x73, 0xb0, 0xf7, 0xda, 0x0e, 0x64, 0x52, 0xc8, 0x10, 0xf3, 0x2b, 0x80, 0x90,
+            0x79, 0xe5, 0x62, 0xf8, 0xea, 0xd2, 0x52, 0x2c, 0x6b, 0x7b,
+        ];
+        let expected = [
+            0x1a, 0xbc, 0x93, 0x24, 0x17, 0x52, 0x1c, 0xa2, 0x4f, 0x2b, 0x04, 0x59, 0xfe, 0x7e,
+            0x6e, 0x0b,
+        ];
+        let mut ctr = Aes192Ctr::try_build(&key, &IV).unwrap();
+        let mut buffer: [u8; 16] = PLAINTEXT[..16].try_into().unwrap();
+
+        ctr.encrypt(&mut buffer).unwrap();
+
+        assert_eq!(buffer, expected);
+    }
+
+    #[test]
+    fn test_aes256_ctr_encryption() {
+        let key = [
+            0x60, 0x3d, 0xeb, 0x10, 0x15, 0xca, 0x71, 0xbe, 0x2b, 0x73, 0xae, 0xf0, 0x85, 0x7d,
+            0x77, 0x81, 0x1f, 0x35, 0x2c, 0x07, 0x3b, 0x61, 0x08, 0xd7, 0x2d, 0x98, 0x10, 0xa3,
+            0x09, 0x14, 0xdf, 0xf4,
+        ];
+        let expected = [
+            0x60, 0x1e, 0xc3, 0x13, 0x77, 0x57, 0x89, 0xa5, 0xb7, 0xa7, 0xf5, 0x04, 0xbb, 0xf3,
+            0xd2, 0x28,
+        ];
+        let mut ctr = Aes256Ctr::try_build(&key, &IV).unwrap();
+        let mut buffer: [u8; 16] = PLAINTEXT[..16].try_into().unwrap();
+
+        ctr.encrypt(&mut buffer).unwrap();
+
+        assert_eq!(buffer, expected);
+    }
+
+    #[test]
+    fn test_counter_state_is_kept_between_calls() {
+        let mut ctr = Aes128Ctr::try_build(&AES128_KEY, &IV).unwrap();
+        let mut first = [0u8; 16];
+        let mut second = [0u8; 16];
+        first.copy_from_slice(&PLAINTEXT[..16]);
+        second.copy_from_slice(&PLAINTEXT[16..]);
+
+        ctr.encrypt(&mut first).unwrap();
+        ctr.encrypt(&mut second).unwrap();
+
+        assert_eq!(first, AES128_CIPHERTEXT[..16]);
+        assert_eq!(second, AES128_CIPHERTEXT[16..]);
+    }
+
+    #[test]
+    fn test_decryption_restores_plaintext() {
+        let mut encryptor = Aes128Ctr::try_build(&AES128_KEY, &IV).unwrap();
+        let mut decryptor = Aes128Ctr::try_build(&AES128_KEY, &IV).unwrap();
+        let mut buffer = PLAINTEXT;
+
+        encryptor.encrypt(&mut buffer).unwrap();
+        decryptor.decrypt(&mut buffer).unwrap();
+
+        assert_eq!(buffer, PLAINTEXT);
+    }
+
+    #[test]
+    fn test_key_and_iv_lengths() {
+        let ctr = Aes256Ctr::try_build(&[0u8; 32], &IV).unwrap();
+
+        assert_eq!(ctr.key_len(), 32);
+        assert_eq!(ctr.iv_len(), 16);
+        assert_eq!(ctr.block_size(), 16);
+    }
+
+    #[test]
+    fn test_incorrect_buffer_length() {
+        let mut ctr = Aes128Ctr::try_build(&AES128_KEY, &IV).unwrap();
+        let mut buffer = [42u8; 17];
+
+        let err = ctr.encrypt(&mut buffer).unwrap_err().to_string();
+
+        assert_eq!(
+            err,
+            "block size has incorrect length: (buffer length has to be a multiple of 16 bytes)"
+        );
+    }
+
+    #[test]
+    fn test_incorrect_key_and_iv_length() {
+        let key_err = Aes256Ctr::try_build(&AES128_KEY, &IV)
+            .err()
+            .unwrap()
+            .to_string();
+        let iv_err = Aes128Ctr::try_build(&AES128_KEY, &IV[..8])
+            .err()
+            .unwrap()
+            .to_string();
+
+        assert_eq!(
+            key_err,
+            "key has incorrect length: (key length does not match the AES variant)"
+        );
+        assert_eq!(iv_err, "iv has incorrect length: (iv has to have 16 bytes)");
+    }
+}
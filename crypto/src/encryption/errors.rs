@@ -10,4 +10,19 @@ pub enum EncryptionError {
 pub enum DecryptionError {
     #[error("block size has incorrect length: ({0})")]
     IncorrectBlockSize(&'static str),
+
+    #[error("authentication tag mismatch")]
+    AuthenticationFailed,
+}
+
+#[derive(Debug, Error)]
+pub enum CipherInitError {
+    #[error("key has incorrect length: ({0})")]
+    IncorrectKeyLength(&'static str),
+
+    #[error("iv has incorrect length: ({0})")]
+    IncorrectIvLength(&'static str),
+
+    #[error("unsupported cipher: ({0})")]
+    UnsupportedCipher(String),
 }
@@ -0,0 +1,293 @@
+use super::errors::{CipherInitError, DecryptionError, EncryptionError};
+use super::AeadCipher;
+use aes_gcm::aead::{AeadInPlace, KeyInit, KeySizeUser};
+use aes_gcm::{Aes128Gcm, Aes256Gcm, Nonce, Tag};
+use zeroize::Zeroize;
+
+const AES_BLOCK_SIZE: usize = 16;
+const FIXED_FIELD_SIZE: usize = 4;
+const PACKET_LENGTH_SIZE: usize = 4;
+pub const IV_SIZE: usize = 12;
+pub const TAG_SIZE: usize = 16;
+
+// AES-GCM for the SSH transport layer as described in RFC 5647 and used by
+// aes128-gcm@openssh.com and aes256-gcm@openssh.com.
+//
+// The 12 byte IV is split into a 4 byte fixed field and an 8 byte invocation
+// counter, which is incremented after every packet. The packet length is sent
+// in the clear and authenticated as the associated data.
+pub struct AesGcm<C: KeyInit + AeadInPlace> {
+    cipher: C,
+    fixed: [u8; FIXED_FIELD_SIZE],
+    invocation_counter: u64,
+}
+
+pub type Aes128GcmCipher = AesGcm<Aes128Gcm>;
+pub type Aes256GcmCipher = AesGcm<Aes256Gcm>;
+
+impl<C: KeyInit + AeadInPlace> AesGcm<C> {
+    pub fn try_build(key: &[u8], iv: &[u8]) -> Result<Self, CipherInitError> {
+        if iv.len() != IV_SIZE {
+            return Err(CipherInitError::IncorrectIvLength(
+                "iv has to have 12 bytes",
+            ));
+        }
+
+        let cipher = C::new_from_slice(key).map_err(|_| {
+            CipherInitError::IncorrectKeyLength("key length does not match the AES variant")
+        })?;
+
+        let mut fixed = [0u8; FIXED_FIELD_SIZE];
+        fixed.copy_from_slice(&iv[..FIXED_FIELD_SIZE]);
+
+        let invocation_counter = u64::from_be_bytes(
+            iv[FIXED_FIELD_SIZE..]
+                .try_into()
+                .expect("Should not be reachable"),
+        );
+
+        Ok(Self {
+            cipher,
+            fixed,
+            invocation_counter,
+        })
+    }
+
+    fn nonce(&self) -> [u8; IV_SIZE] {
+        let mut nonce = [0u8; IV_SIZE];
+        nonce[..FIXED_FIELD_SIZE].copy_from_slice(&self.fixed);
+        nonce[FIXED_FIELD_SIZE..].copy_from_slice(&self.invocation_counter.to_be_bytes());
+
+        nonce
+    }
+}
+
+// The cipher wipes its own key schedule, the IV is derived from the shared
+// secret as well.
+impl<C: KeyInit + AeadInPlace> Drop for AesGcm<C> {
+    fn drop(&mut self) {
+        self.fixed.zeroize();
+        self.invocation_counter.zeroize();
+    }
+}
+
+impl<C: KeyInit + AeadInPlace> AeadCipher for AesGcm<C> {
+    fn block_size(&self) -> usize {
+        AES_BLOCK_SIZE
+    }
+
+    fn key_len(&self) -> usize {
+        <C as KeySizeUser>::key_size()
+    }
+
+    fn iv_len(&self) -> usize {
+        IV_SIZE
+    }
+
+    fn tag_len(&self) -> usize {
+        TAG_SIZE
+    }
+
+    // The packet length is not encrypted, it is only authenticated.
+    fn decrypt_length(
+        &self,
+        _sequence_number: u32,
+        length: &[u8],
+    ) -> Result<[u8; PACKET_LENGTH_SIZE], DecryptionError> {
+        length
+            .try_into()
+            .map_err(|_| DecryptionError::IncorrectBlockSize("packet length has to have 4 bytes"))
+    }
+
+    fn seal(
+        &mut self,
+        _sequence_number: u32,
+        packet: &mut [u8],
+    ) -> Result<Vec<u8>, EncryptionError> {
+        if packet.len() < PACKET_LENGTH_SIZE
+            || !(packet.len() - PACKET_LENGTH_SIZE).is_multiple_of(AES_BLOCK_SIZE)
+        {
+            return Err(EncryptionError::IncorrectBlockSize(
+                "encrypted part of the packet has to be a multiple of 16 bytes",
+            ));
+        }
+
+        let nonce = self.nonce();
+        let (length, body) = packet.split_at_mut(PACKET_LENGTH_SIZE);
+        let tag = self
+            .cipher
+            .encrypt_in_place_detached(Nonce::from_slice(&nonce), length, body)
+            .map_err(|_| EncryptionError::IncorrectBlockSize("packet is too long for AES-GCM"))?;
+
+        self.invocation_counter = self.invocation_counter.wrapping_add(1);
+
+        Ok(tag.to_vec())
+    }
+
+    fn open(
+        &mut self,
+        _sequence_number: u32,
+        packet: &mut [u8],
+        tag: &[u8],
+    ) -> Result<(), DecryptionError> {
+        if packet.len() < PACKET_LENGTH_SIZE
+            || !(packet.len() - PACKET_LENGTH_SIZE).is_multiple_of(AES_BLOCK_SIZE)
+        {
+            return Err(DecryptionError::IncorrectBlockSize(
+                "encrypted part of the packet has to be a multiple of 16 bytes",
+            ));
+        }
+
+        if tag.len() != TAG_SIZE {
+            return Err(DecryptionError::AuthenticationFailed);
+        }
+
+        let nonce = self.nonce();
+        let (length, body) = packet.split_at_mut(PACKET_LENGTH_SIZE);
+        self.cipher
+            .decrypt_in_place_detached(
+                Nonce::from_slice(&nonce),
+                length,
+                body,
+                Tag::from_slice(tag),
+            )
+            .map_err(|_| DecryptionError::AuthenticationFailed)?;
+
+        self.invocation_counter = self.invocation_counter.wrapping_add(1);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Known answer vectors cross-checked against an independent AES-GCM
+    // implementation, with the key 0x00.. and the IV 0xa0..0xab.
+    const PLAINTEXT: [u8; 20] = [
+        0x00, 0x00, 0x00, 0x10, 0x05, 0x14, 0x70, 0x61, 0x72, 0x75, 0x73, 0x74, 0x69, 0x6b, 0x6f,
+        0xaa, 0xaa, 0xaa, 0xaa, 0xaa,
+    ];
+    const AES128_CIPHERTEXT: [[u8; 16]; 2] = [
+        [
+            0xaf, 0x92, 0x48, 0xda, 0x0c, 0xfc, 0x40, 0x7e, 0xe3, 0x13, 0xda, 0xaa, 0xec, 0xb8,
+            0x1a, 0xca,
+        ],
+        [
+            0xf9, 0x9d, 0x31, 0x24, 0x1e, 0x94, 0x5f, 0x94, 0x95, 0xdc, 0x17, 0x1a, 0xc6, 0x43,
+            0x2d, 0x52,
+        ],
+    ];
+    const AES128_TAGS: [[u8; 16]; 2] = [
+        [
+            0x55, 0xa4, 0x27, 0x6d, 0x40, 0x7d, 0x6b, 0x6a, 0x67, 0x0e, 0xf5, 0xf9, 0xed, 0x96,
+            0x2b, 0x71,
+        ],
+        [
+            0xe1, 0xa7, 0xa5, 0xd7, 0x0b, 0x17, 0x01, 0x77, 0x4d, 0x4e, 0x56, 0x98, 0xf9, 0x1e,
+            0xe8, 0x74,
+        ],
+    ];
+
+    fn iv() -> Vec<u8> {
+        (0xa0..0xac).collect()
+    }
+
+    #[test]
+    fn test_aes128_gcm_invocation_counter_is_incremented() {
+        let key: Vec<u8> = (0..16).collect();
+        let mut gcm = Aes128GcmCipher::try_build(&key, &iv()).unwrap();
+
+        for (expected, expected_tag) in AES128_CIPHERTEXT.iter().zip(AES128_TAGS.iter()) {
+            let mut packet = PLAINTEXT;
+            let tag = gcm.seal(0, &mut packet).unwrap();
+
+            assert_eq!(packet[..4], PLAINTEXT[..4]);
+            assert_eq!(packet[4..], expected[..]);
+            assert_eq!(tag, expected_tag);
+        }
+    }
+
+    #[test]
+    fn test_aes256_gcm_encryption() {
+        let key: Vec<u8> = (0..32).collect();
+        let expected = [
+            0xe3, 0x0c, 0x0c, 0x4c, 0x37, 0xbe, 0x71, 0xcb, 0x0b, 0x0e, 0xe8, 0x79, 0xad, 0xd0,
+            0x6a, 0x74,
+        ];
+        let expected_tag = [
+            0xca, 0xbd, 0xc4, 0xa9, 0xcc, 0xb4, 0x69, 0x35, 0x17, 0x02, 0x0a, 0xee, 0x7d, 0x72,
+            0x76, 0x28,
+        ];
+        let mut gcm = Aes256GcmCipher::try_build(&key, &iv()).unwrap();
+        let mut packet = PLAINTEXT;
+
+        let tag = gcm.seal(0, &mut packet).unwrap();
+
+        assert_eq!(packet[4..], expected);
+        assert_eq!(tag, expected_tag);
+    }
+
+    #[test]
+    fn test_aes128_gcm_decryption() {
+        let key: Vec<u8> = (0..16).collect();
+        let mut gcm = Aes128GcmCipher::try_build(&key, &iv()).unwrap();
+        let mut packet = PLAINTEXT;
+        packet[4..].copy_from_slice(&AES128_CIPHERTEXT[0]);
+
+        let length = gcm.decrypt_length(0, &packet[..4]).unwrap();
+        gcm.open(0, &mut packet, &AES128_TAGS[0]).unwrap();
+
+        assert_eq!(length, [0, 0, 0, 0x10]);
+        assert_eq!(packet, PLAINTEXT);
+    }
+
+    #[test]
+    fn test_tampered_length_is_rejected() {
+        let key: Vec<u8> = (0..16).collect();
+        let mut gcm = Aes128GcmCipher::try_build(&key, &iv()).unwrap();
+        let mut packet = PLAINTEXT;
+        packet[4..].copy_from_slice(&AES128_CIPHERTEXT[0]);
+        packet[3] = 0x20;
+
+        let err = gcm
+            .open(0, &mut packet, &AES128_TAGS[0])
+            .unwrap_err()
+            .to_string();
+
+        assert_eq!(err, "authentication tag mismatch");
+    }
+
+    #[test]
+    fn test_unaligned_packet_is_rejected() {
+        let key: Vec<u8> = (0..16).collect();
+        let mut gcm = Aes128GcmCipher::try_build(&key, &iv()).unwrap();
+        let mut packet = [0u8; 19];
+
+        let err = gcm.seal(0, &mut packet).unwrap_err().to_string();
+
+        assert_eq!(
+            err,
+            "block size has incorrect length: (encrypted part of the packet has to be a multiple of 16 bytes)"
+        );
+    }
+
+    #[test]
+    fn test_incorrect_key_and_iv_length() {
+        let key_err = Aes256GcmCipher::try_build(&[0u8; 16], &iv())
+            .err()
+            .unwrap()
+            .to_string();
+        let iv_err = Aes128GcmCipher::try_build(&[0u8; 16], &[0u8; 16])
+            .err()
+            .unwrap()
+            .to_string();
+
+        assert_eq!(
+            key_err,
+            "key has incorrect length: (key length does not match the AES variant)"
+        );
+        assert_eq!(iv_err, "iv has incorrect length: (iv has to have 12 bytes)");
+    }
+}
@@ -1,11 +1,65 @@
 pub mod aes;
+#[cfg(feature = "legacy-ciphers")]
+pub mod cbc;
+pub mod chacha20_poly1305;
+pub mod ctr;
 pub mod errors;
+pub mod gcm;
 
-pub enum EncryptionType {
-    AES(aes::AES),
+// Stateful stream or block cipher used together with a separate MAC. The
+// state (CTR counter, CBC chaining IV) is kept between calls, so the whole
+// direction of a connection has to be processed by the same instance.
+pub trait Cipher {
+    fn block_size(&self) -> usize;
+    fn key_len(&self) -> usize;
+    fn iv_len(&self) -> usize;
+
+    // Encrypt the buffer in place, its length has to be a multiple of
+    // the block size.
+    fn encrypt(&mut self, buffer: &mut [u8]) -> Result<(), errors::EncryptionError>;
+
+    // Decrypt the buffer in place, its length has to be a multiple of
+    // the block size.
+    fn decrypt(&mut self, buffer: &mut [u8]) -> Result<(), errors::DecryptionError>;
+}
+
+// Authenticated encryption, which replaces both the cipher and the MAC of
+// the transport layer. The packet passed to `seal` and `open` always starts
+// with the 4 byte packet length, it is up to the implementation whether
+// the length is encrypted or only authenticated.
+pub trait AeadCipher {
+    fn block_size(&self) -> usize;
+    fn key_len(&self) -> usize;
+    fn iv_len(&self) -> usize;
+    fn tag_len(&self) -> usize;
+
+    // Recover the packet length from the first 4 bytes read from the wire,
+    // before the rest of the packet is available.
+    fn decrypt_length(
+        &self,
+        sequence_number: u32,
+        length: &[u8],
+    ) -> Result<[u8; 4], errors::DecryptionError>;
+
+    // Encrypt the packet in place and return the authentication tag.
+    fn seal(
+        &mut self,
+        sequence_number: u32,
+        packet: &mut [u8],
+    ) -> Result<Vec<u8>, errors::EncryptionError>;
+
+    // Verify the tag and decrypt the packet in place.
+    fn open(
+        &mut self,
+        sequence_number: u32,
+        packet: &mut [u8],
+        tag: &[u8],
+    ) -> Result<(), errors::DecryptionError>;
 }
 
-pub trait Encryption {
-    fn encrypt(&self, block: &mut [u8]) -> Result<(), errors::EncryptionError>;
-    fn decrypt(&self, block: &mut [u8]) -> Result<(), errors::DecryptionError>;
+// Cipher built from the negotiated algorithm name. AEAD ciphers do not
+// use a separately negotiated MAC.
+pub enum CipherMode {
+    Cipher(Box<dyn Cipher>),
+    Aead(Box<dyn AeadCipher>),
 }
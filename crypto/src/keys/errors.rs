@@ -0,0 +1,30 @@
+use crate::encoding::errors::DecodingError;
+use crate::signature::errors::KeyError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum KeyFileError {
+    #[error("key file has invalid format: ({0})")]
+    InvalidFormat(&'static str),
+
+    #[error("unsupported key file cipher: ({0})")]
+    UnsupportedCipher(String),
+
+    #[error("unsupported key file kdf: ({0})")]
+    UnsupportedKdf(String),
+
+    #[error("bcrypt kdf rounds are out of range: ({0})")]
+    InvalidKdfRounds(u32),
+
+    #[error("key file is encrypted and no passphrase was given")]
+    PassphraseRequired,
+
+    #[error("incorrect passphrase")]
+    WrongPassphrase,
+
+    #[error("invalid key in key file: ({0})")]
+    InvalidKey(#[from] KeyError),
+
+    #[error("decoding key file failed: ({0})")]
+    DecodingFailed(#[from] DecodingError),
+}
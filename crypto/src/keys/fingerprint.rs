@@ -0,0 +1,259 @@
+use crate::encoding::Reader;
+use crate::signature::ecdsa::EcdsaCurve;
+use crate::signature::errors::KeyError;
+use crate::signature::{ed25519, rsa};
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use base64::Engine;
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+// Size of the randomart field, as drawn by OpenSSH.
+const FIELD_WIDTH: usize = 17;
+const FIELD_HEIGHT: usize = 9;
+// Symbols for the number of times the bishop visited a cell, the last two
+// mark where it started and where it ended.
+const SYMBOLS: &[u8] = b" .o+=*BOX@%&#/^SE";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FingerprintHash {
+    Sha256,
+    Md5,
+}
+
+impl FingerprintHash {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sha256 => "SHA256",
+            Self::Md5 => "MD5",
+        }
+    }
+
+    fn digest(&self, blob: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha256 => Sha256::digest(blob).to_vec(),
+            Self::Md5 => Md5::digest(blob).to_vec(),
+        }
+    }
+}
+
+// Fingerprint of a public key blob as printed by `ssh-keygen -l`: the
+// unpadded base64 digest for SHA256 and the colon separated hex digest for
+// the legacy MD5 format.
+pub fn fingerprint(blob: &[u8], hash: FingerprintHash) -> String {
+    let digest = hash.digest(blob);
+    let text = match hash {
+        FingerprintHash::Sha256 => STANDARD_NO_PAD.encode(digest),
+        FingerprintHash::Md5 => digest
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(":"),
+    };
+
+    format!("{}:{}", hash.name(), text)
+}
+
+// Visual host key as printed by `ssh-keygen -lv`. A bishop starts in the
+// centre of the field and moves diagonally for every two bits of the
+// digest, starting with the least significant ones of each byte.
+pub fn randomart(blob: &[u8], hash: FingerprintHash) -> Result<String, KeyError> {
+    let (name, bits) = key_size(blob)?;
+    let end = SYMBOLS.len() - 1;
+    let mut field = [[0usize; FIELD_WIDTH]; FIELD_HEIGHT];
+    let (mut x, mut y) = (FIELD_WIDTH / 2, FIELD_HEIGHT / 2);
+
+    for byte in hash.digest(blob) {
+        for step in 0..4 {
+            let input = byte >> (2 * step);
+            x = match input & 0x01 {
+                0 => x.saturating_sub(1),
+                _ => (x + 1).min(FIELD_WIDTH - 1),
+            };
+            y = match input & 0x02 {
+                0 => y.saturating_sub(1),
+                _ => (y + 1).min(FIELD_HEIGHT - 1),
+            };
+
+            if field[y][x] < end - 2 {
+                field[y][x] += 1;
+            }
+        }
+    }
+    field[FIELD_HEIGHT / 2][FIELD_WIDTH / 2] = end - 1;
+    field[y][x] = end;
+
+    // Titles which do not fit the border leave out the key size.
+    let mut title = format!("[{} {}]", name, bits);
+    if title.len() > FIELD_WIDTH {
+        title = format!("[{}]", name);
+    }
+
+    let mut art = border(&title);
+    for row in field {
+        art.push('|');
+        art.extend(row.iter().map(|count| SYMBOLS[*count] as char));
+        art.push_str("|\n");
+    }
+    art.push_str(border(&format!("[{}]", hash.name())).trim_end());
+
+    Ok(art)
+}
+
+fn border(label: &str) -> String {
+    let left = FIELD_WIDTH.saturating_sub(label.len()) / 2;
+    let right = FIELD_WIDTH.saturating_sub(left + label.len());
+
+    format!("+{}{}{}+\n", "-".repeat(left), label, "-".repeat(right))
+}
+
+// Key type and size in bits, as shown by `ssh-keygen -l` and in the
+// randomart title.
+pub fn key_size(blob: &[u8]) -> Result<(&'static str, usize), KeyError> {
+    let mut reader = Reader::new(blob);
+    let algorithm = reader.read_utf8()?;
+
+    if let Some(curve) = EcdsaCurve::from_algorithm(algorithm) {
+        let bits = match curve {
+            EcdsaCurve::NistP256 => 256,
+            EcdsaCurve::NistP384 => 384,
+            EcdsaCurve::NistP521 => 521,
+        };
+        return Ok(("ECDSA", bits));
+    }
+
+    match algorithm {
+        ed25519::ALGORITHM => Ok(("ED25519", 256)),
+        rsa::ALGORITHM => {
+            reader.read_mpint()?;
+            let n = reader.read_mpint()?;
+            let bits = match n.first() {
+                Some(first) => n.len() * 8 - first.leading_zeros() as usize,
+                None => return Err(KeyError::InvalidFormat("rsa modulus is empty")),
+            };
+
+            Ok(("RSA", bits))
+        }
+        _ => Err(KeyError::UnsupportedAlgorithm(algorithm.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::engine::general_purpose::STANDARD;
+
+    const ID_RSA_PUB: &str = include_str!("../../../rsa_keys/id_rsa.pub");
+    // Public key of RFC 8032, section 7.1, test 1.
+    const ED25519_PUB: &str =
+        "AAAAC3NzaC1lZDI1NTE5AAAAINdamAGCsQq31Uv+08lkBzoO4XLz2qYjJa8CGmj3B1Ea";
+    const NISTP521_PUB: &str = concat!(
+        "AAAAE2VjZHNhLXNoYTItbmlzdHA1MjEAAAAIbmlzdHA1MjEAAACFBAEH8VrGu2EmrBHN3H",
+        "roQ9Mhxy9e5IhoI4gN1iC/kwVVkLoN2VAr+ZR1MdWYo4fXM1zIX07v3dJFQuTaBreof39B",
+        "SQCCPjPss0vmHnHEx3B0Uar5Ik0LvbpG86j/GvIHpcsSP9k041grCI3chufBLNLQshuDLc",
+        "dEdLZB4Y8apFjdEZVi0g==",
+    );
+
+    fn blob(data: &str) -> Vec<u8> {
+        STANDARD.decode(data).unwrap()
+    }
+
+    fn id_rsa_blob() -> Vec<u8> {
+        blob(ID_RSA_PUB.split_whitespace().nth(1).unwrap())
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(
+            fingerprint(&id_rsa_blob(), FingerprintHash::Sha256),
+            "SHA256:OAwZMJ845MBLpyPLcTEPWekcFxnoT2HalurW9xVy2Gs"
+        );
+        assert_eq!(
+            fingerprint(&id_rsa_blob(), FingerprintHash::Md5),
+            "MD5:fe:80:5c:93:d4:53:67:4a:c9:aa:63:54:79:3c:dc:06"
+        );
+        assert_eq!(
+            fingerprint(&blob(ED25519_PUB), FingerprintHash::Sha256),
+            "SHA256:bbXpuKG6zhzdmnxq256TlqzFBzRl2f6OOg722cYNbU8"
+        );
+        assert_eq!(
+            fingerprint(&blob(ED25519_PUB), FingerprintHash::Md5),
+            "MD5:cf:07:be:9d:68:ae:65:54:6d:a0:93:c3:6f:bd:0d:82"
+        );
+    }
+
+    #[test]
+    fn test_randomart_rsa() {
+        let expected = "\
++---[RSA 3072]----+\n\
+|o +.+.oo+        |\n\
+| * X B =         |\n\
+|. B & * o        |\n\
+|.= o O *    o    |\n\
+|o.+   O S  o +   |\n\
+|..   . o    o o  |\n\
+|    . .      E   |\n\
+|     o . .  o    |\n\
+|    .   . ..     |\n\
++----[SHA256]-----+";
+
+        assert_eq!(
+            randomart(&id_rsa_blob(), FingerprintHash::Sha256).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_randomart_ed25519_md5() {
+        let expected = "\
++--[ED25519 256]--+\n\
+|            .    |\n\
+|         . o o   |\n\
+|          * . o  |\n\
+|           * o   |\n\
+|        S E + o  |\n\
+|         = o . + |\n\
+|          * . . .|\n\
+|         o.= .   |\n\
+|        .++ o    |\n\
++------[MD5]------+";
+
+        assert_eq!(
+            randomart(&blob(ED25519_PUB), FingerprintHash::Md5).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_randomart_ecdsa() {
+        let expected = "\
++---[ECDSA 521]---+\n\
+|     ..    o+**o |\n\
+|     .oo   o+o+. |\n\
+|     .o.o o  +   |\n\
+|    .oo+ . .o    |\n\
+|    ==+.S . ..   |\n\
+|   o.B== . .E    |\n\
+|    ===oo        |\n\
+|   . o=o         |\n\
+|     o+.o        |\n\
++----[SHA256]-----+";
+
+        assert_eq!(
+            randomart(&blob(NISTP521_PUB), FingerprintHash::Sha256).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_randomart_unsupported_key() {
+        let mut blob = blob(ED25519_PUB);
+        blob[14] = b'8';
+
+        assert_eq!(
+            randomart(&blob, FingerprintHash::Sha256)
+                .unwrap_err()
+                .to_string(),
+            "unsupported key algorithm: (ssh-ed25518)"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+pub mod errors;
+pub mod fingerprint;
+pub mod openssh;
+pub mod pem;
+pub mod public;
+
+use crate::secret::SecretBytes;
+use crate::signature::ecdsa::{EcdsaCurve, EcdsaPrivateKey};
+use crate::signature::ed25519::Ed25519PrivateKey;
+use crate::signature::errors::{KeyError, SignatureError};
+use crate::signature::rsa::RsaPrivateKey;
+use crate::signature::{PublicKey, Signature, Signer};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use errors::KeyFileError;
+use rand_core::{CryptoRng, RngCore};
+use zeroize::Zeroizing;
+
+// Modulus size of generated RSA keys, the same as ssh-keygen.
+pub const DEFAULT_RSA_BITS: usize = 3072;
+
+// Type of a key to generate, RSA keys with the modulus size in bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    Ed25519,
+    Ecdsa(EcdsaCurve),
+    Rsa(usize),
+}
+
+// Private key of any supported type, as loaded from or written to a key
+// file.
+#[derive(Debug)]
+pub enum PrivateKey {
+    Ed25519(Ed25519PrivateKey),
+    Ecdsa(EcdsaPrivateKey),
+    Rsa(RsaPrivateKey),
+}
+
+impl PrivateKey {
+    pub fn generate<R>(key_type: KeyType, rng: &mut R) -> Result<Self, KeyError>
+    where
+        R: RngCore + CryptoRng + ?Sized,
+    {
+        Ok(match key_type {
+            KeyType::Ed25519 => Self::Ed25519(Ed25519PrivateKey::generate(rng)),
+            KeyType::Ecdsa(curve) => Self::Ecdsa(EcdsaPrivateKey::generate(curve, rng)),
+            KeyType::Rsa(bits) => Self::Rsa(RsaPrivateKey::generate(bits, rng)?),
+        })
+    }
+
+    // Key type name of the public key blob.
+    pub fn algorithm(&self) -> &'static str {
+        self.public_key().algorithm()
+    }
+
+    fn signer(&self) -> &dyn Signer {
+        match self {
+            Self::Ed25519(key) => key,
+            Self::Ecdsa(key) => key,
+            Self::Rsa(key) => key,
+        }
+    }
+}
+
+impl Signer for PrivateKey {
+    fn public_key(&self) -> Box<dyn PublicKey> {
+        self.signer().public_key()
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<Signature, SignatureError> {
+        self.signer().sign(data)
+    }
+
+    fn signature_algorithm(&self) -> &'static str {
+        self.signer().signature_algorithm()
+    }
+}
+
+// Load a private key from any of the supported file formats, based on its
+// `-----BEGIN <label>-----` line. Only OpenSSH keys carry a comment, it is
+// empty for the others.
+pub fn decode<F>(text: &str, passphrase: F) -> Result<(PrivateKey, String), KeyFileError>
+where
+    F: FnOnce() -> Option<SecretBytes>,
+{
+    match armor_label(text)? {
+        openssh::LABEL => openssh::decode(text, passphrase),
+        _ => Ok((pem::decode(text, passphrase)?, String::new())),
+    }
+}
+
+pub(crate) fn armor_label(text: &str) -> Result<&str, KeyFileError> {
+    text.lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .and_then(|l| l.strip_prefix("-----BEGIN "))
+        .and_then(|l| l.strip_suffix("-----"))
+        .ok_or(KeyFileError::InvalidFormat("begin line is missing"))
+}
+
+// Base64 body between the `-----BEGIN <label>-----` and `-----END <label>-----`
+// lines. Header lines (`Name: value`) are returned separately.
+pub(crate) fn unarmor(
+    text: &str,
+    label: &str,
+) -> Result<(Vec<String>, Zeroizing<Vec<u8>>), KeyFileError> {
+    let begin = format!("-----BEGIN {}-----", label);
+    let end = format!("-----END {}-----", label);
+    let mut lines = text.lines().map(str::trim).skip_while(|l| l.is_empty());
+
+    if lines.next() != Some(begin.as_str()) {
+        return Err(KeyFileError::InvalidFormat("begin line is missing"));
+    }
+
+    let mut headers = Vec::new();
+    let mut body = String::new();
+    for line in lines {
+        if line == end {
+            let data = STANDARD
+                .decode(body)
+                .map_err(|_| KeyFileError::InvalidFormat("body is not valid base64"))?;
+            return Ok((headers, Zeroizing::new(data)));
+        }
+
+        if line.contains(':') && body.is_empty() {
+            headers.push(line.to_string());
+        } else {
+            body.push_str(line);
+        }
+    }
+
+    Err(KeyFileError::InvalidFormat("end line is missing"))
+}
+
+pub(crate) fn armor(label: &str, data: &[u8], line_length: usize) -> String {
+    let body = STANDARD.encode(data);
+    let mut text = format!("-----BEGIN {}-----\n", label);
+
+    for line in body.as_bytes().chunks(line_length) {
+        text.push_str(std::str::from_utf8(line).expect("Should not be reachable"));
+        text.push('\n');
+    }
+    text.push_str(&format!("-----END {}-----\n", label));
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::AlgorithmPolicy;
+    use rand_core::OsRng;
+
+    #[test]
+    fn test_generate_keys() {
+        let key_types = [
+            (KeyType::Ed25519, "ssh-ed25519"),
+            (KeyType::Ecdsa(EcdsaCurve::NistP256), "ecdsa-sha2-nistp256"),
+            (KeyType::Ecdsa(EcdsaCurve::NistP384), "ecdsa-sha2-nistp384"),
+            (KeyType::Ecdsa(EcdsaCurve::NistP521), "ecdsa-sha2-nistp521"),
+            (KeyType::Rsa(2048), "ssh-rsa"),
+        ];
+
+        for (key_type, algorithm) in key_types {
+            let key = PrivateKey::generate(key_type, &mut OsRng).unwrap();
+            let public_key = AlgorithmPolicy::default()
+                .parse_public_key(&key.public_key().to_blob())
+                .unwrap();
+            let signature = key.sign(b"parustiko").unwrap();
+
+            assert_eq!(key.algorithm(), algorithm);
+            assert!(public_key.verify(b"parustiko", &signature).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_generate_keys_differ() {
+        let first = PrivateKey::generate(KeyType::Ed25519, &mut OsRng).unwrap();
+        let second = PrivateKey::generate(KeyType::Ed25519, &mut OsRng).unwrap();
+
+        assert_ne!(first.public_key().to_blob(), second.public_key().to_blob());
+    }
+
+    #[test]
+    fn test_generate_rsa_with_unsupported_size() {
+        for bits in [1024, 16392] {
+            assert_eq!(
+                PrivateKey::generate(KeyType::Rsa(bits), &mut OsRng)
+                    .unwrap_err()
+                    .to_string(),
+                format!("unsupported rsa modulus size: ({} bits)", bits)
+            );
+        }
+    }
+}
@@ -0,0 +1,501 @@
+use super::errors::KeyFileError;
+use super::pem::{curve_from_oid, EC_PUBLIC_KEY, ED25519, RSA_ENCRYPTION};
+use super::unarmor;
+use crate::encoding::{Reader, Writer};
+use crate::registry::AlgorithmPolicy;
+use crate::signature::ecdsa::EcdsaPublicKey;
+use crate::signature::ed25519::Ed25519PublicKey;
+use crate::signature::errors::KeyError;
+use crate::signature::{rsa, PublicKey};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use pkcs8::der::Decode;
+use pkcs8::spki::SubjectPublicKeyInfoRef;
+
+const PEM_LABEL: &str = "PUBLIC KEY";
+const RFC4716_BEGIN: &str = "---- BEGIN SSH2 PUBLIC KEY ----";
+const RFC4716_END: &str = "---- END SSH2 PUBLIC KEY ----";
+const RFC4716_COMMENT: &str = "Comment";
+// RFC 4716 limits lines to 72 bytes, ssh-keygen wraps the body at 70.
+const RFC4716_MAX_LINE_LENGTH: usize = 72;
+const RFC4716_LINE_LENGTH: usize = 70;
+
+// Public key blob as stored in a `.pub` file, an authorized_keys line or
+// another public key file, together with its comment. The options of
+// authorized_keys lines are kept as they are, without interpreting them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKeyEntry {
+    algorithm: String,
+    blob: Vec<u8>,
+    comment: String,
+    options: Option<String>,
+}
+
+impl PublicKeyEntry {
+    pub fn new(blob: Vec<u8>, comment: &str) -> Result<Self, KeyFileError> {
+        let algorithm = Reader::new(&blob).read_utf8()?.to_string();
+
+        Ok(Self {
+            algorithm,
+            blob,
+            comment: comment.to_string(),
+            options: None,
+        })
+    }
+
+    // Load a public key in any of the supported formats. RFC 4716 and PEM
+    // blocks are recognized by their begin line, anything else has to be a
+    // single line.
+    pub fn decode(text: &str) -> Result<Self, KeyFileError> {
+        let start = text.trim_start();
+
+        if start.starts_with(RFC4716_BEGIN) {
+            Self::from_rfc4716(text)
+        } else if start.starts_with("-----BEGIN ") {
+            Self::from_pem(text)
+        } else {
+            Self::from_line(text)
+        }
+    }
+
+    // `[options] <type> <base64 blob> [comment]`, as written by ssh-keygen
+    // and found in authorized_keys files.
+    pub fn from_line(line: &str) -> Result<Self, KeyFileError> {
+        let line = line.trim();
+        let (options, rest) = match starts_with_key(line) {
+            true => (None, line),
+            false => {
+                let (options, rest) = split_options(line)?;
+                (Some(options), rest)
+            }
+        };
+
+        let (algorithm, rest) = split_field(rest);
+        let (data, comment) = split_field(rest);
+        let blob = STANDARD
+            .decode(data)
+            .map_err(|_| KeyFileError::InvalidFormat("public key is not valid base64"))?;
+
+        let mut key = Self::new(blob, comment)?;
+        if key.algorithm != algorithm {
+            return Err(KeyFileError::InvalidFormat(
+                "key type does not match the public key",
+            ));
+        }
+        key.options = options.map(str::to_string);
+
+        Ok(key)
+    }
+
+    // Public key file of RFC 4716. Headers other than the comment are
+    // skipped.
+    pub fn from_rfc4716(text: &str) -> Result<Self, KeyFileError> {
+        let mut lines = text.lines().map(str::trim).skip_while(|l| l.is_empty());
+
+        if lines.next() != Some(RFC4716_BEGIN) {
+            return Err(KeyFileError::InvalidFormat("begin line is missing"));
+        }
+
+        let mut headers: Vec<String> = Vec::new();
+        let mut continued = false;
+        let mut body = String::new();
+        for line in lines {
+            if line == RFC4716_END {
+                let blob = STANDARD
+                    .decode(body)
+                    .map_err(|_| KeyFileError::InvalidFormat("body is not valid base64"))?;
+
+                return Self::new(blob, rfc4716_comment(&headers).unwrap_or_default());
+            }
+
+            // Header lines ending with a backslash continue on the next line.
+            let content = line.strip_suffix('\\').unwrap_or(line);
+            if continued {
+                let header = headers.last_mut().expect("Should not be reachable");
+                header.push_str(content);
+            } else if line.contains(':') && body.is_empty() {
+                headers.push(content.to_string());
+            } else {
+                body.push_str(line);
+                continue;
+            }
+            continued = line.ends_with('\\');
+        }
+
+        Err(KeyFileError::InvalidFormat("end line is missing"))
+    }
+
+    // `-----BEGIN PUBLIC KEY-----` block holding a SubjectPublicKeyInfo, as
+    // written by OpenSSL and `ssh-keygen -e -m PKCS8`. These have no comment.
+    pub fn from_pem(text: &str) -> Result<Self, KeyFileError> {
+        let (_, der) = unarmor(text, PEM_LABEL)?;
+        let info = SubjectPublicKeyInfoRef::try_from(der.as_slice())
+            .map_err(|_| KeyFileError::InvalidFormat("public key info is invalid"))?;
+        let key = info
+            .subject_public_key
+            .as_bytes()
+            .ok_or(KeyFileError::InvalidFormat("public key info is invalid"))?;
+
+        let blob = match info.algorithm.oid {
+            RSA_ENCRYPTION => {
+                let key = ::rsa::pkcs1::RsaPublicKey::from_der(key)
+                    .map_err(|_| KeyFileError::InvalidFormat("rsa public key is invalid"))?;
+
+                let mut writer = Writer::new();
+                writer
+                    .put_string(rsa::ALGORITHM.as_bytes())
+                    .put_mpint(key.public_exponent.as_bytes())
+                    .put_mpint(key.modulus.as_bytes());
+                writer.into_bytes()
+            }
+            EC_PUBLIC_KEY => {
+                let curve = info.algorithm.parameters_oid().map_err(|_| {
+                    KeyFileError::InvalidFormat("ec public key does not name its curve")
+                })?;
+
+                EcdsaPublicKey::from_point(curve_from_oid(curve)?, key)?.to_blob()
+            }
+            ED25519 => Ed25519PublicKey::from_bytes(key)?.to_blob(),
+            oid => return Err(KeyError::UnsupportedAlgorithm(oid.to_string()).into()),
+        };
+
+        Self::new(blob, "")
+    }
+
+    // Single line form, as written to `.pub` files and authorized_keys.
+    pub fn to_line(&self) -> String {
+        let mut line = match &self.options {
+            Some(options) => format!("{} ", options),
+            None => String::new(),
+        };
+        line.push_str(&format!(
+            "{} {}",
+            self.algorithm,
+            STANDARD.encode(&self.blob)
+        ));
+
+        if !self.comment.is_empty() {
+            line.push(' ');
+            line.push_str(&self.comment);
+        }
+
+        line
+    }
+
+    pub fn to_rfc4716(&self) -> String {
+        let mut text = format!("{}\n", RFC4716_BEGIN);
+
+        if !self.comment.is_empty() {
+            let header = format!("{}: \"{}\"", RFC4716_COMMENT, self.comment);
+            let mut line = String::new();
+
+            for c in header.chars() {
+                if line.len() + c.len_utf8() >= RFC4716_MAX_LINE_LENGTH {
+                    text.push_str(&line);
+                    text.push_str("\\\n");
+                    line.clear();
+                }
+                line.push(c);
+            }
+            text.push_str(&line);
+            text.push('\n');
+        }
+
+        let body = STANDARD.encode(&self.blob);
+        for line in body.as_bytes().chunks(RFC4716_LINE_LENGTH) {
+            text.push_str(std::str::from_utf8(line).expect("Should not be reachable"));
+            text.push('\n');
+        }
+        text.push_str(RFC4716_END);
+        text.push('\n');
+
+        text
+    }
+
+    // Parse the key blob, e.g. to check a signature made with the key.
+    pub fn to_public_key(&self, policy: &AlgorithmPolicy) -> Result<Box<dyn PublicKey>, KeyError> {
+        policy.parse_public_key(&self.blob)
+    }
+
+    pub fn get_algorithm(&self) -> &str {
+        &self.algorithm
+    }
+
+    pub fn get_blob(&self) -> &[u8] {
+        &self.blob
+    }
+
+    pub fn get_comment(&self) -> &str {
+        &self.comment
+    }
+
+    pub fn get_options(&self) -> Option<&str> {
+        self.options.as_deref()
+    }
+
+    pub fn set_comment(&mut self, comment: &str) {
+        self.comment = comment.to_string();
+    }
+}
+
+// Keys of an authorized_keys file, skipping empty lines and comments.
+pub fn parse_authorized_keys(
+    text: &str,
+) -> impl Iterator<Item = Result<PublicKeyEntry, KeyFileError>> + '_ {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(PublicKeyEntry::from_line)
+}
+
+fn split_field(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+
+    match text.find(char::is_whitespace) {
+        Some(i) => (&text[..i], text[i..].trim_start()),
+        None => (text, ""),
+    }
+}
+
+// Whether the line starts with the key type, instead of options. Key
+// types are never valid base64, so the key blob is the second field exactly
+// when there are no options.
+fn starts_with_key(line: &str) -> bool {
+    let (_, rest) = split_field(line);
+    let (data, _) = split_field(rest);
+
+    STANDARD
+        .decode(data)
+        .is_ok_and(|blob| Reader::new(&blob).read_utf8().is_ok())
+}
+
+// Options are separated by commas and end at the first whitespace outside
+// of double quotes, e.g. `command="echo \"a b\"",no-pty`.
+fn split_options(line: &str) -> Result<(&str, &str), KeyFileError> {
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => return Ok((&line[..i], &line[i..])),
+            _ => {}
+        }
+    }
+
+    Err(KeyFileError::InvalidFormat(
+        "line does not contain a public key",
+    ))
+}
+
+fn rfc4716_comment(headers: &[String]) -> Option<&str> {
+    headers.iter().find_map(|header| {
+        let (tag, value) = header.split_once(':')?;
+        if !tag.trim().eq_ignore_ascii_case(RFC4716_COMMENT) {
+            return None;
+        }
+
+        let value = value.trim();
+        Some(
+            value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::fingerprint::{fingerprint, FingerprintHash};
+
+    const ID_RSA_PUB: &str = include_str!("../../../rsa_keys/id_rsa.pub");
+    // Written by `ssh-keygen -e -m PKCS8` from id_rsa.pub.
+    const ID_RSA_PEM: &str = "\
+-----BEGIN PUBLIC KEY-----\n\
+MIIBojANBgkqhkiG9w0BAQEFAAOCAY8AMIIBigKCAYEAnBMr+QsIvohszImPpPVA\n\
+Kj82y7wtFpk8DlnvIzQD79sc2zxL2kYFe5JgGXEJP/YaR7Zc86QCZLR8n/1DlH49\n\
+gHvNdDP7Dc6AfWPHfYgx92j6z60aoNkAryznYAWicMf7puk0lS1J/pstsIzPDjU4\n\
+SR2pfbbNd6ccQYNjWxBdsqiN0JFrkiVAvtl9scuck+TsVMwfbt1JfgUslxmLna//\n\
+pr55JUei3g/60C40xW4xGJSL+PQhA6eBZ+fvurIrxvjauPmX6tEd9bz3sEySWFBD\n\
+c1Iy0lwYZ0bin6ihPzWMri+g8s14FdkMYnooCloXYvFewvcSlLT+vdKnCf4P3dT9\n\
+nG5XIU85mO/ZI+36Gf1YjP3GcaF8CLqZ3E+YGuH3qE1ak6D6vX5diuf7BZlyZFjq\n\
+qtgkzRmC5KFRsXwUW1EM4hbMbZZai9g8aogxXB/Eu3jp41cDZU3KS2AhpdgqQzlC\n\
+0yNvrSqt+toya2DTKHhIyan2HTE0oknrwWCZ/afiP/STAgMBAAE=\n\
+-----END PUBLIC KEY-----\n";
+    const NISTP256_LINE: &str = concat!(
+        "ecdsa-sha2-nistp256 AAAAE2VjZHNhLXNoYTItbmlzdHAyNTYAAAAIbmlzdHAyNTYAAABBBDybXZ8iMOOa+zQajeox",
+        "aCQd3THW6O6NS9h/hJ7szvHD0eOwT7ELYB2Wl8VgKXQkGTVboPHA5z1WiwBITSVrz9g= ecdsa-test",
+    );
+    // Written by `ssh-keygen -e` and `ssh-keygen -e -m PKCS8`.
+    const NISTP256_RFC4716: &str = "\
+---- BEGIN SSH2 PUBLIC KEY ----\n\
+Comment: \"256-bit ECDSA, converted by root@vm from OpenSSH\"\n\
+AAAAE2VjZHNhLXNoYTItbmlzdHAyNTYAAAAIbmlzdHAyNTYAAABBBDybXZ8iMOOa+zQaje\n\
+oxaCQd3THW6O6NS9h/hJ7szvHD0eOwT7ELYB2Wl8VgKXQkGTVboPHA5z1WiwBITSVrz9g=\n\
+---- END SSH2 PUBLIC KEY ----\n";
+    const NISTP256_PEM: &str = "\
+-----BEGIN PUBLIC KEY-----\n\
+MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEPJtdnyIw45r7NBqN6jFoJB3dMdbo\n\
+7o1L2H+EnuzO8cPR47BPsQtgHZaXxWApdCQZNVug8cDnPVaLAEhNJWvP2A==\n\
+-----END PUBLIC KEY-----\n";
+    // Public key of RFC 8032, section 7.1, test 1.
+    const ED25519_LINE: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINdamAGCsQq31Uv+08lkBzoO4XLz2qYjJa8CGmj3B1Ea";
+    const ED25519_PEM: &str = "\
+-----BEGIN PUBLIC KEY-----\n\
+MCowBQYDK2VwAyEA11qYAYKxCrfVS/7TyWQHOg7hcvPapiMlrwIaaPcHURo=\n\
+-----END PUBLIC KEY-----\n";
+
+    #[test]
+    fn test_from_line() {
+        let key = PublicKeyEntry::from_line(ID_RSA_PUB).unwrap();
+
+        assert_eq!(key.get_algorithm(), "ssh-rsa");
+        assert_eq!(key.get_comment(), "leghart@leghart-GL65-9SC");
+        assert_eq!(key.get_options(), None);
+        assert_eq!(key.to_line(), ID_RSA_PUB.trim());
+        assert_eq!(
+            fingerprint(key.get_blob(), FingerprintHash::Sha256),
+            "SHA256:OAwZMJ845MBLpyPLcTEPWekcFxnoT2HalurW9xVy2Gs"
+        );
+        assert_eq!(
+            key.to_public_key(&AlgorithmPolicy::default())
+                .unwrap()
+                .to_blob(),
+            key.get_blob()
+        );
+    }
+
+    #[test]
+    fn test_from_line_without_comment() {
+        let key = PublicKeyEntry::from_line(ED25519_LINE).unwrap();
+
+        assert_eq!(key.get_algorithm(), "ssh-ed25519");
+        assert_eq!(key.get_comment(), "");
+        assert_eq!(key.to_line(), ED25519_LINE);
+    }
+
+    #[test]
+    fn test_from_line_with_options() {
+        let options = r#"from="10.0.0.0/8",command="echo \"hello world\"",no-pty"#;
+        let line = format!("{} {}  key with spaces", options, ED25519_LINE);
+        let key = PublicKeyEntry::from_line(&line).unwrap();
+
+        assert_eq!(key.get_options(), Some(options));
+        assert_eq!(key.get_algorithm(), "ssh-ed25519");
+        assert_eq!(key.get_comment(), "key with spaces");
+        assert_eq!(
+            key.to_line(),
+            format!("{} {} key with spaces", options, ED25519_LINE)
+        );
+    }
+
+    #[test]
+    fn test_from_line_with_invalid_key() {
+        let mismatch = ED25519_LINE.replace("ssh-ed25519", "ssh-rsa");
+
+        assert_eq!(
+            PublicKeyEntry::from_line("no-pty ssh-ed25519 @@@@")
+                .unwrap_err()
+                .to_string(),
+            "key file has invalid format: (public key is not valid base64)"
+        );
+        assert_eq!(
+            PublicKeyEntry::from_line(&mismatch)
+                .unwrap_err()
+                .to_string(),
+            "key file has invalid format: (key type does not match the public key)"
+        );
+        assert_eq!(
+            PublicKeyEntry::from_line("command=\"unterminated ssh-ed25519")
+                .unwrap_err()
+                .to_string(),
+            "key file has invalid format: (line does not contain a public key)"
+        );
+    }
+
+    #[test]
+    fn test_parse_authorized_keys() {
+        let text = format!(
+            "# keys\n\n{}\nno-agent-forwarding {}\n",
+            ID_RSA_PUB.trim(),
+            NISTP256_LINE
+        );
+        let keys: Vec<_> = parse_authorized_keys(&text)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].get_algorithm(), "ssh-rsa");
+        assert_eq!(keys[1].get_algorithm(), "ecdsa-sha2-nistp256");
+        assert_eq!(keys[1].get_options(), Some("no-agent-forwarding"));
+        assert_eq!(keys[1].get_comment(), "ecdsa-test");
+    }
+
+    #[test]
+    fn test_from_rfc4716() {
+        let expected = PublicKeyEntry::from_line(NISTP256_LINE).unwrap();
+        let key = PublicKeyEntry::decode(NISTP256_RFC4716).unwrap();
+
+        assert_eq!(key.get_blob(), expected.get_blob());
+        assert_eq!(
+            key.get_comment(),
+            "256-bit ECDSA, converted by root@vm from OpenSSH"
+        );
+        assert_eq!(key.to_rfc4716(), NISTP256_RFC4716);
+    }
+
+    #[test]
+    fn test_rfc4716_continued_headers() {
+        let mut key = PublicKeyEntry::from_line(ED25519_LINE).unwrap();
+        key.set_comment(&"a".repeat(100));
+        let text = key.to_rfc4716();
+
+        assert!(text.lines().all(|l| l.len() <= RFC4716_MAX_LINE_LENGTH));
+        assert_eq!(PublicKeyEntry::from_rfc4716(&text).unwrap(), key);
+
+        let text = text.replacen(
+            "Comment",
+            "x-command: /home/me/bin/lock-in-guest.sh \\\n  --now\nComment",
+            1,
+        );
+        assert_eq!(PublicKeyEntry::from_rfc4716(&text).unwrap(), key);
+    }
+
+    #[test]
+    fn test_from_pem() {
+        let cases = [
+            (ID_RSA_PEM, ID_RSA_PUB),
+            (NISTP256_PEM, NISTP256_LINE),
+            (ED25519_PEM, ED25519_LINE),
+        ];
+
+        for (pem, line) in cases {
+            let key = PublicKeyEntry::decode(pem).unwrap();
+
+            assert_eq!(
+                key.get_blob(),
+                PublicKeyEntry::from_line(line).unwrap().get_blob()
+            );
+            assert_eq!(key.get_comment(), "");
+        }
+    }
+
+    #[test]
+    fn test_from_pem_with_unsupported_algorithm() {
+        // X25519 key of RFC 8410, section 10.2.
+        let text = "\
+-----BEGIN PUBLIC KEY-----\n\
+MCowBQYDK2VuAyEAGb9ECWmEzf6FQbrBZ9w7lshQhqowtrbLDFw4rXAxZuE=\n\
+-----END PUBLIC KEY-----\n";
+
+        assert_eq!(
+            PublicKeyEntry::from_pem(text).unwrap_err().to_string(),
+            "invalid key in key file: (unsupported key algorithm: (1.3.101.110))"
+        );
+    }
+}
@@ -1 +1,7 @@
+pub mod encoding;
 pub mod encryption;
+pub mod keys;
+pub mod mac;
+pub mod registry;
+pub mod secret;
+pub mod signature;
@@ -0,0 +1,16 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MacInitError {
+    #[error("key has incorrect length: ({0})")]
+    IncorrectKeyLength(&'static str),
+
+    #[error("unsupported MAC: ({0})")]
+    UnsupportedMac(String),
+}
+
+#[derive(Debug, Error)]
+pub enum MacError {
+    #[error("message authentication code mismatch")]
+    Mismatch,
+}
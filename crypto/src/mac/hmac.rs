@@ -0,0 +1,191 @@
+use super::errors::{MacError, MacInitError};
+use super::Mac;
+use crate::secret::SecretBytes;
+use hmac::digest::core_api::BlockSizeUser;
+use hmac::digest::{Digest, KeyInit};
+use hmac::{Mac as _, SimpleHmac};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use std::marker::PhantomData;
+use subtle::ConstantTimeEq;
+
+// HMAC as used by the SSH transport layer (RFC 4253, RFC 6668). The key
+// has the same length as the digest output and the tag is computed over
+// the sequence number followed by the packet.
+//
+// The digest state cannot be wiped, so only the key is kept around and the
+// keyed state is created for every packet.
+pub struct Hmac<D: Digest + BlockSizeUser> {
+    key: SecretBytes,
+    etm: bool,
+    digest: PhantomData<D>,
+}
+
+pub type HmacSha1 = Hmac<Sha1>;
+pub type HmacSha256 = Hmac<Sha256>;
+pub type HmacSha512 = Hmac<Sha512>;
+
+impl<D: Digest + BlockSizeUser> Hmac<D> {
+    pub fn try_build(key: &[u8], etm: bool) -> Result<Self, MacInitError> {
+        if key.len() != <D as Digest>::output_size() {
+            return Err(MacInitError::IncorrectKeyLength(
+                "key has to be as long as the digest output",
+            ));
+        }
+
+        Ok(Self {
+            key: SecretBytes::from(key),
+            etm,
+            digest: PhantomData,
+        })
+    }
+}
+
+// Plain HMAC of the data, without the sequence number of the transport
+// layer, e.g. for the hashed host names of known_hosts files.
+pub fn hmac<D: Digest + BlockSizeUser>(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = <SimpleHmac<D> as KeyInit>::new_from_slice(key).expect("Should not be reachable");
+    mac.update(data);
+
+    mac.finalize().into_bytes().to_vec()
+}
+
+impl<D: Digest + BlockSizeUser> Mac for Hmac<D> {
+    fn key_len(&self) -> usize {
+        <D as Digest>::output_size()
+    }
+
+    fn tag_len(&self) -> usize {
+        <D as Digest>::output_size()
+    }
+
+    fn is_etm(&self) -> bool {
+        self.etm
+    }
+
+    fn compute(&mut self, sequence_number: u32, packet: &[u8]) -> Vec<u8> {
+        let mut mac = <SimpleHmac<D> as KeyInit>::new_from_slice(self.key.expose_secret())
+            .expect("Should not be reachable");
+        mac.update(&sequence_number.to_be_bytes());
+        mac.update(packet);
+
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    fn verify(&mut self, sequence_number: u32, packet: &[u8], tag: &[u8]) -> Result<(), MacError> {
+        let expected = self.compute(sequence_number, packet);
+
+        if !bool::from(expected.as_slice().ct_eq(tag)) {
+            return Err(MacError::Mismatch);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEQUENCE_NUMBER: u32 = 3;
+    const PACKET: &[u8] = b"parustiko";
+
+    #[test]
+    fn test_hmac_sha1_matches_rfc_2202() {
+        // RFC 2202, test case 1. The first 4 bytes of "Hi There" are
+        // passed as the sequence number.
+        let mut mac = HmacSha1::try_build(&[0x0b; 20], false).unwrap();
+        let expected = [
+            0xb6, 0x17, 0x31, 0x86, 0x55, 0x05, 0x72, 0x64, 0xe2, 0x8b, 0xc0, 0xb6, 0xfb, 0x37,
+            0x8c, 0x8e, 0xf1, 0x46, 0xbe, 0x00,
+        ];
+
+        let tag = mac.compute(u32::from_be_bytes(*b"Hi T"), b"here");
+
+        assert_eq!(tag, expected);
+    }
+
+    #[test]
+    fn test_plain_hmac_matches_rfc_2202() {
+        // RFC 2202, test case 2, with a key shorter than the digest.
+        let expected = [
+            0xef, 0xfc, 0xdf, 0x6a, 0xe5, 0xeb, 0x2f, 0xa2, 0xd2, 0x74, 0x16, 0xd5, 0xf1, 0x84,
+            0xdf, 0x9c, 0x25, 0x9a, 0x7c, 0x79,
+        ];
+
+        assert_eq!(
+            hmac::<Sha1>(b"Jefe", b"what do ya want for nothing?"),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_hmac_sha256() {
+        let key: Vec<u8> = (0..32).collect();
+        let mut mac = HmacSha256::try_build(&key, false).unwrap();
+        let expected = [
+            0xed, 0x17, 0x14, 0x52, 0x9e, 0x55, 0xf5, 0x1b, 0x8c, 0xa6, 0xae, 0x5b, 0x51, 0x08,
+            0xc8, 0x33, 0xdc, 0x0c, 0x76, 0xaa, 0x09, 0x21, 0x8a, 0xa0, 0x71, 0x2a, 0xd6, 0x80,
+            0x2b, 0xed, 0x74, 0xab,
+        ];
+
+        let tag = mac.compute(SEQUENCE_NUMBER, PACKET);
+
+        assert_eq!(tag, expected);
+        assert_eq!(mac.tag_len(), 32);
+    }
+
+    #[test]
+    fn test_hmac_sha512() {
+        let key: Vec<u8> = (0..64).collect();
+        let mut mac = HmacSha512::try_build(&key, true).unwrap();
+        let expected = [
+            0x5a, 0x11, 0x9e, 0x45, 0x2b, 0xc6, 0x6c, 0xd6, 0xf7, 0xba, 0x44, 0x0b, 0x29, 0xbe,
+            0xc6, 0x85, 0xd1, 0x15, 0xb4, 0x9d, 0x02, 0xaa, 0xe6, 0xff, 0xa2, 0x6c, 0x8d, 0xe9,
+            0x90, 0x7d, 0x8d, 0xe8, 0xbb, 0x2f, 0x6c, 0x1b, 0x83, 0x09, 0xa6, 0xe4, 0xe5, 0x39,
+            0xc6, 0x4a, 0x6f, 0x37, 0x98, 0x1f, 0x40, 0x64, 0x9b, 0xa2, 0x5f, 0xd2, 0x3a, 0xf0,
+            0x5d, 0xa8, 0xa4, 0xfa, 0xac, 0xab, 0x57, 0xc2,
+        ];
+
+        let tag = mac.compute(SEQUENCE_NUMBER, PACKET);
+
+        assert_eq!(tag, expected);
+        assert!(mac.is_etm());
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut mac = HmacSha256::try_build(&[1u8; 32], false).unwrap();
+        let tag = mac.compute(SEQUENCE_NUMBER, PACKET);
+
+        assert!(mac.verify(SEQUENCE_NUMBER, PACKET, &tag).is_ok());
+        assert!(mac.verify(SEQUENCE_NUMBER + 1, PACKET, &tag).is_err());
+        assert!(mac.verify(SEQUENCE_NUMBER, b"parustikO", &tag).is_err());
+    }
+
+    #[test]
+    fn test_verify_truncated_tag() {
+        let mut mac = HmacSha256::try_build(&[1u8; 32], false).unwrap();
+        let tag = mac.compute(SEQUENCE_NUMBER, PACKET);
+
+        let err = mac
+            .verify(SEQUENCE_NUMBER, PACKET, &tag[..16])
+            .unwrap_err()
+            .to_string();
+
+        assert_eq!(err, "message authentication code mismatch");
+    }
+
+    #[test]
+    fn test_incorrect_key_length() {
+        let err = HmacSha512::try_build(&[1u8; 32], false)
+            .err()
+            .unwrap()
+            .to_string();
+
+        assert_eq!(
+            err,
+            "key has incorrect length: (key has to be as long as the digest output)"
+        );
+    }
+}
@@ -0,0 +1,24 @@
+pub mod errors;
+pub mod hmac;
+pub mod umac;
+
+// Message authentication code of the transport layer. The sequence number
+// is a part of the authenticated data, so it has to be passed on every call.
+pub trait Mac {
+    fn key_len(&self) -> usize;
+    fn tag_len(&self) -> usize;
+
+    // Encrypt-then-MAC variants authenticate the ciphertext and leave
+    // the packet length unencrypted.
+    fn is_etm(&self) -> bool;
+
+    fn compute(&mut self, sequence_number: u32, packet: &[u8]) -> Vec<u8>;
+
+    // Compare the expected tag with the received one in constant time.
+    fn verify(
+        &mut self,
+        sequence_number: u32,
+        packet: &[u8],
+        tag: &[u8],
+    ) -> Result<(), errors::MacError>;
+}
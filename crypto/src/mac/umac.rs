@@ -0,0 +1,344 @@
+use super::errors::{MacError, MacInitError};
+use super::Mac;
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::{Aes128, Block};
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, Zeroizing};
+
+const KEY_SIZE: usize = 16;
+const NONCE_SIZE: usize = 8;
+const L1_KEY_SIZE: usize = 1024;
+const L1_PAD_BOUNDARY: usize = 32;
+
+// Longer messages switch to a 128 bit polynomial in the second hash layer,
+// which is not implemented as transport packets never get that large.
+const MAX_MESSAGE_SIZE: usize = 1 << 24;
+
+const P36: u64 = (1 << 36) - 5;
+const P64: u64 = u64::MAX - 58;
+const MASK64: u64 = 0x01ff_ffff_01ff_ffff;
+const MAX_WORD64: u64 = u64::MAX - u32::MAX as u64;
+
+// UMAC as described in RFC 4418 and used by umac-64@openssh.com and
+// umac-128@openssh.com. The 16 byte key is expanded into the keys of the
+// three hash layers and the pad cipher, the nonce is the packet sequence
+// number encoded as a 64 bit integer.
+pub struct Umac<const TAG_SIZE: usize> {
+    pdf_cipher: Aes128,
+    l1_key: Vec<u32>,
+    l2_key: Vec<u64>,
+    l3_key1: Vec<[u64; 8]>,
+    l3_key2: Vec<u32>,
+    etm: bool,
+}
+
+pub type Umac64 = Umac<8>;
+pub type Umac128 = Umac<16>;
+
+impl<const TAG_SIZE: usize> Umac<TAG_SIZE> {
+    const ITERATIONS: usize = TAG_SIZE / 4;
+
+    pub fn try_build(key: &[u8], etm: bool) -> Result<Self, MacInitError> {
+        let cipher = Aes128::new_from_slice(key)
+            .map_err(|_| MacInitError::IncorrectKeyLength("key has to have 16 bytes"))?;
+
+        let pdf_key = kdf(&cipher, 0, KEY_SIZE);
+        let pdf_cipher = Aes128::new_from_slice(&pdf_key).expect("Should not be reachable");
+
+        let l1_key = kdf(&cipher, 1, L1_KEY_SIZE + (Self::ITERATIONS - 1) * 16)
+            .chunks_exact(4)
+            .map(|word| u32::from_be_bytes(word.try_into().expect("Should not be reachable")))
+            .collect();
+
+        // Only the first 8 bytes of every L2 key are used by the 64 bit
+        // polynomial.
+        let l2_key = kdf(&cipher, 2, Self::ITERATIONS * 24)
+            .chunks_exact(24)
+            .map(|key| {
+                u64::from_be_bytes(key[..8].try_into().expect("Should not be reachable")) & MASK64
+            })
+            .collect();
+
+        let l3_key1 = kdf(&cipher, 3, Self::ITERATIONS * 64)
+            .chunks_exact(64)
+            .map(|key| {
+                let mut words = [0u64; 8];
+                for (word, bytes) in words.iter_mut().zip(key.chunks_exact(8)) {
+                    *word = u64::from_be_bytes(bytes.try_into().expect("Should not be reachable"))
+                        % P36;
+                }
+                words
+            })
+            .collect();
+
+        let l3_key2 = kdf(&cipher, 4, Self::ITERATIONS * 4)
+            .chunks_exact(4)
+            .map(|word| u32::from_be_bytes(word.try_into().expect("Should not be reachable")))
+            .collect();
+
+        Ok(Self {
+            pdf_cipher,
+            l1_key,
+            l2_key,
+            l3_key1,
+            l3_key2,
+            etm,
+        })
+    }
+
+    fn tag(&self, nonce: &[u8; NONCE_SIZE], message: &[u8]) -> [u8; TAG_SIZE] {
+        debug_assert!(message.len() <= MAX_MESSAGE_SIZE);
+
+        let mut tag = self.pdf(nonce);
+
+        for i in 0..Self::ITERATIONS {
+            let hash = self.uhash_iteration(i, message);
+            for (t, h) in tag[i * 4..(i + 1) * 4].iter_mut().zip(hash.to_be_bytes()) {
+                *t ^= h;
+            }
+        }
+
+        tag
+    }
+
+    // Pad-derivation function. The 64 bit tag only needs half of the cipher
+    // block, so the last bit of the nonce selects which half is used.
+    fn pdf(&self, nonce: &[u8; NONCE_SIZE]) -> [u8; TAG_SIZE] {
+        let blocks_per_pad = 16 / TAG_SIZE;
+        let index = nonce[NONCE_SIZE - 1] as usize % blocks_per_pad;
+
+        let mut block = Block::default();
+        block[..NONCE_SIZE].copy_from_slice(nonce);
+        block[NONCE_SIZE - 1] ^= index as u8;
+        self.pdf_cipher.encrypt_block(&mut block);
+
+        let mut pad = [0u8; TAG_SIZE];
+        pad.copy_from_slice(&block[index * TAG_SIZE..(index + 1) * TAG_SIZE]);
+
+        pad
+    }
+
+    fn uhash_iteration(&self, i: usize, message: &[u8]) -> u32 {
+        let l1_key = &self.l1_key[i * 4..i * 4 + L1_KEY_SIZE / 4];
+        let hashes = l1_hash(l1_key, message);
+
+        let l2 = if message.len() <= L1_KEY_SIZE {
+            hashes[0]
+        } else {
+            poly64(self.l2_key[i], &hashes)
+        };
+
+        l3_hash(&self.l3_key1[i], self.l3_key2[i], l2)
+    }
+}
+
+impl<const TAG_SIZE: usize> Drop for Umac<TAG_SIZE> {
+    fn drop(&mut self) {
+        self.l1_key.zeroize();
+        self.l2_key.zeroize();
+        self.l3_key1.zeroize();
+        self.l3_key2.zeroize();
+    }
+}
+
+impl<const TAG_SIZE: usize> Mac for Umac<TAG_SIZE> {
+    fn key_len(&self) -> usize {
+        KEY_SIZE
+    }
+
+    fn tag_len(&self) -> usize {
+        TAG_SIZE
+    }
+
+    fn is_etm(&self) -> bool {
+        self.etm
+    }
+
+    fn compute(&mut self, sequence_number: u32, packet: &[u8]) -> Vec<u8> {
+        let nonce = (sequence_number as u64).to_be_bytes();
+
+        self.tag(&nonce, packet).to_vec()
+    }
+
+    fn verify(&mut self, sequence_number: u32, packet: &[u8], tag: &[u8]) -> Result<(), MacError> {
+        let expected = self.compute(sequence_number, packet);
+
+        if !bool::from(expected.as_slice().ct_eq(tag)) {
+            return Err(MacError::Mismatch);
+        }
+
+        Ok(())
+    }
+}
+
+// Key-derivation function, the output is AES in counter mode keyed with the
+// user key, with the index in the upper half of every counter block.
+fn kdf(cipher: &Aes128, index: u64, length: usize) -> Zeroizing<Vec<u8>> {
+    let mut output = Zeroizing::new(Vec::with_capacity(length.next_multiple_of(16)));
+
+    for counter in 1..=length.div_ceil(16) as u64 {
+        let mut block = Block::default();
+        block[..8].copy_from_slice(&index.to_be_bytes());
+        block[8..].copy_from_slice(&counter.to_be_bytes());
+        cipher.encrypt_block(&mut block);
+        output.extend_from_slice(&block);
+        block.as_mut_slice().zeroize();
+    }
+
+    output.truncate(length);
+    output
+}
+
+// The message is hashed in 1024 byte chunks, the last one zero padded to
+// a multiple of 32 bytes. An empty message is hashed as a single padded
+// chunk.
+fn l1_hash(key: &[u32], message: &[u8]) -> Vec<u64> {
+    if message.is_empty() {
+        return vec![nh(key, &[0u8; L1_PAD_BOUNDARY])];
+    }
+
+    message
+        .chunks(L1_KEY_SIZE)
+        .map(|chunk| {
+            let bit_length = (chunk.len() as u64) * 8;
+
+            let hash = if chunk.len().is_multiple_of(L1_PAD_BOUNDARY) {
+                nh(key, chunk)
+            } else {
+                let mut padded = chunk.to_vec();
+                padded.resize(chunk.len().next_multiple_of(L1_PAD_BOUNDARY), 0);
+                nh(key, &padded)
+            };
+
+            hash.wrapping_add(bit_length)
+        })
+        .collect()
+}
+
+// NH universal hash. The message words are little endian, the length of the
+// message has to be a multiple of 32 bytes.
+fn nh(key: &[u32], message: &[u8]) -> u64 {
+    let words: Vec<u32> = message
+        .chunks_exact(4)
+        .map(|word| u32::from_le_bytes(word.try_into().expect("Should not be reachable")))
+        .collect();
+
+    let mut y = 0u64;
+    for (m, k) in words.chunks_exact(8).zip(key.chunks_exact(8)) {
+        for j in 0..4 {
+            let a = m[j].wrapping_add(k[j]) as u64;
+            let b = m[j + 4].wrapping_add(k[j + 4]) as u64;
+            y = y.wrapping_add(a * b);
+        }
+    }
+
+    y
+}
+
+// Polynomial hash over the L1 output, done when the message is longer than
+// a single L1 chunk.
+fn poly64(key: u64, words: &[u64]) -> u64 {
+    let step = |y: u64, m: u64| ((key as u128 * y as u128 + m as u128) % P64 as u128) as u64;
+
+    words.iter().fold(1, |y, &m| {
+        if m >= MAX_WORD64 {
+            step(step(y, P64 - 1), m - (u64::MAX - P64 + 1))
+        } else {
+            step(y, m)
+        }
+    })
+}
+
+// Inner product hash of the L2 output, extended to 128 bits and reduced to
+// 32 bits.
+fn l3_hash(key1: &[u64; 8], key2: u32, message: u64) -> u32 {
+    let mut bytes = [0u8; 16];
+    bytes[8..].copy_from_slice(&message.to_be_bytes());
+
+    let y = bytes
+        .chunks_exact(2)
+        .zip(key1.iter())
+        .fold(0u64, |y, (m, &k)| {
+            (y + u16::from_be_bytes([m[0], m[1]]) as u64 * k) % P36
+        });
+
+    (y as u32) ^ key2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 4418, section 5 test vectors.
+    const KEY: &[u8; 16] = b"abcdefghijklmnop";
+    const NONCE: &[u8; 8] = b"bcdefghi";
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02X}", b)).collect()
+    }
+
+    #[test]
+    fn test_umac64_matches_rfc_4418() {
+        let umac = Umac64::try_build(KEY, false).unwrap();
+        let vectors: [(Vec<u8>, &str); 5] = [
+            (vec![], "6E155FAD26900BE1"),
+            (vec![b'a'; 3], "44B5CB542F220104"),
+            (vec![b'a'; 1 << 10], "26BF2F5D60118BD9"),
+            (vec![b'a'; 1 << 15], "27F8EF643B0D118D"),
+            (b"abc".repeat(500), "D4CF26DDEFD5C01A"),
+        ];
+
+        for (message, expected) in vectors {
+            assert_eq!(hex(&umac.tag(NONCE, &message)), expected);
+        }
+    }
+
+    #[test]
+    fn test_umac128_matches_rfc_4418() {
+        let umac = Umac128::try_build(KEY, false).unwrap();
+        let vectors: [(Vec<u8>, &str); 4] = [
+            (vec![], "32FEDB100C79AD58F07FF7643CC60465"),
+            (vec![b'a'; 3], "185E4FE905CBA7BD85E4C2DC3D117D8D"),
+            (vec![b'a'; 1 << 10], "7A54ABE04AF82D60FB298C3CBD195BCB"),
+            (vec![b'a'; 1 << 15], "7B136BD911E4B734286EF2BE501F2C3C"),
+        ];
+
+        for (message, expected) in vectors {
+            assert_eq!(hex(&umac.tag(NONCE, &message)), expected);
+        }
+    }
+
+    #[test]
+    fn test_sequence_number_is_the_nonce() {
+        let mut umac = Umac64::try_build(KEY, true).unwrap();
+        let nonce = u64::from_be_bytes(*NONCE);
+
+        let tag = umac.tag(&(nonce & 0xffff_ffff).to_be_bytes(), b"parustiko");
+
+        assert_eq!(umac.compute(nonce as u32, b"parustiko"), tag);
+        assert!(umac.is_etm());
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut umac = Umac128::try_build(&[1u8; 16], false).unwrap();
+        let tag = umac.compute(3, b"parustiko");
+
+        assert!(umac.verify(3, b"parustiko", &tag).is_ok());
+        assert!(umac.verify(4, b"parustiko", &tag).is_err());
+        assert_eq!(
+            umac.verify(3, b"parustikO", &tag).unwrap_err().to_string(),
+            "message authentication code mismatch"
+        );
+    }
+
+    #[test]
+    fn test_incorrect_key_length() {
+        let err = Umac64::try_build(&[1u8; 32], false)
+            .err()
+            .unwrap()
+            .to_string();
+
+        assert_eq!(err, "key has incorrect length: (key has to have 16 bytes)");
+    }
+}
@@ -0,0 +1,521 @@
+use crate::encoding::Reader;
+#[cfg(feature = "legacy-ciphers")]
+use crate::encryption::cbc;
+use crate::encryption::errors::CipherInitError;
+use crate::encryption::{chacha20_poly1305, ctr, gcm, CipherMode};
+use crate::mac::errors::MacInitError;
+use crate::mac::{hmac, umac, Mac};
+use crate::signature::certificate::{self, Certificate};
+use crate::signature::errors::{CertificateError, KeyError};
+use crate::signature::{ecdsa, ed25519, rsa, PublicKey};
+
+type CipherBuilder = fn(&[u8], &[u8]) -> Result<CipherMode, CipherInitError>;
+type MacBuilder = fn(&[u8]) -> Result<Box<dyn Mac>, MacInitError>;
+
+// Description of a cipher known by its SSH algorithm name. The key and IV
+// lengths are needed before the cipher exists, to derive the right amount
+// of key material after the key exchange.
+pub struct CipherAlgorithm {
+    pub name: &'static str,
+    pub key_len: usize,
+    pub iv_len: usize,
+    pub block_size: usize,
+    pub aead: bool,
+    build: CipherBuilder,
+}
+
+impl CipherAlgorithm {
+    pub fn build(&self, key: &[u8], iv: &[u8]) -> Result<CipherMode, CipherInitError> {
+        (self.build)(key, iv)
+    }
+}
+
+pub struct MacAlgorithm {
+    pub name: &'static str,
+    pub key_len: usize,
+    pub tag_len: usize,
+    pub etm: bool,
+    build: MacBuilder,
+}
+
+impl MacAlgorithm {
+    pub fn build(&self, key: &[u8]) -> Result<Box<dyn Mac>, MacInitError> {
+        (self.build)(key)
+    }
+}
+
+// Supported ciphers in the order of preference.
+pub const CIPHERS: &[CipherAlgorithm] = &[
+    CipherAlgorithm {
+        name: "chacha20-poly1305@openssh.com",
+        key_len: 64,
+        iv_len: 0,
+        block_size: 8,
+        aead: true,
+        build: |key, _| {
+            Ok(CipherMode::Aead(Box::new(
+                chacha20_poly1305::ChaCha20Poly1305::try_build(key)?,
+            )))
+        },
+    },
+    CipherAlgorithm {
+        name: "aes128-gcm@openssh.com",
+        key_len: 16,
+        iv_len: 12,
+        block_size: 16,
+        aead: true,
+        build: |key, iv| {
+            Ok(CipherMode::Aead(Box::new(gcm::Aes128GcmCipher::try_build(
+                key, iv,
+            )?)))
+        },
+    },
+    CipherAlgorithm {
+        name: "aes256-gcm@openssh.com",
+        key_len: 32,
+        iv_len: 12,
+        block_size: 16,
+        aead: true,
+        build: |key, iv| {
+            Ok(CipherMode::Aead(Box::new(gcm::Aes256GcmCipher::try_build(
+                key, iv,
+            )?)))
+        },
+    },
+    CipherAlgorithm {
+        name: "aes128-ctr",
+        key_len: 16,
+        iv_len: 16,
+        block_size: 16,
+        aead: false,
+        build: |key, iv| {
+            Ok(CipherMode::Cipher(Box::new(ctr::Aes128Ctr::try_build(
+                key, iv,
+            )?)))
+        },
+    },
+    CipherAlgorithm {
+        name: "aes192-ctr",
+        key_len: 24,
+        iv_len: 16,
+        block_size: 16,
+        aead: false,
+        build: |key, iv| {
+            Ok(CipherMode::Cipher(Box::new(ctr::Aes192Ctr::try_build(
+                key, iv,
+            )?)))
+        },
+    },
+    CipherAlgorithm {
+        name: "aes256-ctr",
+        key_len: 32,
+        iv_len: 16,
+        block_size: 16,
+        aead: false,
+        build: |key, iv| {
+            Ok(CipherMode::Cipher(Box::new(ctr::Aes256Ctr::try_build(
+                key, iv,
+            )?)))
+        },
+    },
+];
+
+// CBC ciphers, offered after all of the above and only when allowed by the
+// algorithm policy.
+#[cfg(feature = "legacy-ciphers")]
+pub const LEGACY_CIPHERS: &[CipherAlgorithm] = &[
+    CipherAlgorithm {
+        name: "aes128-cbc",
+        key_len: 16,
+        iv_len: 16,
+        block_size: 16,
+        aead: false,
+        build: |key, iv| {
+            Ok(CipherMode::Cipher(Box::new(cbc::Aes128Cbc::try_build(
+                key, iv,
+            )?)))
+        },
+    },
+    CipherAlgorithm {
+        name: "aes256-cbc",
+        key_len: 32,
+        iv_len: 16,
+        block_size: 16,
+        aead: false,
+        build: |key, iv| {
+            Ok(CipherMode::Cipher(Box::new(cbc::Aes256Cbc::try_build(
+                key, iv,
+            )?)))
+        },
+    },
+    CipherAlgorithm {
+        name: "3des-cbc",
+        key_len: 24,
+        iv_len: 8,
+        block_size: 8,
+        aead: false,
+        build: |key, iv| {
+            Ok(CipherMode::Cipher(Box::new(cbc::TripleDesCbc::try_build(
+                key, iv,
+            )?)))
+        },
+    },
+];
+
+#[cfg(not(feature = "legacy-ciphers"))]
+pub const LEGACY_CIPHERS: &[CipherAlgorithm] = &[];
+
+// Supported MACs in the order of preference, Encrypt-then-MAC first.
+pub const MACS: &[MacAlgorithm] = &[
+    MacAlgorithm {
+        name: "umac-64-etm@openssh.com",
+        key_len: 16,
+        tag_len: 8,
+        etm: true,
+        build: |key| Ok(Box::new(umac::Umac64::try_build(key, true)?)),
+    },
+    MacAlgorithm {
+        name: "umac-128-etm@openssh.com",
+        key_len: 16,
+        tag_len: 16,
+        etm: true,
+        build: |key| Ok(Box::new(umac::Umac128::try_build(key, true)?)),
+    },
+    MacAlgorithm {
+        name: "hmac-sha2-256-etm@openssh.com",
+        key_len: 32,
+        tag_len: 32,
+        etm: true,
+        build: |key| Ok(Box::new(hmac::HmacSha256::try_build(key, true)?)),
+    },
+    MacAlgorithm {
+        name: "hmac-sha2-512-etm@openssh.com",
+        key_len: 64,
+        tag_len: 64,
+        etm: true,
+        build: |key| Ok(Box::new(hmac::HmacSha512::try_build(key, true)?)),
+    },
+    MacAlgorithm {
+        name: "hmac-sha1-etm@openssh.com",
+        key_len: 20,
+        tag_len: 20,
+        etm: true,
+        build: |key| Ok(Box::new(hmac::HmacSha1::try_build(key, true)?)),
+    },
+    MacAlgorithm {
+        name: "umac-64@openssh.com",
+        key_len: 16,
+        tag_len: 8,
+        etm: false,
+        build: |key| Ok(Box::new(umac::Umac64::try_build(key, false)?)),
+    },
+    MacAlgorithm {
+        name: "umac-128@openssh.com",
+        key_len: 16,
+        tag_len: 16,
+        etm: false,
+        build: |key| Ok(Box::new(umac::Umac128::try_build(key, false)?)),
+    },
+    MacAlgorithm {
+        name: "hmac-sha2-256",
+        key_len: 32,
+        tag_len: 32,
+        etm: false,
+        build: |key| Ok(Box::new(hmac::HmacSha256::try_build(key, false)?)),
+    },
+    MacAlgorithm {
+        name: "hmac-sha2-512",
+        key_len: 64,
+        tag_len: 64,
+        etm: false,
+        build: |key| Ok(Box::new(hmac::HmacSha512::try_build(key, false)?)),
+    },
+    MacAlgorithm {
+        name: "hmac-sha1",
+        key_len: 20,
+        tag_len: 20,
+        etm: false,
+        build: |key| Ok(Box::new(hmac::HmacSha1::try_build(key, false)?)),
+    },
+];
+
+// Host key signature algorithms in the order of preference. ssh-rsa is the
+// SHA-1 signature of RFC 4253 and is only offered when allowed.
+// Certificates come first, as in OpenSSH, so that hosts with a certificate
+// can be verified against their certificate authority.
+const HOST_KEY_ALGORITHMS: &[&str] = &[
+    certificate::ED25519_CERT,
+    certificate::NISTP256_CERT,
+    certificate::NISTP384_CERT,
+    certificate::NISTP521_CERT,
+    certificate::RSA_SHA2_512_CERT,
+    certificate::RSA_SHA2_256_CERT,
+    "ssh-ed25519",
+    "ecdsa-sha2-nistp256",
+    "ecdsa-sha2-nistp384",
+    "ecdsa-sha2-nistp521",
+    "rsa-sha2-512",
+    "rsa-sha2-256",
+];
+const SHA1_HOST_KEY_ALGORITHMS: &[&str] = &[certificate::RSA_CERT, "ssh-rsa"];
+
+// Smallest RSA modulus accepted by default, as recommended by NIST SP
+// 800-131A.
+pub const DEFAULT_MIN_RSA_MODULUS_BITS: usize = 2048;
+
+// Runtime restrictions on top of the algorithms compiled into the crate.
+// Legacy ciphers are never negotiated by accident, they need both the
+// `legacy-ciphers` feature and an explicit opt-in. The same goes for SHA-1
+// RSA signatures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlgorithmPolicy {
+    pub allow_legacy_ciphers: bool,
+    pub allow_sha1_signatures: bool,
+    pub min_rsa_modulus_bits: usize,
+}
+
+impl Default for AlgorithmPolicy {
+    fn default() -> Self {
+        Self {
+            allow_legacy_ciphers: false,
+            allow_sha1_signatures: false,
+            min_rsa_modulus_bits: DEFAULT_MIN_RSA_MODULUS_BITS,
+        }
+    }
+}
+
+impl AlgorithmPolicy {
+    pub fn ciphers(&self) -> impl Iterator<Item = &'static CipherAlgorithm> {
+        let legacy = if self.allow_legacy_ciphers {
+            LEGACY_CIPHERS
+        } else {
+            &[]
+        };
+
+        CIPHERS.iter().chain(legacy)
+    }
+
+    pub fn find_cipher(&self, name: &str) -> Option<&'static CipherAlgorithm> {
+        self.ciphers().find(|c| c.name == name)
+    }
+
+    pub fn build_cipher(
+        &self,
+        name: &str,
+        key: &[u8],
+        iv: &[u8],
+    ) -> Result<CipherMode, CipherInitError> {
+        self.find_cipher(name)
+            .ok_or_else(|| CipherInitError::UnsupportedCipher(name.to_string()))?
+            .build(key, iv)
+    }
+
+    pub fn cipher_names(&self) -> Vec<String> {
+        self.ciphers().map(|c| c.name.to_string()).collect()
+    }
+
+    pub fn host_key_algorithms(&self) -> Vec<String> {
+        let sha1 = if self.allow_sha1_signatures {
+            SHA1_HOST_KEY_ALGORITHMS
+        } else {
+            &[]
+        };
+
+        HOST_KEY_ALGORITHMS
+            .iter()
+            .chain(sha1)
+            .map(|a| a.to_string())
+            .collect()
+    }
+
+    // Parse a public key blob received from the peer, based on the key type
+    // name at its beginning.
+    pub fn parse_public_key(&self, blob: &[u8]) -> Result<Box<dyn PublicKey>, KeyError> {
+        let algorithm = Reader::new(blob).read_utf8()?;
+
+        match algorithm {
+            ed25519::ALGORITHM => Ok(Box::new(ed25519::Ed25519PublicKey::from_blob(blob)?)),
+            ecdsa::NISTP256 | ecdsa::NISTP384 | ecdsa::NISTP521 => {
+                Ok(Box::new(ecdsa::EcdsaPublicKey::from_blob(blob)?))
+            }
+            rsa::ALGORITHM => Ok(Box::new(rsa::RsaPublicKey::from_blob(blob, self)?)),
+            name if certificate::is_certificate(name) => match Certificate::from_blob(blob, self) {
+                Ok(cert) => Ok(Box::new(cert)),
+                Err(CertificateError::InvalidKey(e)) => Err(e),
+                Err(e) => Err(KeyError::InvalidCertificate(e.to_string())),
+            },
+            _ => Err(KeyError::UnsupportedAlgorithm(algorithm.to_string())),
+        }
+    }
+}
+
+pub fn find_cipher(name: &str) -> Option<&'static CipherAlgorithm> {
+    AlgorithmPolicy::default().find_cipher(name)
+}
+
+pub fn find_mac(name: &str) -> Option<&'static MacAlgorithm> {
+    MACS.iter().find(|m| m.name == name)
+}
+
+pub fn build_cipher(name: &str, key: &[u8], iv: &[u8]) -> Result<CipherMode, CipherInitError> {
+    AlgorithmPolicy::default().build_cipher(name, key, iv)
+}
+
+pub fn build_mac(name: &str, key: &[u8]) -> Result<Box<dyn Mac>, MacInitError> {
+    find_mac(name)
+        .ok_or_else(|| MacInitError::UnsupportedMac(name.to_string()))?
+        .build(key)
+}
+
+pub fn cipher_names() -> Vec<String> {
+    AlgorithmPolicy::default().cipher_names()
+}
+
+pub fn mac_names() -> Vec<String> {
+    MACS.iter().map(|m| m.name.to_string()).collect()
+}
+
+pub fn host_key_algorithms() -> Vec<String> {
+    AlgorithmPolicy::default().host_key_algorithms()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cipher_descriptions_match_built_ciphers() {
+        for algorithm in CIPHERS.iter().chain(LEGACY_CIPHERS) {
+            let key = vec![0u8; algorithm.key_len];
+            let iv = vec![0u8; algorithm.iv_len];
+
+            let (key_len, iv_len, block_size, aead) = match algorithm.build(&key, &iv).unwrap() {
+                CipherMode::Cipher(c) => (c.key_len(), c.iv_len(), c.block_size(), false),
+                CipherMode::Aead(c) => (c.key_len(), c.iv_len(), c.block_size(), true),
+            };
+
+            assert_eq!(key_len, algorithm.key_len, "{}", algorithm.name);
+            assert_eq!(iv_len, algorithm.iv_len, "{}", algorithm.name);
+            assert_eq!(block_size, algorithm.block_size, "{}", algorithm.name);
+            assert_eq!(aead, algorithm.aead, "{}", algorithm.name);
+        }
+    }
+
+    #[test]
+    fn test_mac_descriptions_match_built_macs() {
+        for algorithm in MACS {
+            let mac = algorithm.build(&vec![0u8; algorithm.key_len]).unwrap();
+
+            assert_eq!(mac.key_len(), algorithm.key_len, "{}", algorithm.name);
+            assert_eq!(mac.tag_len(), algorithm.tag_len, "{}", algorithm.name);
+            assert_eq!(mac.is_etm(), algorithm.etm, "{}", algorithm.name);
+        }
+    }
+
+    #[test]
+    fn test_build_cipher_from_name() {
+        let cipher = build_cipher("aes192-ctr", &[0u8; 24], &[0u8; 16]);
+
+        assert!(matches!(cipher, Ok(CipherMode::Cipher(_))));
+    }
+
+    #[test]
+    fn test_build_aead_from_name() {
+        let cipher = build_cipher("chacha20-poly1305@openssh.com", &[0u8; 64], &[]);
+
+        assert!(matches!(cipher, Ok(CipherMode::Aead(_))));
+    }
+
+    #[test]
+    fn test_build_with_incorrect_key() {
+        let err = build_cipher("aes256-ctr", &[0u8; 16], &[0u8; 16])
+            .err()
+            .unwrap()
+            .to_string();
+
+        assert_eq!(
+            err,
+            "key has incorrect length: (key length does not match the AES variant)"
+        );
+    }
+
+    #[test]
+    fn test_build_unsupported_algorithms() {
+        let cipher_err = build_cipher("blowfish-cbc", &[0u8; 16], &[0u8; 16])
+            .err()
+            .unwrap()
+            .to_string();
+        let mac_err = build_mac("hmac-md5", &[0u8; 16]).err().unwrap().to_string();
+
+        assert_eq!(cipher_err, "unsupported cipher: (blowfish-cbc)");
+        assert_eq!(mac_err, "unsupported MAC: (hmac-md5)");
+    }
+
+    #[test]
+    fn test_build_umac_from_name() {
+        let mac = build_mac("umac-128-etm@openssh.com", &[0u8; 16]).unwrap();
+
+        assert_eq!(mac.tag_len(), 16);
+        assert!(mac.is_etm());
+    }
+
+    #[test]
+    fn test_cipher_names_are_in_preference_order() {
+        let names = cipher_names();
+
+        assert_eq!(names[0], "chacha20-poly1305@openssh.com");
+        assert_eq!(names.len(), CIPHERS.len());
+    }
+
+    #[test]
+    fn test_legacy_ciphers_are_not_allowed_by_default() {
+        let err = build_cipher("aes128-cbc", &[0u8; 16], &[0u8; 16])
+            .err()
+            .unwrap()
+            .to_string();
+
+        assert_eq!(err, "unsupported cipher: (aes128-cbc)");
+        assert!(!cipher_names().iter().any(|name| name.ends_with("-cbc")));
+    }
+
+    #[cfg(feature = "legacy-ciphers")]
+    #[test]
+    fn test_legacy_ciphers_are_allowed_by_policy() {
+        let policy = AlgorithmPolicy {
+            allow_legacy_ciphers: true,
+            ..Default::default()
+        };
+        let names = policy.cipher_names();
+
+        assert_eq!(
+            names[CIPHERS.len()..],
+            ["aes128-cbc", "aes256-cbc", "3des-cbc"]
+        );
+        assert!(policy
+            .build_cipher("3des-cbc", &[0u8; 24], &[0u8; 8])
+            .is_ok());
+    }
+
+    #[cfg(not(feature = "legacy-ciphers"))]
+    #[test]
+    fn test_policy_cannot_enable_ciphers_which_are_not_compiled_in() {
+        let policy = AlgorithmPolicy {
+            allow_legacy_ciphers: true,
+            ..Default::default()
+        };
+
+        assert_eq!(policy.cipher_names(), cipher_names());
+        assert!(policy.find_cipher("3des-cbc").is_none());
+    }
+
+    #[test]
+    fn test_sha1_host_key_algorithm_needs_opt_in() {
+        let policy = AlgorithmPolicy {
+            allow_sha1_signatures: true,
+            ..Default::default()
+        };
+
+        assert_eq!(host_key_algorithms(), HOST_KEY_ALGORITHMS);
+        assert_eq!(policy.host_key_algorithms().last().unwrap(), "ssh-rsa");
+    }
+}
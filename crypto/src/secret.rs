@@ -0,0 +1,83 @@
+use std::fmt;
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+// Owned secret bytes (session keys, shared secrets, private keys) which are
+// wiped from memory on drop and never printed by `Debug`.
+#[derive(Clone, Default, Zeroize, ZeroizeOnDrop)]
+pub struct SecretBytes(Vec<u8>);
+
+impl SecretBytes {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+
+    pub fn expose_secret(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::new(bytes)
+    }
+}
+
+impl From<&[u8]> for SecretBytes {
+    fn from(bytes: &[u8]) -> Self {
+        Self::new(bytes.to_vec())
+    }
+}
+
+// Secrets are compared in constant time.
+impl PartialEq for SecretBytes {
+    fn eq(&self, other: &Self) -> bool {
+        bool::from(self.0.ct_eq(&other.0))
+    }
+}
+
+impl Eq for SecretBytes {}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretBytes([REDACTED; {}])", self.0.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_does_not_expose_the_secret() {
+        let secret = SecretBytes::new(b"hunter2".to_vec());
+
+        assert_eq!(format!("{:?}", secret), "SecretBytes([REDACTED; 7])");
+    }
+
+    #[test]
+    fn test_zeroize_wipes_the_secret() {
+        let mut secret = SecretBytes::from(&[42u8; 16][..]);
+
+        secret.zeroize();
+
+        assert!(secret.is_empty());
+    }
+
+    #[test]
+    fn test_equality() {
+        let secret = SecretBytes::from(vec![1, 2, 3]);
+
+        assert_eq!(secret, SecretBytes::from(vec![1, 2, 3]));
+        assert_ne!(secret, SecretBytes::from(vec![1, 2, 4]));
+        assert_ne!(secret, SecretBytes::from(vec![1, 2]));
+    }
+}
@@ -0,0 +1,775 @@
+use super::errors::{CertificateError, KeyError, SignatureError};
+use super::{ecdsa, ed25519, rsa, PublicKey, Signature, Signer};
+use crate::encoding::{Reader, Writer};
+use crate::registry::AlgorithmPolicy;
+use rand_core::{CryptoRng, RngCore};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::IpAddr;
+
+pub const ED25519_CERT: &str = "ssh-ed25519-cert-v01@openssh.com";
+pub const NISTP256_CERT: &str = "ecdsa-sha2-nistp256-cert-v01@openssh.com";
+pub const NISTP384_CERT: &str = "ecdsa-sha2-nistp384-cert-v01@openssh.com";
+pub const NISTP521_CERT: &str = "ecdsa-sha2-nistp521-cert-v01@openssh.com";
+pub const RSA_CERT: &str = "ssh-rsa-cert-v01@openssh.com";
+// Host key algorithms of RSA certificates signing with SHA-2, like
+// rsa-sha2-256 and rsa-sha2-512 for plain RSA keys.
+pub const RSA_SHA2_256_CERT: &str = "rsa-sha2-256-cert-v01@openssh.com";
+pub const RSA_SHA2_512_CERT: &str = "rsa-sha2-512-cert-v01@openssh.com";
+
+// Certificate key types with the type of the key they certify.
+const CERT_KEY_TYPES: &[(&str, &str)] = &[
+    (ED25519_CERT, ed25519::ALGORITHM),
+    (NISTP256_CERT, ecdsa::NISTP256),
+    (NISTP384_CERT, ecdsa::NISTP384),
+    (NISTP521_CERT, ecdsa::NISTP521),
+    (RSA_CERT, rsa::ALGORITHM),
+];
+
+const NONCE_SIZE: usize = 32;
+const USER_CERT: u32 = 1;
+const HOST_CERT: u32 = 2;
+
+// Critical options of user certificates, host certificates have none.
+pub const FORCE_COMMAND: &str = "force-command";
+pub const SOURCE_ADDRESS: &str = "source-address";
+// Only meaningful for FIDO keys, which are not supported.
+pub const VERIFY_REQUIRED: &str = "verify-required";
+
+// Extensions given to user certificates by ssh-keygen by default.
+pub const DEFAULT_USER_EXTENSIONS: &[&str] = &[
+    "permit-X11-forwarding",
+    "permit-agent-forwarding",
+    "permit-port-forwarding",
+    "permit-pty",
+    "permit-user-rc",
+];
+
+// Validity of certificates which never expire.
+pub const FOREVER: u64 = u64::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertificateType {
+    User,
+    Host,
+}
+
+impl CertificateType {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Host => "host",
+        }
+    }
+}
+
+// Whether the key type is one of the certificate types.
+pub fn is_certificate(algorithm: &str) -> bool {
+    CERT_KEY_TYPES.iter().any(|(cert, _)| *cert == algorithm)
+}
+
+// OpenSSH certificate as described in PROTOCOL.certkeys. The signature of
+// the certificate authority is checked when the certificate is parsed,
+// everything else only by `validate`.
+pub struct Certificate {
+    algorithm: &'static str,
+    key: Box<dyn PublicKey>,
+    serial: u64,
+    cert_type: CertificateType,
+    key_id: String,
+    principals: Vec<String>,
+    valid_after: u64,
+    valid_before: u64,
+    critical_options: BTreeMap<String, String>,
+    extensions: BTreeMap<String, String>,
+    signature_key: Vec<u8>,
+    blob: Vec<u8>,
+}
+
+impl Certificate {
+    pub fn from_blob(blob: &[u8], policy: &AlgorithmPolicy) -> Result<Self, CertificateError> {
+        let mut reader = Reader::new(blob);
+        let name = reader.read_utf8()?;
+        let (algorithm, key_type) = CERT_KEY_TYPES
+            .iter()
+            .find(|(cert, _)| *cert == name)
+            .ok_or_else(|| KeyError::UnsupportedAlgorithm(name.to_string()))?;
+        reader.read_string()?;
+
+        // The fields of the certified key follow the nonce, they are the
+        // same as in the blob of the plain key.
+        let mut key = Writer::new();
+        key.put_string(key_type.as_bytes());
+        match *key_type {
+            ed25519::ALGORITHM => key.put_string(reader.read_string()?),
+            rsa::ALGORITHM => key
+                .put_mpint(reader.read_mpint()?)
+                .put_mpint(reader.read_mpint()?),
+            _ => key
+                .put_string(reader.read_string()?)
+                .put_string(reader.read_string()?),
+        };
+        let key = policy.parse_public_key(&key.into_bytes())?;
+
+        let serial = reader.read_u64()?;
+        let cert_type = match reader.read_u32()? {
+            USER_CERT => CertificateType::User,
+            HOST_CERT => CertificateType::Host,
+            _ => return Err(CertificateError::InvalidFormat("unknown certificate type")),
+        };
+        let key_id = reader.read_utf8()?.to_string();
+        let principals = parse_principals(reader.read_string()?)?;
+        let valid_after = reader.read_u64()?;
+        let valid_before = reader.read_u64()?;
+        let critical_options = parse_options(reader.read_string()?)?;
+        let extensions = parse_options(reader.read_string()?)?;
+        reader.read_string()?;
+        let signature_key = reader.read_string()?;
+
+        let signed = &blob[..blob.len() - reader.remaining().len()];
+        let signature = Signature::from_blob(reader.read_string()?)?;
+        reader.finish()?;
+
+        if is_certificate(Reader::new(signature_key).read_utf8()?) {
+            return Err(CertificateError::InvalidFormat(
+                "certificate authority key cannot be a certificate",
+            ));
+        }
+        policy
+            .parse_public_key(signature_key)?
+            .verify(signed, &signature)?;
+
+        Ok(Self {
+            algorithm,
+            key,
+            serial,
+            cert_type,
+            key_id,
+            principals,
+            valid_after,
+            valid_before,
+            critical_options,
+            extensions,
+            signature_key: signature_key.to_vec(),
+            blob: blob.to_vec(),
+        })
+    }
+
+    // Check that the certificate can be used as the given type by the
+    // principal, at a time in seconds since the Unix epoch. The client
+    // address is needed for the source-address option of user
+    // certificates. Host certificates without principals are valid for
+    // any host, user certificates have to name the user.
+    pub fn validate(
+        &self,
+        cert_type: CertificateType,
+        principal: &str,
+        now: u64,
+        source: Option<IpAddr>,
+    ) -> Result<(), CertificateError> {
+        if self.cert_type != cert_type {
+            return Err(CertificateError::WrongType(self.cert_type.name()));
+        }
+
+        if now < self.valid_after {
+            return Err(CertificateError::NotYetValid);
+        }
+        if now >= self.valid_before {
+            return Err(CertificateError::Expired);
+        }
+
+        let any_principal = self.principals.is_empty() && cert_type == CertificateType::Host;
+        if !any_principal && !self.principals.iter().any(|p| p == principal) {
+            return Err(CertificateError::PrincipalNotAllowed(principal.to_string()));
+        }
+
+        // Unknown critical options must never be ignored.
+        for (name, value) in &self.critical_options {
+            match (cert_type, name.as_str()) {
+                (CertificateType::User, FORCE_COMMAND | VERIFY_REQUIRED) => {}
+                (CertificateType::User, SOURCE_ADDRESS) => {
+                    let source = source.ok_or_else(|| {
+                        CertificateError::SourceAddressNotAllowed("unknown".to_string())
+                    })?;
+
+                    if !source_address_allowed(value, source.to_canonical())? {
+                        return Err(CertificateError::SourceAddressNotAllowed(
+                            source.to_string(),
+                        ));
+                    }
+                }
+                _ => return Err(CertificateError::UnsupportedCriticalOption(name.clone())),
+            }
+        }
+
+        Ok(())
+    }
+
+    // Server side check of a user certificate, which has to be signed by
+    // one of the trusted certificate authority keys.
+    pub fn authorize_user(
+        &self,
+        trusted_authorities: &[Vec<u8>],
+        user: &str,
+        now: u64,
+        source: Option<IpAddr>,
+    ) -> Result<(), CertificateError> {
+        if !trusted_authorities.contains(&self.signature_key) {
+            return Err(CertificateError::UntrustedAuthority);
+        }
+
+        self.validate(CertificateType::User, user, now, source)
+    }
+
+    // The certified key, which makes the signatures.
+    pub fn get_key(&self) -> &dyn PublicKey {
+        self.key.as_ref()
+    }
+
+    pub fn get_serial(&self) -> u64 {
+        self.serial
+    }
+
+    pub fn get_type(&self) -> CertificateType {
+        self.cert_type
+    }
+
+    pub fn get_key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    pub fn get_principals(&self) -> &[String] {
+        &self.principals
+    }
+
+    pub fn get_valid_after(&self) -> u64 {
+        self.valid_after
+    }
+
+    pub fn get_valid_before(&self) -> u64 {
+        self.valid_before
+    }
+
+    pub fn get_critical_options(&self) -> &BTreeMap<String, String> {
+        &self.critical_options
+    }
+
+    pub fn get_extensions(&self) -> &BTreeMap<String, String> {
+        &self.extensions
+    }
+
+    // Key blob of the certificate authority.
+    pub fn get_signature_key(&self) -> &[u8] {
+        &self.signature_key
+    }
+}
+
+// Signatures are made by the certified key, so they use the algorithm
+// names of the plain key.
+impl PublicKey for Certificate {
+    fn algorithm(&self) -> &'static str {
+        self.algorithm
+    }
+
+    fn to_blob(&self) -> Vec<u8> {
+        self.blob.clone()
+    }
+
+    fn verify(&self, data: &[u8], signature: &Signature) -> Result<(), SignatureError> {
+        self.key.verify(data, signature)
+    }
+}
+
+impl fmt::Debug for Certificate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Certificate")
+            .field("algorithm", &self.algorithm)
+            .field("serial", &self.serial)
+            .field("cert_type", &self.cert_type)
+            .field("key_id", &self.key_id)
+            .field("principals", &self.principals)
+            .field("valid_after", &self.valid_after)
+            .field("valid_before", &self.valid_before)
+            .field("critical_options", &self.critical_options)
+            .field("extensions", &self.extensions)
+            .finish_non_exhaustive()
+    }
+}
+
+// Contents of a new certificate, signed with the key of the certificate
+// authority by `sign`. Certificates are valid forever and for any host
+// unless restricted.
+pub struct CertificateBuilder {
+    key: Vec<u8>,
+    cert_type: CertificateType,
+    serial: u64,
+    key_id: String,
+    principals: Vec<String>,
+    valid_after: u64,
+    valid_before: u64,
+    critical_options: BTreeMap<String, String>,
+    extensions: BTreeMap<String, String>,
+}
+
+impl CertificateBuilder {
+    pub fn new(key: &dyn PublicKey, cert_type: CertificateType) -> Self {
+        Self {
+            key: key.to_blob(),
+            cert_type,
+            serial: 0,
+            key_id: String::new(),
+            principals: Vec::new(),
+            valid_after: 0,
+            valid_before: FOREVER,
+            critical_options: BTreeMap::new(),
+            extensions: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_serial(mut self, serial: u64) -> Self {
+        self.serial = serial;
+        self
+    }
+
+    pub fn with_key_id(mut self, key_id: &str) -> Self {
+        self.key_id = key_id.to_string();
+        self
+    }
+
+    pub fn with_principal(mut self, principal: &str) -> Self {
+        self.principals.push(principal.to_string());
+        self
+    }
+
+    // Valid from `after` up to, but not including, `before`, both in
+    // seconds since the Unix epoch.
+    pub fn with_validity(mut self, after: u64, before: u64) -> Self {
+        self.valid_after = after;
+        self.valid_before = before;
+        self
+    }
+
+    pub fn with_critical_option(mut self, name: &str, value: &str) -> Self {
+        self.critical_options
+            .insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn with_extension(mut self, name: &str, value: &str) -> Self {
+        self.extensions.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn with_default_extensions(self) -> Self {
+        DEFAULT_USER_EXTENSIONS
+            .iter()
+            .fold(self, |builder, name| builder.with_extension(name, ""))
+    }
+
+    // Sign the certificate. The result is checked with the default policy,
+    // so neither the key nor the signature of the authority can be weaker
+    // than what peers accept by default.
+    pub fn sign<R>(
+        self,
+        authority: &dyn Signer,
+        rng: &mut R,
+    ) -> Result<Certificate, CertificateError>
+    where
+        R: RngCore + CryptoRng + ?Sized,
+    {
+        let mut key = Reader::new(&self.key);
+        let key_type = key.read_utf8()?;
+        let (algorithm, _) = CERT_KEY_TYPES
+            .iter()
+            .find(|(_, plain)| *plain == key_type)
+            .ok_or_else(|| KeyError::UnsupportedAlgorithm(key_type.to_string()))?;
+
+        let mut nonce = [0u8; NONCE_SIZE];
+        rng.fill_bytes(&mut nonce);
+        let cert_type = match self.cert_type {
+            CertificateType::User => USER_CERT,
+            CertificateType::Host => HOST_CERT,
+        };
+
+        let mut writer = Writer::new();
+        writer
+            .put_string(algorithm.as_bytes())
+            .put_string(&nonce)
+            .put_bytes(key.remaining())
+            .put_u64(self.serial)
+            .put_u32(cert_type)
+            .put_string(self.key_id.as_bytes())
+            .put_string(&encode_principals(&self.principals))
+            .put_u64(self.valid_after)
+            .put_u64(self.valid_before)
+            .put_string(&encode_options(&self.critical_options))
+            .put_string(&encode_options(&self.extensions))
+            .put_string(&[])
+            .put_string(&authority.public_key().to_blob());
+        let mut blob = writer.into_bytes();
+
+        let signature = authority.sign(&blob)?;
+        let mut writer = Writer::new();
+        writer.put_string(&signature.to_blob());
+        blob.extend(writer.into_bytes());
+
+        Certificate::from_blob(&blob, &AlgorithmPolicy::default())
+    }
+}
+
+fn parse_principals(data: &[u8]) -> Result<Vec<String>, CertificateError> {
+    let mut reader = Reader::new(data);
+    let mut principals = Vec::new();
+
+    while !reader.remaining().is_empty() {
+        principals.push(reader.read_utf8()?.to_string());
+    }
+
+    Ok(principals)
+}
+
+fn encode_principals(principals: &[String]) -> Vec<u8> {
+    let mut writer = Writer::new();
+    for principal in principals {
+        writer.put_string(principal.as_bytes());
+    }
+
+    writer.into_bytes()
+}
+
+// Name and data pairs, where the data is either empty or holds a string.
+fn parse_options(data: &[u8]) -> Result<BTreeMap<String, String>, CertificateError> {
+    let mut reader = Reader::new(data);
+    let mut options = BTreeMap::new();
+
+    while !reader.remaining().is_empty() {
+        let name = reader.read_utf8()?.to_string();
+        let data = reader.read_string()?;
+
+        let value = match data.is_empty() {
+            true => String::new(),
+            false => {
+                let mut data = Reader::new(data);
+                let value = data.read_utf8()?.to_string();
+                data.finish()?;
+                value
+            }
+        };
+
+        if options.insert(name, value).is_some() {
+            return Err(CertificateError::InvalidFormat(
+                "certificate option appears more than once",
+            ));
+        }
+    }
+
+    Ok(options)
+}
+
+// The options are written in lexical order of their names, as required.
+fn encode_options(options: &BTreeMap<String, String>) -> Vec<u8> {
+    let mut writer = Writer::new();
+    for (name, value) in options {
+        writer.put_string(name.as_bytes());
+
+        match value.is_empty() {
+            true => writer.put_string(&[]),
+            false => {
+                let mut data = Writer::new();
+                data.put_string(value.as_bytes());
+                writer.put_string(&data.into_bytes())
+            }
+        };
+    }
+
+    writer.into_bytes()
+}
+
+// Comma separated addresses and CIDR blocks of the source-address option.
+fn source_address_allowed(list: &str, address: IpAddr) -> Result<bool, CertificateError> {
+    let invalid = || CertificateError::InvalidFormat("source address list is invalid");
+
+    for entry in list.split(',') {
+        let (network, prefix) = match entry.split_once('/') {
+            Some((network, prefix)) => (network, Some(prefix)),
+            None => (entry, None),
+        };
+
+        let (network, address, width) = match (network.parse().map_err(|_| invalid())?, address) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                (u32::from(network) as u128, u32::from(address) as u128, 32)
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                (u128::from(network), u128::from(address), 128)
+            }
+            _ => continue,
+        };
+
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u32>()
+                .ok()
+                .filter(|p| *p <= width)
+                .ok_or_else(invalid)?,
+            None => width,
+        };
+        let mask = match prefix {
+            0 => 0,
+            _ => (u128::MAX << (128 - prefix)) >> (128 - width),
+        };
+
+        if network & mask == address & mask {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys;
+    use crate::signature::ed25519::Ed25519PrivateKey;
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use rand_core::OsRng;
+
+    // Signed with `ssh-keygen -s ca -I alice@example.com -n alice,admin -z 42
+    // -V 20240101000000Z:20340101000000Z -O force-command=/usr/bin/uptime
+    // -O source-address=192.0.2.0/24,2001:db8::/32`.
+    const USER_CERT: &str = concat!(
+        "AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIFmrZMZtTt3YnV54ZH+fcdO1MxBYjFbictBR8JYl",
+        "b7fSAAAAINfg1xNoKmOiosPf/16ApWhaadVwYDfkUF04ucIafUNoAAAAAAAAACoAAAABAAAAEWFsaWNlQGV4YW1wbGUu",
+        "Y29tAAAAEgAAAAVhbGljZQAAAAVhZG1pbgAAAABlkgCAAAAAAHhh+AAAAABcAAAADWZvcmNlLWNvbW1hbmQAAAATAAAA",
+        "Dy91c3IvYmluL3VwdGltZQAAAA5zb3VyY2UtYWRkcmVzcwAAAB4AAAAaMTkyLjAuMi4wLzI0LDIwMDE6ZGI4OjovMzIA",
+        "AACCAAAAFXBlcm1pdC1YMTEtZm9yd2FyZGluZwAAAAAAAAAXcGVybWl0LWFnZW50LWZvcndhcmRpbmcAAAAAAAAAFnBl",
+        "cm1pdC1wb3J0LWZvcndhcmRpbmcAAAAAAAAACnBlcm1pdC1wdHkAAAAAAAAADnBlcm1pdC11c2VyLXJjAAAAAAAAAAAA",
+        "AAAzAAAAC3NzaC1lZDI1NTE5AAAAIObd37FwuLffLGRE5svNpKN40ooPPYRO5/iraXFOaA6IAAAAUwAAAAtzc2gtZWQy",
+        "NTUxOQAAAED5gSlIfAqGwv3uE8GkFMkTI3j1ydtBpIsI1qInQ1PW/J4wJuMifm68l5gGiJ8rqDuDttnnkQe7TLc1m1SR",
+        "A0AO",
+    );
+    // Signed with `ssh-keygen -s ca -h -I web01 -n web01.example.com,web01
+    // -z 7 -V 20240101000000Z:20340101000000Z`.
+    const HOST_CERT: &str = concat!(
+        "AAAAKGVjZHNhLXNoYTItbmlzdHAyNTYtY2VydC12MDFAb3BlbnNzaC5jb20AAAAglqKaxFnZqcekZjmyVPFTK1/lDyxy",
+        "0lkH5UHj7vFU+MEAAAAIbmlzdHAyNTYAAABBBDybXZ8iMOOa+zQajeoxaCQd3THW6O6NS9h/hJ7szvHD0eOwT7ELYB2W",
+        "l8VgKXQkGTVboPHA5z1WiwBITSVrz9gAAAAAAAAABwAAAAIAAAAFd2ViMDEAAAAeAAAAEXdlYjAxLmV4YW1wbGUuY29t",
+        "AAAABXdlYjAxAAAAAGWSAIAAAAAAeGH4AAAAAAAAAAAAAAAAAAAAADMAAAALc3NoLWVkMjU1MTkAAAAg5t3fsXC4t98s",
+        "ZETmy82ko3jSig89hE7n+KtpcU5oDogAAABTAAAAC3NzaC1lZDI1NTE5AAAAQEkU5qclbzvnk2v+jcq/hiAFcbXJx3I8",
+        "nuHwQH+16e1JMU0X+M/OArPd+C+91iUrD5VUMwALj28AXR7FL9ODDQg=",
+    );
+    const CA_KEY: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIObd37FwuLffLGRE5svNpKN40ooPPYRO5/iraXFOaA6I";
+    const USER_KEY: &str = "AAAAC3NzaC1lZDI1NTE5AAAAINfg1xNoKmOiosPf/16ApWhaadVwYDfkUF04ucIafUNo";
+    const ID_RSA: &str = include_str!("../../../rsa_keys/id_rsa");
+    // 2024-06-01 and 2035-01-01.
+    const NOW: u64 = 1717200000;
+    const LATER: u64 = 2051222400;
+
+    fn blob(data: &str) -> Vec<u8> {
+        STANDARD.decode(data).unwrap()
+    }
+
+    fn parse(data: &str) -> Certificate {
+        Certificate::from_blob(&blob(data), &AlgorithmPolicy::default()).unwrap()
+    }
+
+    fn source() -> Option<IpAddr> {
+        Some("192.0.2.10".parse().unwrap())
+    }
+
+    #[test]
+    fn test_parse_user_certificate() {
+        let cert = parse(USER_CERT);
+
+        assert_eq!(cert.algorithm(), ED25519_CERT);
+        assert_eq!(cert.to_blob(), blob(USER_CERT));
+        assert_eq!(cert.get_key().to_blob(), blob(USER_KEY));
+        assert_eq!(cert.get_signature_key(), blob(CA_KEY));
+        assert_eq!(cert.get_serial(), 42);
+        assert_eq!(cert.get_type(), CertificateType::User);
+        assert_eq!(cert.get_key_id(), "alice@example.com");
+        assert_eq!(cert.get_principals(), ["alice", "admin"]);
+        assert_eq!(cert.get_valid_after(), 1704067200);
+        assert_eq!(cert.get_valid_before(), 2019686400);
+        assert_eq!(
+            cert.get_critical_options()[FORCE_COMMAND],
+            "/usr/bin/uptime"
+        );
+        assert!(cert
+            .get_extensions()
+            .keys()
+            .map(String::as_str)
+            .eq(DEFAULT_USER_EXTENSIONS.iter().copied()));
+    }
+
+    #[test]
+    fn test_parse_host_certificate() {
+        let cert = parse(HOST_CERT);
+
+        assert_eq!(cert.algorithm(), NISTP256_CERT);
+        assert_eq!(
+            crate::signature::parse_public_key(&blob(HOST_CERT))
+                .unwrap()
+                .algorithm(),
+            NISTP256_CERT
+        );
+        assert_eq!(cert.get_key().algorithm(), "ecdsa-sha2-nistp256");
+        assert_eq!(cert.get_type(), CertificateType::Host);
+        assert!(cert.get_critical_options().is_empty());
+        assert!(cert
+            .validate(CertificateType::Host, "web01.example.com", NOW, None)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_parse_with_invalid_signature() {
+        let mut data = blob(HOST_CERT);
+        let length = data.len();
+        data[length - 1] ^= 1;
+
+        assert_eq!(
+            Certificate::from_blob(&data, &AlgorithmPolicy::default())
+                .unwrap_err()
+                .to_string(),
+            "certificate signature is invalid: (signature verification failed)"
+        );
+    }
+
+    #[test]
+    fn test_validate_user_certificate() {
+        let cert = parse(USER_CERT);
+        let validate = |principal, now, source| {
+            cert.validate(CertificateType::User, principal, now, source)
+                .map_err(|e| e.to_string())
+        };
+
+        assert_eq!(validate("admin", NOW, source()), Ok(()));
+        assert_eq!(
+            validate("admin", NOW, Some("2001:db8::1".parse().unwrap())),
+            Ok(())
+        );
+        assert_eq!(
+            validate("admin", NOW, Some("::ffff:192.0.2.1".parse().unwrap())),
+            Ok(())
+        );
+        assert_eq!(
+            validate("root", NOW, source()).unwrap_err(),
+            "principal is not allowed by the certificate: (root)"
+        );
+        assert_eq!(
+            validate("alice", 1600000000, source()).unwrap_err(),
+            "certificate is not valid yet"
+        );
+        assert_eq!(
+            validate("alice", LATER, source()).unwrap_err(),
+            "certificate has expired"
+        );
+        assert_eq!(
+            validate("alice", NOW, Some("198.51.100.1".parse().unwrap())).unwrap_err(),
+            "source address is not allowed by the certificate: (198.51.100.1)"
+        );
+        assert_eq!(
+            cert.validate(CertificateType::Host, "alice", NOW, source())
+                .unwrap_err()
+                .to_string(),
+            "certificate has the wrong type: (user)"
+        );
+    }
+
+    #[test]
+    fn test_authorize_user() {
+        let cert = parse(USER_CERT);
+
+        assert!(cert
+            .authorize_user(&[blob(CA_KEY)], "alice", NOW, source())
+            .is_ok());
+        assert_eq!(
+            cert.authorize_user(&[blob(USER_KEY)], "alice", NOW, source())
+                .unwrap_err()
+                .to_string(),
+            "certificate is not signed by a trusted authority"
+        );
+    }
+
+    #[test]
+    fn test_sign_certificate() {
+        let (authority, _) = keys::decode(ID_RSA, || None).unwrap();
+        let key = Ed25519PrivateKey::generate(&mut OsRng);
+        let cert = CertificateBuilder::new(&key.public(), CertificateType::User)
+            .with_serial(1)
+            .with_key_id("deploy")
+            .with_principal("deploy")
+            .with_validity(NOW, LATER)
+            .with_critical_option(FORCE_COMMAND, "/usr/bin/true")
+            .with_default_extensions()
+            .sign(&authority, &mut OsRng)
+            .unwrap();
+
+        assert_eq!(cert.get_key().to_blob(), key.public().to_blob());
+        assert_eq!(cert.get_signature_key(), authority.public_key().to_blob());
+        assert_eq!(cert.get_extensions().len(), DEFAULT_USER_EXTENSIONS.len());
+        assert!(cert
+            .validate(CertificateType::User, "deploy", NOW, None)
+            .is_ok());
+
+        let signature = key.sign(b"parustiko").unwrap();
+        assert!(cert.verify(b"parustiko", &signature).is_ok());
+    }
+
+    #[test]
+    fn test_sign_with_unknown_critical_option() {
+        let authority = Ed25519PrivateKey::generate(&mut OsRng);
+        let cert = CertificateBuilder::new(parse(HOST_CERT).get_key(), CertificateType::Host)
+            .with_critical_option("no-such-option", "")
+            .sign(&authority, &mut OsRng)
+            .unwrap();
+
+        assert_eq!(cert.get_principals(), [] as [String; 0]);
+        assert_eq!(
+            cert.validate(CertificateType::Host, "any.example.com", NOW, None)
+                .unwrap_err()
+                .to_string(),
+            "unsupported critical option: (no-such-option)"
+        );
+    }
+
+    #[test]
+    fn test_certificate_cannot_be_certified() {
+        let authority = Ed25519PrivateKey::generate(&mut OsRng);
+        let err = CertificateBuilder::new(&parse(HOST_CERT), CertificateType::Host)
+            .sign(&authority, &mut OsRng)
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "certificate key is invalid: (unsupported key algorithm: ({}))",
+                NISTP256_CERT
+            )
+        );
+    }
+
+    #[test]
+    fn test_source_address_allowed() {
+        let cases = [
+            ("10.0.0.0/8", "10.1.2.3", true),
+            ("10.0.0.0/8", "11.0.0.1", false),
+            ("0.0.0.0/0", "203.0.113.5", true),
+            ("203.0.113.5", "203.0.113.5", true),
+            ("2001:db8::/32,10.0.0.0/8", "2001:db8:1::1", true),
+            ("2001:db8::/32", "10.0.0.1", false),
+        ];
+
+        for (list, address, expected) in cases {
+            assert_eq!(
+                source_address_allowed(list, address.parse().unwrap()).unwrap(),
+                expected,
+                "{} {}",
+                list,
+                address
+            );
+        }
+        assert!(source_address_allowed("10.0.0.0/33", "10.0.0.1".parse().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,529 @@
+use super::errors::{KeyError, SignatureError};
+use super::{PublicKey, Signature, Signer};
+use crate::encoding::{Reader, Writer};
+use p256::ecdsa::signature::{Signer as _, Verifier as _};
+use rand_core::{CryptoRng, RngCore};
+use std::fmt;
+use zeroize::Zeroizing;
+
+pub const NISTP256: &str = "ecdsa-sha2-nistp256";
+pub const NISTP384: &str = "ecdsa-sha2-nistp384";
+pub const NISTP521: &str = "ecdsa-sha2-nistp521";
+// Only the uncompressed point encoding is used by SSH implementations.
+const UNCOMPRESSED_POINT: u8 = 0x04;
+
+// ECDSA curves of RFC 5656. The hash of the signature follows from the
+// curve: SHA-256 for nistp256, SHA-384 for nistp384 and SHA-512 for
+// nistp521.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EcdsaCurve {
+    NistP256,
+    NistP384,
+    NistP521,
+}
+
+impl EcdsaCurve {
+    pub fn from_algorithm(name: &str) -> Option<Self> {
+        match name {
+            NISTP256 => Some(Self::NistP256),
+            NISTP384 => Some(Self::NistP384),
+            NISTP521 => Some(Self::NistP521),
+            _ => None,
+        }
+    }
+
+    pub fn algorithm(&self) -> &'static str {
+        match self {
+            Self::NistP256 => NISTP256,
+            Self::NistP384 => NISTP384,
+            Self::NistP521 => NISTP521,
+        }
+    }
+
+    // Curve name used inside the key blob.
+    pub fn identifier(&self) -> &'static str {
+        match self {
+            Self::NistP256 => "nistp256",
+            Self::NistP384 => "nistp384",
+            Self::NistP521 => "nistp521",
+        }
+    }
+
+    // Length of the field elements and scalars in bytes.
+    pub fn field_size(&self) -> usize {
+        match self {
+            Self::NistP256 => 32,
+            Self::NistP384 => 48,
+            Self::NistP521 => 66,
+        }
+    }
+}
+
+// Big endian integer from an mpint, left padded to the field size.
+fn pad_to_field(value: &[u8], curve: EcdsaCurve) -> Option<Vec<u8>> {
+    let value = &value[value.iter().take_while(|b| **b == 0).count()..];
+    let size = curve.field_size();
+
+    if value.len() > size {
+        return None;
+    }
+
+    let mut padded = vec![0u8; size - value.len()];
+    padded.extend_from_slice(value);
+
+    Some(padded)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EcdsaPublicKey {
+    curve: EcdsaCurve,
+    point: Vec<u8>,
+}
+
+impl EcdsaPublicKey {
+    // The public key is the uncompressed SEC1 encoding of the curve point.
+    pub fn from_point(curve: EcdsaCurve, point: &[u8]) -> Result<Self, KeyError> {
+        if point.first() != Some(&UNCOMPRESSED_POINT) || point.len() != 1 + 2 * curve.field_size() {
+            return Err(KeyError::InvalidFormat(
+                "ecdsa public key has to be an uncompressed point",
+            ));
+        }
+
+        let valid = match curve {
+            EcdsaCurve::NistP256 => p256::ecdsa::VerifyingKey::from_sec1_bytes(point).is_ok(),
+            EcdsaCurve::NistP384 => p384::ecdsa::VerifyingKey::from_sec1_bytes(point).is_ok(),
+            EcdsaCurve::NistP521 => p521::ecdsa::VerifyingKey::from_sec1_bytes(point).is_ok(),
+        };
+        if !valid {
+            return Err(KeyError::InvalidFormat(
+                "ecdsa public key is not a curve point",
+            ));
+        }
+
+        Ok(Self {
+            curve,
+            point: point.to_vec(),
+        })
+    }
+
+    pub fn from_blob(blob: &[u8]) -> Result<Self, KeyError> {
+        let mut reader = Reader::new(blob);
+
+        let curve = EcdsaCurve::from_algorithm(reader.read_utf8()?)
+            .ok_or(KeyError::InvalidFormat("key blob is not an ecdsa key"))?;
+        if reader.read_utf8()? != curve.identifier() {
+            return Err(KeyError::InvalidFormat(
+                "ecdsa curve does not match the key algorithm",
+            ));
+        }
+
+        let key = Self::from_point(curve, reader.read_string()?)?;
+        reader.finish()?;
+
+        Ok(key)
+    }
+
+    pub fn get_curve(&self) -> EcdsaCurve {
+        self.curve
+    }
+
+    pub fn get_point(&self) -> &[u8] {
+        &self.point
+    }
+
+    // The signature blob holds r and s as mpints, RFC 5656 section 3.1.2.
+    fn signature_bytes(&self, signature: &Signature) -> Result<Vec<u8>, SignatureError> {
+        let mut reader = Reader::new(signature.get_bytes());
+        let r = reader.read_mpint()?;
+        let s = reader.read_mpint()?;
+        reader.finish()?;
+
+        let invalid = || SignatureError::InvalidFormat("ecdsa signature is longer than the field");
+        let mut bytes = pad_to_field(r, self.curve).ok_or_else(invalid)?;
+        bytes.extend(pad_to_field(s, self.curve).ok_or_else(invalid)?);
+
+        Ok(bytes)
+    }
+}
+
+impl PublicKey for EcdsaPublicKey {
+    fn algorithm(&self) -> &'static str {
+        self.curve.algorithm()
+    }
+
+    fn to_blob(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer
+            .put_string(self.curve.algorithm().as_bytes())
+            .put_string(self.curve.identifier().as_bytes())
+            .put_string(&self.point);
+
+        writer.into_bytes()
+    }
+
+    fn verify(&self, data: &[u8], signature: &Signature) -> Result<(), SignatureError> {
+        if signature.get_algorithm() != self.curve.algorithm() {
+            return Err(SignatureError::AlgorithmMismatch(
+                signature.get_algorithm().to_string(),
+            ));
+        }
+
+        let bytes = self.signature_bytes(signature)?;
+
+        let verify = || -> Result<(), p256::ecdsa::Error> {
+            match self.curve {
+                EcdsaCurve::NistP256 => {
+                    let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(&self.point)?;
+                    key.verify(data, &p256::ecdsa::Signature::from_slice(&bytes)?)
+                }
+                EcdsaCurve::NistP384 => {
+                    let key = p384::ecdsa::VerifyingKey::from_sec1_bytes(&self.point)?;
+                    key.verify(data, &p384::ecdsa::Signature::from_slice(&bytes)?)
+                }
+                EcdsaCurve::NistP521 => {
+                    let key = p521::ecdsa::VerifyingKey::from_sec1_bytes(&self.point)?;
+                    key.verify(data, &p521::ecdsa::Signature::from_slice(&bytes)?)
+                }
+            }
+        };
+
+        verify().map_err(|_| SignatureError::VerificationFailed)
+    }
+}
+
+enum SigningKey {
+    NistP256(p256::ecdsa::SigningKey),
+    NistP384(p384::ecdsa::SigningKey),
+    NistP521(p521::ecdsa::SigningKey),
+}
+
+// The private scalar is wiped on drop by the `ecdsa` crate. Signatures are
+// deterministic, as described in RFC 6979, except for nistp521 where `p521`
+// only supports a random nonce taken from the operating system.
+pub struct EcdsaPrivateKey {
+    key: SigningKey,
+}
+
+impl EcdsaPrivateKey {
+    // The private key is the big endian scalar, leading zeros may be
+    // missing as when it is stored as an mpint.
+    pub fn from_scalar(curve: EcdsaCurve, scalar: &[u8]) -> Result<Self, KeyError> {
+        let invalid = || KeyError::InvalidFormat("ecdsa private key is not a valid scalar");
+        let scalar = Zeroizing::new(pad_to_field(scalar, curve).ok_or_else(invalid)?);
+
+        let key = match curve {
+            EcdsaCurve::NistP256 => SigningKey::NistP256(
+                p256::ecdsa::SigningKey::from_slice(&scalar).map_err(|_| invalid())?,
+            ),
+            EcdsaCurve::NistP384 => SigningKey::NistP384(
+                p384::ecdsa::SigningKey::from_slice(&scalar).map_err(|_| invalid())?,
+            ),
+            EcdsaCurve::NistP521 => SigningKey::NistP521(
+                p521::ecdsa::SigningKey::from_slice(&scalar).map_err(|_| invalid())?,
+            ),
+        };
+
+        Ok(Self { key })
+    }
+
+    pub fn generate<R>(curve: EcdsaCurve, mut rng: &mut R) -> Self
+    where
+        R: RngCore + CryptoRng + ?Sized,
+    {
+        let key = match curve {
+            EcdsaCurve::NistP256 => SigningKey::NistP256(p256::ecdsa::SigningKey::random(&mut rng)),
+            EcdsaCurve::NistP384 => SigningKey::NistP384(p384::ecdsa::SigningKey::random(&mut rng)),
+            EcdsaCurve::NistP521 => SigningKey::NistP521(p521::ecdsa::SigningKey::random(&mut rng)),
+        };
+
+        Self { key }
+    }
+
+    pub fn get_curve(&self) -> EcdsaCurve {
+        match self.key {
+            SigningKey::NistP256(_) => EcdsaCurve::NistP256,
+            SigningKey::NistP384(_) => EcdsaCurve::NistP384,
+            SigningKey::NistP521(_) => EcdsaCurve::NistP521,
+        }
+    }
+
+    // Big endian scalar, padded to the field size.
+    pub fn to_scalar(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(match &self.key {
+            SigningKey::NistP256(key) => key.to_bytes().to_vec(),
+            SigningKey::NistP384(key) => key.to_bytes().to_vec(),
+            SigningKey::NistP521(key) => key.to_bytes().to_vec(),
+        })
+    }
+
+    pub fn public(&self) -> EcdsaPublicKey {
+        let point = match &self.key {
+            SigningKey::NistP256(key) => key.verifying_key().to_encoded_point(false).to_bytes(),
+            SigningKey::NistP384(key) => key.verifying_key().to_encoded_point(false).to_bytes(),
+            SigningKey::NistP521(key) => p521::ecdsa::VerifyingKey::from(key)
+                .to_encoded_point(false)
+                .to_bytes(),
+        };
+
+        EcdsaPublicKey {
+            curve: self.get_curve(),
+            point: point.to_vec(),
+        }
+    }
+}
+
+impl Signer for EcdsaPrivateKey {
+    fn public_key(&self) -> Box<dyn PublicKey> {
+        Box::new(self.public())
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<Signature, SignatureError> {
+        let bytes = match &self.key {
+            SigningKey::NistP256(key) => key
+                .try_sign(data)
+                .map(|s: p256::ecdsa::Signature| s.to_vec()),
+            SigningKey::NistP384(key) => key
+                .try_sign(data)
+                .map(|s: p384::ecdsa::Signature| s.to_vec()),
+            SigningKey::NistP521(key) => key
+                .try_sign(data)
+                .map(|s: p521::ecdsa::Signature| s.to_vec()),
+        }
+        .map_err(|_| SignatureError::InvalidFormat("data cannot be signed with the key"))?;
+
+        let (r, s) = bytes.split_at(bytes.len() / 2);
+        let mut writer = Writer::new();
+        writer.put_mpint(r).put_mpint(s);
+
+        Ok(Signature::new(
+            self.get_curve().algorithm(),
+            writer.into_bytes(),
+        ))
+    }
+}
+
+impl fmt::Debug for EcdsaPrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EcdsaPrivateKey")
+            .field("public", &self.public())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::parse_public_key;
+
+    // RFC 6979, appendix A.2.5 to A.2.7, with the message "sample". The key
+    // blobs were cross-checked with OpenSSH.
+    const P256_SCALAR: &str = "c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721";
+    const P256_BLOB: &str = concat!(
+        "0000001365636473612d736861322d6e69737470323536000000086e697374703235360000004104",
+        "60fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb67903fe1008b8bc99",
+        "a41ae9e95628bc64f2f1b20c2d7e9f5177a3c294d4462299",
+    );
+    const P256_R: &str = "efd48b2aacb6a8fd1140dd9cd45e81d69d2c877b56aaf991c34d0ea84eaf3716";
+    const P256_S: &str = "f7cb1c942d657c41d436c7a1b6e29f65f3e900dbb9aff4064dc4ab2f843acda8";
+    const P384_SCALAR: &str = concat!(
+        "6b9d3dad2e1b8c1c05b19875b6659f4de23c3b667bf297ba9aa47740787137d896d5724e4c70a825",
+        "f872c9ea60d2edf5",
+    );
+    const P384_BLOB: &str = concat!(
+        "0000001365636473612d736861322d6e69737470333834000000086e697374703338340000006104",
+        "ec3a4e415b4e19a4568618029f427fa5da9a8bc4ae92e02e06aae5286b300c64def8f0ea90558660",
+        "64a254515480bc138015d9b72d7d57244ea8ef9ac0c621896708a59367f9dfb9f54ca84b3f1c9db1",
+        "288b231c3ae0d4fe7344fd2533264720",
+    );
+    const P384_R: &str = concat!(
+        "94edbb92a5ecb8aad4736e56c691916b3f88140666ce9fa73d64c4ea95ad133c81a648152e44acf9",
+        "6e36dd1e80fabe46",
+    );
+    const P384_S: &str = concat!(
+        "99ef4aeb15f178cea1fe40db2603138f130e740a19624526203b6351d0a3a94fa329c145786e679e",
+        "7b82c71a38628ac8",
+    );
+    const P521_SCALAR: &str = concat!(
+        "00fad06daa62ba3b25d2fb40133da757205de67f5bb0018fee8c86e1b68c7e75caa896eb32f1f47c",
+        "70855836a6d16fcc1466f6d8fbec67db89ec0c08b0e996b83538",
+    );
+    const P521_BLOB: &str = concat!(
+        "0000001365636473612d736861322d6e69737470353231000000086e697374703532310000008504",
+        "01894550d0785932e00eaa23b694f213f8c3121f86dc97a04e5a7167db4e5bcd371123d46e45db6b",
+        "5d5370a7f20fb633155d38ffa16d2bd761dcac474b9a2f5023a400493101c962cd4d2fddf782285e",
+        "64584139c2f91b47f87ff82354d6630f746a28a0db25741b5b34a828008b22acc23f924faafbd4d3",
+        "3f81ea66956dfeaa2bfdfcf5",
+    );
+    const P521_R: &str = concat!(
+        "00c328fafcbd79dd77850370c46325d987cb525569fb63c5d3bc53950e6d4c5f174e25a1ee9017b5",
+        "d450606add152b534931d7d4e8455cc91f9b15bf05ec36e377fa",
+    );
+    const P521_S: &str = concat!(
+        "00617cce7cf5064806c467f678d3b4080d6f1cc50af26ca209417308281b68af282623eaa63e5b5c",
+        "0723d8b8c37ff0777b1a20f8ccb1dccc43997f1ee0e44da4a67a",
+    );
+
+    fn hex(value: &str) -> Vec<u8> {
+        (0..value.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&value[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn vectors() -> [(
+        EcdsaCurve,
+        &'static str,
+        &'static str,
+        &'static str,
+        &'static str,
+    ); 3] {
+        [
+            (EcdsaCurve::NistP256, P256_SCALAR, P256_BLOB, P256_R, P256_S),
+            (EcdsaCurve::NistP384, P384_SCALAR, P384_BLOB, P384_R, P384_S),
+            (EcdsaCurve::NistP521, P521_SCALAR, P521_BLOB, P521_R, P521_S),
+        ]
+    }
+
+    fn signature(curve: EcdsaCurve, r: &str, s: &str) -> Signature {
+        let mut writer = Writer::new();
+        writer.put_mpint(&hex(r)).put_mpint(&hex(s));
+
+        Signature::new(curve.algorithm(), writer.into_bytes())
+    }
+
+    #[test]
+    fn test_sign_matches_rfc_6979() {
+        for (curve, scalar, blob, r, s) in &vectors()[..2] {
+            let key = EcdsaPrivateKey::from_scalar(*curve, &hex(scalar)).unwrap();
+
+            let signed = key.sign(b"sample").unwrap();
+
+            assert_eq!(signed, signature(*curve, r, s), "{}", curve.algorithm());
+            assert_eq!(key.public().to_blob(), hex(blob), "{}", curve.algorithm());
+        }
+    }
+
+    #[test]
+    fn test_nistp521_signature_verifies() {
+        let key = EcdsaPrivateKey::from_scalar(EcdsaCurve::NistP521, &hex(P521_SCALAR)).unwrap();
+
+        let signed = key.sign(b"sample").unwrap();
+
+        assert_eq!(signed.get_algorithm(), NISTP521);
+        assert_eq!(key.public().to_blob(), hex(P521_BLOB));
+        assert!(key.public().verify(b"sample", &signed).is_ok());
+    }
+
+    #[test]
+    fn test_public_key_blob_roundtrip() {
+        for (curve, _, blob, _, _) in vectors() {
+            let key = EcdsaPublicKey::from_blob(&hex(blob)).unwrap();
+
+            assert_eq!(key.get_curve(), curve);
+            assert_eq!(key.get_point().len(), 1 + 2 * curve.field_size());
+            assert_eq!(key.to_blob(), hex(blob));
+            assert_eq!(
+                parse_public_key(&hex(blob)).unwrap().algorithm(),
+                curve.algorithm()
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify() {
+        for (curve, _, blob, r, s) in vectors() {
+            let key = EcdsaPublicKey::from_blob(&hex(blob)).unwrap();
+            let signature = signature(curve, r, s);
+
+            assert!(
+                key.verify(b"sample", &signature).is_ok(),
+                "{}",
+                curve.algorithm()
+            );
+            assert_eq!(
+                key.verify(b"test", &signature).unwrap_err().to_string(),
+                "signature verification failed"
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_rejects_signature_of_other_curve() {
+        let key = EcdsaPublicKey::from_blob(&hex(P256_BLOB)).unwrap();
+        let other = signature(EcdsaCurve::NistP384, P384_R, P384_S);
+        let mut long = signature(EcdsaCurve::NistP384, P384_R, P384_S);
+        long = Signature::new(NISTP256, long.get_bytes().to_vec());
+
+        let other_err = key.verify(b"sample", &other).unwrap_err().to_string();
+        let long_err = key.verify(b"sample", &long).unwrap_err().to_string();
+
+        assert_eq!(
+            other_err,
+            "signature algorithm does not match the key: (ecdsa-sha2-nistp384)"
+        );
+        assert_eq!(
+            long_err,
+            "signature has invalid format: (ecdsa signature is longer than the field)"
+        );
+    }
+
+    #[test]
+    fn test_invalid_blobs() {
+        let mut curve = Writer::new();
+        curve
+            .put_string(NISTP256.as_bytes())
+            .put_string(b"nistp384")
+            .put_string(&[4; 65]);
+        let mut compressed = Writer::new();
+        compressed
+            .put_string(NISTP256.as_bytes())
+            .put_string(b"nistp256")
+            .put_string(&[2; 33]);
+        let mut off_curve = Writer::new();
+        off_curve
+            .put_string(NISTP256.as_bytes())
+            .put_string(b"nistp256")
+            .put_string(&[4; 65]);
+
+        let errors: Vec<String> = [curve, compressed, off_curve]
+            .into_iter()
+            .map(|blob| {
+                EcdsaPublicKey::from_blob(&blob.into_bytes())
+                    .unwrap_err()
+                    .to_string()
+            })
+            .collect();
+
+        assert_eq!(
+            errors,
+            [
+                "key has invalid format: (ecdsa curve does not match the key algorithm)",
+                "key has invalid format: (ecdsa public key has to be an uncompressed point)",
+                "key has invalid format: (ecdsa public key is not a curve point)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_scalar() {
+        let zero = EcdsaPrivateKey::from_scalar(EcdsaCurve::NistP256, &[0; 32]);
+        let long = EcdsaPrivateKey::from_scalar(EcdsaCurve::NistP256, &[1; 33]);
+
+        for result in [zero, long] {
+            assert_eq!(
+                result.unwrap_err().to_string(),
+                "key has invalid format: (ecdsa private key is not a valid scalar)"
+            );
+        }
+    }
+
+    #[test]
+    fn test_debug_does_not_expose_the_scalar() {
+        let key = EcdsaPrivateKey::from_scalar(EcdsaCurve::NistP256, &hex(P256_SCALAR)).unwrap();
+
+        let debug = format!("{:?}", key);
+
+        assert!(debug.starts_with("EcdsaPrivateKey { public: "));
+        assert!(!debug.contains("201, 175, 169"));
+    }
+}
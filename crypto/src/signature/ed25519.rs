@@ -0,0 +1,286 @@
+use super::errors::{KeyError, SignatureError};
+use super::{PublicKey, Signature, Signer};
+use crate::encoding::{Reader, Writer};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use rand_core::{CryptoRng, RngCore};
+use std::fmt;
+use zeroize::Zeroizing;
+
+pub const ALGORITHM: &str = "ssh-ed25519";
+const KEY_SIZE: usize = 32;
+const SIGNATURE_SIZE: usize = 64;
+
+// ssh-ed25519 as described in RFC 8709. The key blob holds the 32 byte
+// public key and the signature is the plain 64 byte Ed25519 signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ed25519PublicKey {
+    key: VerifyingKey,
+}
+
+impl Ed25519PublicKey {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, KeyError> {
+        let bytes: &[u8; KEY_SIZE] = bytes
+            .try_into()
+            .map_err(|_| KeyError::InvalidFormat("ed25519 public key has to have 32 bytes"))?;
+
+        let key = VerifyingKey::from_bytes(bytes)
+            .map_err(|_| KeyError::InvalidFormat("ed25519 public key is not a curve point"))?;
+
+        Ok(Self { key })
+    }
+
+    pub fn from_blob(blob: &[u8]) -> Result<Self, KeyError> {
+        let mut reader = Reader::new(blob);
+
+        if reader.read_utf8()? != ALGORITHM {
+            return Err(KeyError::InvalidFormat("key blob is not an ed25519 key"));
+        }
+
+        let key = Self::from_bytes(reader.read_string()?)?;
+        reader.finish()?;
+
+        Ok(key)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; KEY_SIZE] {
+        self.key.as_bytes()
+    }
+}
+
+impl PublicKey for Ed25519PublicKey {
+    fn algorithm(&self) -> &'static str {
+        ALGORITHM
+    }
+
+    fn to_blob(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer
+            .put_string(ALGORITHM.as_bytes())
+            .put_string(self.key.as_bytes());
+
+        writer.into_bytes()
+    }
+
+    fn verify(&self, data: &[u8], signature: &Signature) -> Result<(), SignatureError> {
+        if signature.get_algorithm() != ALGORITHM {
+            return Err(SignatureError::AlgorithmMismatch(
+                signature.get_algorithm().to_string(),
+            ));
+        }
+
+        let bytes: &[u8; SIGNATURE_SIZE] = signature
+            .get_bytes()
+            .try_into()
+            .map_err(|_| SignatureError::InvalidFormat("ed25519 signature has to have 64 bytes"))?;
+
+        self.key
+            .verify_strict(data, &ed25519_dalek::Signature::from_bytes(bytes))
+            .map_err(|_| SignatureError::VerificationFailed)
+    }
+}
+
+// The secret key is wiped on drop by `ed25519_dalek`.
+pub struct Ed25519PrivateKey {
+    key: SigningKey,
+}
+
+impl Ed25519PrivateKey {
+    // The private key is the 32 byte seed, as in RFC 8032.
+    pub fn from_seed(seed: &[u8]) -> Result<Self, KeyError> {
+        let seed: &[u8; KEY_SIZE] = seed
+            .try_into()
+            .map_err(|_| KeyError::InvalidFormat("ed25519 seed has to have 32 bytes"))?;
+
+        Ok(Self {
+            key: SigningKey::from_bytes(seed),
+        })
+    }
+
+    pub fn generate<R>(rng: &mut R) -> Self
+    where
+        R: RngCore + CryptoRng + ?Sized,
+    {
+        let mut seed = Zeroizing::new([0u8; KEY_SIZE]);
+        rng.fill_bytes(seed.as_mut());
+
+        Self {
+            key: SigningKey::from_bytes(&seed),
+        }
+    }
+
+    pub fn public(&self) -> Ed25519PublicKey {
+        Ed25519PublicKey {
+            key: self.key.verifying_key(),
+        }
+    }
+
+    pub fn to_seed(&self) -> Zeroizing<[u8; KEY_SIZE]> {
+        Zeroizing::new(self.key.to_bytes())
+    }
+}
+
+impl Signer for Ed25519PrivateKey {
+    fn public_key(&self) -> Box<dyn PublicKey> {
+        Box::new(self.public())
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<Signature, SignatureError> {
+        use ed25519_dalek::Signer as _;
+
+        Ok(Signature::new(
+            ALGORITHM,
+            self.key.sign(data).to_bytes().to_vec(),
+        ))
+    }
+}
+
+impl fmt::Debug for Ed25519PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ed25519PrivateKey")
+            .field("public", &self.public())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::parse_public_key;
+
+    // RFC 8032, section 7.1, test 1.
+    const SEED: [u8; 32] = [
+        0x9d, 0x61, 0xb1, 0x9d, 0xef, 0xfd, 0x5a, 0x60, 0xba, 0x84, 0x4a, 0xf4, 0x92, 0xec, 0x2c,
+        0xc4, 0x44, 0x49, 0xc5, 0x69, 0x7b, 0x32, 0x69, 0x19, 0x70, 0x3b, 0xac, 0x03, 0x1c, 0xae,
+        0x7f, 0x60,
+    ];
+    const PUBLIC_KEY: [u8; 32] = [
+        0xd7, 0x5a, 0x98, 0x01, 0x82, 0xb1, 0x0a, 0xb7, 0xd5, 0x4b, 0xfe, 0xd3, 0xc9, 0x64, 0x07,
+        0x3a, 0x0e, 0xe1, 0x72, 0xf3, 0xda, 0xa6, 0x23, 0x25, 0xaf, 0x02, 0x1a, 0x68, 0xf7, 0x07,
+        0x51, 0x1a,
+    ];
+    const SIGNATURE: [u8; 64] = [
+        0xe5, 0x56, 0x43, 0x00, 0xc3, 0x60, 0xac, 0x72, 0x90, 0x86, 0xe2, 0xcc, 0x80, 0x6e, 0x82,
+        0x8a, 0x84, 0x87, 0x7f, 0x1e, 0xb8, 0xe5, 0xd9, 0x74, 0xd8, 0x73, 0xe0, 0x65, 0x22, 0x49,
+        0x01, 0x55, 0x5f, 0xb8, 0x82, 0x15, 0x90, 0xa3, 0x3b, 0xac, 0xc6, 0x1e, 0x39, 0x70, 0x1c,
+        0xf9, 0xb4, 0x6b, 0xd2, 0x5b, 0xf5, 0xf0, 0x59, 0x5b, 0xbe, 0x24, 0x65, 0x51, 0x41, 0x43,
+        0x8e, 0x7a, 0x10, 0x0b,
+    ];
+
+    // The RFC 8032 public key in the OpenSSH key blob format, as accepted
+    // by ssh-keygen.
+    fn blob() -> Vec<u8> {
+        let mut blob = vec![0, 0, 0, 11];
+        blob.extend_from_slice(b"ssh-ed25519");
+        blob.extend_from_slice(&[0, 0, 0, 32]);
+        blob.extend_from_slice(&PUBLIC_KEY);
+        blob
+    }
+
+    #[test]
+    fn test_sign_matches_rfc_8032() {
+        let key = Ed25519PrivateKey::from_seed(&SEED).unwrap();
+
+        let signature = key.sign(b"").unwrap();
+
+        assert_eq!(signature.get_algorithm(), "ssh-ed25519");
+        assert_eq!(signature.get_bytes(), SIGNATURE);
+        assert_eq!(key.public().as_bytes(), &PUBLIC_KEY);
+    }
+
+    #[test]
+    fn test_public_key_blob_roundtrip() {
+        let key = Ed25519PublicKey::from_blob(&blob()).unwrap();
+
+        assert_eq!(key.as_bytes(), &PUBLIC_KEY);
+        assert_eq!(key.to_blob(), blob());
+        assert_eq!(parse_public_key(&blob()).unwrap().to_blob(), blob());
+    }
+
+    #[test]
+    fn test_verify() {
+        let key = Ed25519PublicKey::from_blob(&blob()).unwrap();
+        let signature = Signature::new(ALGORITHM, SIGNATURE.to_vec());
+
+        assert!(key.verify(b"", &signature).is_ok());
+        assert_eq!(
+            key.verify(b"parustiko", &signature)
+                .unwrap_err()
+                .to_string(),
+            "signature verification failed"
+        );
+    }
+
+    #[test]
+    fn test_verify_signature_from_blob() {
+        let signer = Ed25519PrivateKey::from_seed(&[7u8; 32]).unwrap();
+        let blob = signer.sign(b"exchange hash").unwrap().to_blob();
+
+        let signature = Signature::from_blob(&blob).unwrap();
+
+        assert!(signer
+            .public_key()
+            .verify(b"exchange hash", &signature)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_verify_rejects_other_algorithms() {
+        let key = Ed25519PublicKey::from_blob(&blob()).unwrap();
+        let signature = Signature::new("rsa-sha2-256", SIGNATURE.to_vec());
+
+        let err = key.verify(b"", &signature).unwrap_err().to_string();
+
+        assert_eq!(
+            err,
+            "signature algorithm does not match the key: (rsa-sha2-256)"
+        );
+    }
+
+    #[test]
+    fn test_verify_rejects_truncated_signature() {
+        let key = Ed25519PublicKey::from_blob(&blob()).unwrap();
+        let signature = Signature::new(ALGORITHM, SIGNATURE[..63].to_vec());
+
+        let err = key.verify(b"", &signature).unwrap_err().to_string();
+
+        assert_eq!(
+            err,
+            "signature has invalid format: (ed25519 signature has to have 64 bytes)"
+        );
+    }
+
+    #[test]
+    fn test_invalid_blobs() {
+        let mut long_key = blob();
+        long_key[18] = 33;
+        long_key.push(0);
+        let mut trailing = blob();
+        trailing.push(0);
+
+        let long_key_err = Ed25519PublicKey::from_blob(&long_key)
+            .unwrap_err()
+            .to_string();
+        let trailing_err = Ed25519PublicKey::from_blob(&trailing)
+            .unwrap_err()
+            .to_string();
+
+        assert_eq!(
+            long_key_err,
+            "key has invalid format: (ed25519 public key has to have 32 bytes)"
+        );
+        assert_eq!(
+            trailing_err,
+            "decoding key failed: (trailing data after the last field)"
+        );
+    }
+
+    #[test]
+    fn test_debug_does_not_expose_the_seed() {
+        let key = Ed25519PrivateKey::from_seed(&SEED).unwrap();
+
+        let debug = format!("{:?}", key);
+
+        assert!(debug.starts_with("Ed25519PrivateKey { public: "));
+        assert!(!debug.contains("157, 97, 177"));
+    }
+}
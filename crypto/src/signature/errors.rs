@@ -0,0 +1,80 @@
+use crate::encoding::errors::DecodingError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum KeyError {
+    #[error("key has invalid format: ({0})")]
+    InvalidFormat(&'static str),
+
+    #[error("unsupported key algorithm: ({0})")]
+    UnsupportedAlgorithm(String),
+
+    #[error("rsa modulus is smaller than allowed by the policy: ({0} bits)")]
+    ModulusTooSmall(usize),
+
+    #[error("unsupported rsa modulus size: ({0} bits)")]
+    UnsupportedModulusSize(usize),
+
+    #[error("invalid certificate: ({0})")]
+    InvalidCertificate(String),
+
+    #[error("decoding key failed: ({0})")]
+    DecodingFailed(#[from] DecodingError),
+}
+
+#[derive(Debug, Error)]
+pub enum SignatureError {
+    #[error("signature has invalid format: ({0})")]
+    InvalidFormat(&'static str),
+
+    #[error("signature algorithm does not match the key: ({0})")]
+    AlgorithmMismatch(String),
+
+    #[error("signature algorithm is not allowed by the policy: ({0})")]
+    DisallowedAlgorithm(String),
+
+    #[error("signature verification failed")]
+    VerificationFailed,
+
+    #[error("signing with an external signer failed: ({0})")]
+    SigningFailed(String),
+
+    #[error("decoding signature failed: ({0})")]
+    DecodingFailed(#[from] DecodingError),
+}
+
+#[derive(Debug, Error)]
+pub enum CertificateError {
+    #[error("certificate has invalid format: ({0})")]
+    InvalidFormat(&'static str),
+
+    #[error("certificate key is invalid: ({0})")]
+    InvalidKey(#[from] KeyError),
+
+    #[error("certificate signature is invalid: ({0})")]
+    InvalidSignature(#[from] SignatureError),
+
+    #[error("certificate has the wrong type: ({0})")]
+    WrongType(&'static str),
+
+    #[error("certificate is not valid yet")]
+    NotYetValid,
+
+    #[error("certificate has expired")]
+    Expired,
+
+    #[error("principal is not allowed by the certificate: ({0})")]
+    PrincipalNotAllowed(String),
+
+    #[error("unsupported critical option: ({0})")]
+    UnsupportedCriticalOption(String),
+
+    #[error("source address is not allowed by the certificate: ({0})")]
+    SourceAddressNotAllowed(String),
+
+    #[error("certificate is not signed by a trusted authority")]
+    UntrustedAuthority,
+
+    #[error("decoding certificate failed: ({0})")]
+    DecodingFailed(#[from] DecodingError),
+}
@@ -0,0 +1,124 @@
+pub mod certificate;
+pub mod ecdsa;
+pub mod ed25519;
+pub mod errors;
+pub mod rsa;
+
+use crate::encoding::{Reader, Writer};
+use crate::registry::AlgorithmPolicy;
+use errors::{KeyError, SignatureError};
+
+// Signature blob as sent in the key exchange reply and in authentication
+// requests: the signature algorithm name followed by the algorithm
+// specific signature bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    algorithm: String,
+    bytes: Vec<u8>,
+}
+
+impl Signature {
+    pub fn new(algorithm: &str, bytes: Vec<u8>) -> Self {
+        Self {
+            algorithm: algorithm.to_string(),
+            bytes,
+        }
+    }
+
+    pub fn from_blob(blob: &[u8]) -> Result<Self, SignatureError> {
+        let mut reader = Reader::new(blob);
+        let algorithm = reader.read_utf8()?;
+        let bytes = reader.read_string()?;
+        reader.finish()?;
+
+        Ok(Self::new(algorithm, bytes.to_vec()))
+    }
+
+    pub fn to_blob(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer
+            .put_string(self.algorithm.as_bytes())
+            .put_string(&self.bytes);
+
+        writer.into_bytes()
+    }
+
+    pub fn get_algorithm(&self) -> &str {
+        &self.algorithm
+    }
+
+    pub fn get_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+// Public half of a host or user key.
+pub trait PublicKey {
+    // Key type name, which is also the first field of the key blob.
+    fn algorithm(&self) -> &'static str;
+
+    // Key blob in the SSH wire format (RFC 4253, section 6.6).
+    fn to_blob(&self) -> Vec<u8>;
+
+    fn verify(&self, data: &[u8], signature: &Signature) -> Result<(), SignatureError>;
+}
+
+// Private half of a key, used to prove the possession of the key, e.g. by
+// a server signing the exchange hash.
+pub trait Signer {
+    fn public_key(&self) -> Box<dyn PublicKey>;
+
+    fn sign(&self, data: &[u8]) -> Result<Signature, SignatureError>;
+
+    // Algorithm name of the signatures, which differs from the key type
+    // for RSA keys signing with SHA-2 (RFC 8332).
+    fn signature_algorithm(&self) -> &'static str {
+        self.public_key().algorithm()
+    }
+}
+
+// Parse a public key blob received from the peer with the default
+// algorithm policy.
+pub fn parse_public_key(blob: &[u8]) -> Result<Box<dyn PublicKey>, KeyError> {
+    AlgorithmPolicy::default().parse_public_key(blob)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_blob_roundtrip() {
+        let signature = Signature::new("ssh-ed25519", vec![1, 2, 3]);
+        let blob = signature.to_blob();
+
+        assert_eq!(blob[..4], [0, 0, 0, 11]);
+        assert_eq!(Signature::from_blob(&blob).unwrap(), signature);
+    }
+
+    #[test]
+    fn test_signature_blob_with_trailing_data() {
+        let mut blob = Signature::new("ssh-ed25519", vec![1, 2, 3]).to_blob();
+        blob.push(0);
+
+        let err = Signature::from_blob(&blob).unwrap_err().to_string();
+
+        assert_eq!(
+            err,
+            "decoding signature failed: (trailing data after the last field)"
+        );
+    }
+
+    #[test]
+    fn test_parse_unsupported_public_key() {
+        let mut writer = Writer::new();
+        writer.put_string(b"ssh-dss").put_string(&[1, 2, 3]);
+
+        let err = parse_public_key(&writer.into_bytes())
+            .err()
+            .unwrap()
+            .to_string();
+
+        assert_eq!(err, "unsupported key algorithm: (ssh-dss)");
+    }
+}
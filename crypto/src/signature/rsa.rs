@@ -0,0 +1,527 @@
+use super::errors::{KeyError, SignatureError};
+use super::{PublicKey, Signature, Signer};
+use crate::encoding::{Reader, Writer};
+use crate::registry::{AlgorithmPolicy, DEFAULT_MIN_RSA_MODULUS_BITS};
+use rand_core::{CryptoRng, RngCore};
+use rsa::traits::PublicKeyParts;
+use rsa::{BigUint, Pkcs1v15Sign};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use std::fmt;
+
+pub const ALGORITHM: &str = "ssh-rsa";
+// OpenSSH refuses larger keys as well.
+const MAX_MODULUS_BITS: usize = 16384;
+
+// Signature algorithms of ssh-rsa keys, RFC 8332. The key blob is the same
+// for all of them, only the hash used by the PKCS #1 v1.5 signature differs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RsaHash {
+    // The original ssh-rsa signature, which is only accepted when explicitly
+    // allowed by the policy.
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl RsaHash {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sha1 => "ssh-rsa",
+            Self::Sha256 => "rsa-sha2-256",
+            Self::Sha512 => "rsa-sha2-512",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ssh-rsa" => Some(Self::Sha1),
+            "rsa-sha2-256" => Some(Self::Sha256),
+            "rsa-sha2-512" => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+
+    fn digest(&self, data: &[u8]) -> (Pkcs1v15Sign, Vec<u8>) {
+        match self {
+            Self::Sha1 => (Pkcs1v15Sign::new::<Sha1>(), Sha1::digest(data).to_vec()),
+            Self::Sha256 => (Pkcs1v15Sign::new::<Sha256>(), Sha256::digest(data).to_vec()),
+            Self::Sha512 => (Pkcs1v15Sign::new::<Sha512>(), Sha512::digest(data).to_vec()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RsaPublicKey {
+    key: rsa::RsaPublicKey,
+    allow_sha1: bool,
+}
+
+impl RsaPublicKey {
+    // Build the key from the big endian public exponent and modulus, making
+    // sure the modulus is within the limits of the policy.
+    pub fn from_components(e: &[u8], n: &[u8], policy: &AlgorithmPolicy) -> Result<Self, KeyError> {
+        let n = BigUint::from_bytes_be(n);
+
+        if n.bits() < policy.min_rsa_modulus_bits {
+            return Err(KeyError::ModulusTooSmall(n.bits()));
+        }
+
+        let key =
+            rsa::RsaPublicKey::new_with_max_size(n, BigUint::from_bytes_be(e), MAX_MODULUS_BITS)
+                .map_err(|_| KeyError::InvalidFormat("rsa public key parameters are invalid"))?;
+
+        Ok(Self {
+            key,
+            allow_sha1: policy.allow_sha1_signatures,
+        })
+    }
+
+    pub fn from_blob(blob: &[u8], policy: &AlgorithmPolicy) -> Result<Self, KeyError> {
+        let mut reader = Reader::new(blob);
+
+        if reader.read_utf8()? != ALGORITHM {
+            return Err(KeyError::InvalidFormat("key blob is not an rsa key"));
+        }
+
+        let e = reader.read_mpint()?;
+        let n = reader.read_mpint()?;
+        reader.finish()?;
+
+        Self::from_components(e, n, policy)
+    }
+
+    pub fn modulus_bits(&self) -> usize {
+        self.key.n().bits()
+    }
+
+    // Accept ssh-rsa signatures, like a key parsed with a policy allowing
+    // them.
+    pub fn with_sha1_signatures(mut self, allowed: bool) -> Self {
+        self.allow_sha1 = allowed;
+        self
+    }
+}
+
+// Keys are the same whatever signatures they accept.
+impl PartialEq for RsaPublicKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.key.n() == other.key.n() && self.key.e() == other.key.e()
+    }
+}
+
+impl Eq for RsaPublicKey {}
+
+impl PublicKey for RsaPublicKey {
+    fn algorithm(&self) -> &'static str {
+        ALGORITHM
+    }
+
+    fn to_blob(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer
+            .put_string(ALGORITHM.as_bytes())
+            .put_mpint(&self.key.e().to_bytes_be())
+            .put_mpint(&self.key.n().to_bytes_be());
+
+        writer.into_bytes()
+    }
+
+    fn verify(&self, data: &[u8], signature: &Signature) -> Result<(), SignatureError> {
+        let hash = RsaHash::from_name(signature.get_algorithm()).ok_or_else(|| {
+            SignatureError::AlgorithmMismatch(signature.get_algorithm().to_string())
+        })?;
+
+        if hash == RsaHash::Sha1 && !self.allow_sha1 {
+            return Err(SignatureError::DisallowedAlgorithm(hash.name().to_string()));
+        }
+
+        // Some implementations strip leading zeros of the signature, which
+        // has to be as long as the modulus otherwise.
+        let size = self.key.size();
+        let bytes = signature.get_bytes();
+        if bytes.len() > size {
+            return Err(SignatureError::InvalidFormat(
+                "rsa signature is longer than the modulus",
+            ));
+        }
+        let mut padded = vec![0u8; size - bytes.len()];
+        padded.extend_from_slice(bytes);
+
+        let (scheme, hashed) = hash.digest(data);
+        self.key
+            .verify(scheme, &hashed, &padded)
+            .map_err(|_| SignatureError::VerificationFailed)
+    }
+}
+
+// The private key is wiped on drop by the `rsa` crate.
+pub struct RsaPrivateKey {
+    key: rsa::RsaPrivateKey,
+    hash: RsaHash,
+}
+
+impl RsaPrivateKey {
+    // Build the key from its big endian components. Signatures are made
+    // with rsa-sha2-512 unless changed with `set_hash`.
+    pub fn from_components(
+        n: &[u8],
+        e: &[u8],
+        d: &[u8],
+        p: &[u8],
+        q: &[u8],
+    ) -> Result<Self, KeyError> {
+        let key = rsa::RsaPrivateKey::from_components(
+            BigUint::from_bytes_be(n),
+            BigUint::from_bytes_be(e),
+            BigUint::from_bytes_be(d),
+            vec![BigUint::from_bytes_be(p), BigUint::from_bytes_be(q)],
+        )
+        .map_err(|_| KeyError::InvalidFormat("rsa private key parameters are invalid"))?;
+
+        key.validate()
+            .map_err(|_| KeyError::InvalidFormat("rsa private key parameters are invalid"))?;
+
+        Ok(Self {
+            key,
+            hash: RsaHash::Sha512,
+        })
+    }
+
+    // New key with the public exponent 65537. Keys smaller than the default
+    // policy allows are refused, as they could not be used with it.
+    pub fn generate<R>(bits: usize, mut rng: &mut R) -> Result<Self, KeyError>
+    where
+        R: RngCore + CryptoRng + ?Sized,
+    {
+        if !(DEFAULT_MIN_RSA_MODULUS_BITS..=MAX_MODULUS_BITS).contains(&bits) {
+            return Err(KeyError::UnsupportedModulusSize(bits));
+        }
+
+        let key = rsa::RsaPrivateKey::new(&mut rng, bits)
+            .map_err(|_| KeyError::UnsupportedModulusSize(bits))?;
+
+        Ok(Self {
+            key,
+            hash: RsaHash::Sha512,
+        })
+    }
+
+    // Select the signature algorithm negotiated with the peer.
+    pub fn set_hash(&mut self, hash: RsaHash) {
+        self.hash = hash;
+    }
+
+    pub(crate) fn inner(&self) -> &rsa::RsaPrivateKey {
+        &self.key
+    }
+
+    // SHA-1 signatures are refused by the public key even when the private
+    // key signs with it, the verifier has to opt in.
+    pub fn public(&self) -> RsaPublicKey {
+        RsaPublicKey {
+            key: self.key.to_public_key(),
+            allow_sha1: false,
+        }
+    }
+}
+
+impl Signer for RsaPrivateKey {
+    fn public_key(&self) -> Box<dyn PublicKey> {
+        Box::new(self.public())
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<Signature, SignatureError> {
+        let (scheme, hashed) = self.hash.digest(data);
+        let bytes = self
+            .key
+            .sign(scheme, &hashed)
+            .map_err(|_| SignatureError::InvalidFormat("data cannot be signed with the key"))?;
+
+        Ok(Signature::new(self.hash.name(), bytes))
+    }
+
+    fn signature_algorithm(&self) -> &'static str {
+        self.hash.name()
+    }
+}
+
+impl fmt::Debug for RsaPrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RsaPrivateKey")
+            .field("modulus_bits", &self.key.n().bits())
+            .field("hash", &self.hash)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::parse_public_key;
+
+    // 3072 bit key from `rsa_keys/id_rsa` and its signatures over
+    // "parustiko", cross-checked with OpenSSL.
+    const BLOB: &str = concat!(
+        "000000077373682d7273610000000301000100000181009c132bf90b08be886ccc898fa4f5402a3f",
+        "36cbbc2d16993c0e59ef233403efdb1cdb3c4bda46057b92601971093ff61a47b65cf3a40264b47c",
+        "9ffd43947e3d807bcd7433fb0dce807d63c77d8831f768facfad1aa0d900af2ce76005a270c7fba6",
+        "e934952d49fe9b2db08ccf0e3538491da97db6cd77a71c4183635b105db2a88dd0916b922540bed9",
+        "7db1cb9c93e4ec54cc1f6edd497e052c97198b9dafffa6be792547a2de0ffad02e34c56e3118948b",
+        "f8f42103a78167e7efbab22bc6f8dab8f997ead11df5bcf7b04c92585043735232d25c186746e29f",
+        "a8a13f358cae2fa0f2cd7815d90c627a280a5a1762f15ec2f71294b4febdd2a709fe0fddd4fd9c6e",
+        "57214f3998efd923edfa19fd588cfdc671a17c08ba99dc4f981ae1f7a84d5a93a0fabd7e5d8ae7fb",
+        "0599726458eaaad824cd1982e4a151b17c145b510ce216cc6d965a8bd83c6a88315c1fc4bb78e9e3",
+        "5703654dca4b6021a5d82a433942d3236fad2aadfada326b60d3287848c9a9f61d3134a249ebc160",
+        "99fda7e23ff493",
+    );
+    const N: &str = concat!(
+        "9c132bf90b08be886ccc898fa4f5402a3f36cbbc2d16993c0e59ef233403efdb1cdb3c4bda46057b",
+        "92601971093ff61a47b65cf3a40264b47c9ffd43947e3d807bcd7433fb0dce807d63c77d8831f768",
+        "facfad1aa0d900af2ce76005a270c7fba6e934952d49fe9b2db08ccf0e3538491da97db6cd77a71c",
+        "4183635b105db2a88dd0916b922540bed97db1cb9c93e4ec54cc1f6edd497e052c97198b9dafffa6",
+        "be792547a2de0ffad02e34c56e3118948bf8f42103a78167e7efbab22bc6f8dab8f997ead11df5bc",
+        "f7b04c92585043735232d25c186746e29fa8a13f358cae2fa0f2cd7815d90c627a280a5a1762f15e",
+        "c2f71294b4febdd2a709fe0fddd4fd9c6e57214f3998efd923edfa19fd588cfdc671a17c08ba99dc",
+        "4f981ae1f7a84d5a93a0fabd7e5d8ae7fb0599726458eaaad824cd1982e4a151b17c145b510ce216",
+        "cc6d965a8bd83c6a88315c1fc4bb78e9e35703654dca4b6021a5d82a433942d3236fad2aadfada32",
+        "6b60d3287848c9a9f61d3134a249ebc16099fda7e23ff493",
+    );
+    const D: &str = concat!(
+        "065a4e9053cd1450e21df77d297504f6058bd302476543a853feedfe378ba69efb4ab52fbc120147",
+        "fba5b4ded3fd20ee0657cdc6334bcf5b8e7e7d32b724784e64603a110f951d491b08568b643d1347",
+        "6a7cf1d34337f1fe2f5eb8bf6b1bac61552b7c05270132764762d103de4252dfc14b2fd659b784a9",
+        "1757db0873715f3192079893b630b9b4ef8e38a295c0d7e7de244f24f1a80e013e7b89b8caf6331f",
+        "4f6250fa4a334005431329d4b925ed037dbb0cd84050418e527380937a92b053c9eac2b8840b55e9",
+        "a2ba17c357f45d0e77ac01b5f5b1207800f0932a1e0760e7230f3abe796ef0152a3fae0ff5a1be97",
+        "bb3ca09856d00346442b053ba2f373ae89dc265a28f9fd285bfcffeb1c39d7e3f485a53c562dfb26",
+        "17207e2a943298f2528f594e324997e811d69d05a9fd12d665c67a75d056300262da1d397bc2bd3d",
+        "18857c83df5ea1b6bd49fdf1b892cbe8e1ce50323bfd44fe65693901f589289b01c92d95d4b90d81",
+        "93c3b736f6651af0c65f11b5e4a690c25d398ca444adc321",
+    );
+    const P: &str = concat!(
+        "d747e260509f49a49998aafa7459a4a884b489ede08b5290fdf620b76c5e55f821536671f8b6fc2c",
+        "833860c0a0d33b9353a529509b5df816740be2a40deee14efb021f9c4322a383ba5b8b5913ae86f7",
+        "ce560e50fa5ee0efca89a03f61117dfe72992cfef3eda902d4b0b71340230b9aa8f95d879a0b01f1",
+        "af216099c13234c8be74a7ede46edc941a31c6a17f5d183e53b60a59ec16a2209dc5fa08d2c3c550",
+        "708fa7873b426839ffb8a593828cf05b04ccaecc05efd0b1bc143a818902dff1",
+    );
+    const Q: &str = concat!(
+        "b9987a1fd60b21af7b787f6314a59f696174fe0110881ac30a2679bf0b67e3f6e6ffd0509bcfc88d",
+        "ebd521c5871fa3c692111f33f3c172a446482ed365edba2b7e9e1989adccea9e7c04a4912975a43c",
+        "f5c636eaf37bfda136b47278930c7e4d7043386f681d54f004bef816a85776826c3402583d2a0998",
+        "d95404aaf8495adb770e42912ffe524fcd0126e250b9b4ba33eb868ebe4696c22b9473b2f13c674c",
+        "7b0f224368f4e8fde7e4993137f8e4a15cc91aee92f53ee7a3b54e71876560c3",
+    );
+    const SHA256_SIGNATURE: &str = concat!(
+        "2c5b2973edc01a09c971d5383762566d50b0e0bdc9136669ec5426fde58c41fb3136e115c1b3cb61",
+        "8367aab069456327c918c651627714d3a750c4aad9783196a79239181487e9ff35a5d9aeb3cb2636",
+        "4b40d8629e41549113fc118b1014a03f4ca789202b85f3d6fbe286d686232331cd8fb5d3c7524981",
+        "57d82f80c8fe351a93e5d96f05b2bd6bc793610d656c837c6a7e84244fa6a79cc0f0c2d70cbe1b1b",
+        "5c4afc69084d6683001242406e2876beb710020ca13de3ad0d224675d113a366164882a6446711b1",
+        "aabe561a2c0271ca2bbca69dd8f97cf976d229bfe12ba004f77ef7b6cb27c0c911164007dcc3ebb3",
+        "fcc46ab9d9f2d19a220cc1da0c171044de616665d7c83b6c57acca46f5a4c5f9175f850afaab24f5",
+        "e164ce2e3a43e28c9158225e50f9019373a6424e3f217a68c6bd3368f368edadfae65a6c21bb15bd",
+        "29a101d80ecfc1da72cb4ab312b3077b25acfbe3839829b4a89a478f573227ac5da8959727834729",
+        "a16ccf309ea1402030b0f4522813906f9b3add1918778b22",
+    );
+    const SHA512_SIGNATURE: &str = concat!(
+        "0f0656b0a58ceb70aa404faa30ddab9e438689b431a0b9dba5aa27d55d8cd81392721a0c8c5690e1",
+        "680f20aca8e56337cf3347ab0bd165b6093d9bcaac13ad9692eeab0a52fd895e78746266c621b093",
+        "aa1b55000d3edf9595a9c6560bd8ec689efaa77192c4ef94d67752b267272c8f3a543a93401a0c17",
+        "15a40d2cc5e66876450ec39ac1c308980f075bb834385617c2233b46572461e5add89f8ccaffa0f7",
+        "2e232a2d92c2a77de9ceeb673abd312d9806e385fd111c7542ad5bb9e2db4454f9f87e99205f3e3c",
+        "f6a10fb483a8e58254f515f5382694fb397cdf90a0597da580acaf5044f4fe469c70268533450835",
+        "cf346b21982762cab355a4f70d4666b8291ba859ae7b9fb67b04ddc176725e60115ec0595d841489",
+        "fa693ac6a95498bc6241e14e2bd9b7dc97235dd36074f5f56c4867b4907f77661de9870cfb5d2160",
+        "445fcd46b3252bbe076c46e62dcb06f9a2e6e8c5e9cbf31487f54a89c78d0d4573529f96c7599a63",
+        "df6df10bb2b05a91c27dd33bc0dcf9d68c2f51972f685914",
+    );
+    const SHA1_SIGNATURE: &str = concat!(
+        "939ace94c20054a26541933755d608aa87ad5db78d45d6749183d0a0fc710ece1810b106654f6164",
+        "9d01772b84f6919b929f69d53361df64d6938b13032752c7c3c0ecf3466c002c15a56578a5f65b82",
+        "62ab30df085b86ce9d07086e1ed1e3979437dde7d0bbaea956ff0ddd25215c40436048a8ab96c860",
+        "33da9a99331f8629100602ebbe16e6f32ca6003ebe14bc80a40a6b2bc2ae7454ef790b1948db5b5e",
+        "2d004c8e7869075a569242f8c255b65637d4b6870369522f5ceba987130ab5d60bee3633434cc3ce",
+        "cac6fecd3a8faa9f6c1f90ba4a501de08600799432ba71c244b43b2c091f5c78df4c516b5082d90e",
+        "62184f831b681ec34822acbd7b3a30b02915f9af539bc4354e8602ec57652b2a085a171068f2390a",
+        "0f55a7c5cdfcefdd888318648389cb41c843fd12d8aa4accb84528b808a54cc60a64d2bc53b21a13",
+        "cc627adb7b441918c8440c86be6d9e89339b8f1964ae17abd3bc53447d0bc155e304e5362d602e6b",
+        "4d71772ec8a3bbc3816a1c5a86d26770f2070d0023ffcb3d",
+    );
+
+    fn hex(value: &str) -> Vec<u8> {
+        (0..value.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&value[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn private_key() -> RsaPrivateKey {
+        RsaPrivateKey::from_components(&hex(N), &[1, 0, 1], &hex(D), &hex(P), &hex(Q)).unwrap()
+    }
+
+    fn sha1_policy() -> AlgorithmPolicy {
+        AlgorithmPolicy {
+            allow_sha1_signatures: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_public_key_blob_roundtrip() {
+        let key = RsaPublicKey::from_blob(&hex(BLOB), &AlgorithmPolicy::default()).unwrap();
+
+        assert_eq!(key.modulus_bits(), 3072);
+        assert_eq!(key.to_blob(), hex(BLOB));
+        assert_eq!(parse_public_key(&hex(BLOB)).unwrap().to_blob(), hex(BLOB));
+        assert_eq!(private_key().public().to_blob(), hex(BLOB));
+    }
+
+    #[test]
+    fn test_sign_matches_reference_signatures() {
+        let mut key = private_key();
+
+        let sha512 = key.sign(b"parustiko").unwrap();
+        key.set_hash(RsaHash::Sha256);
+        let sha256 = key.sign(b"parustiko").unwrap();
+        key.set_hash(RsaHash::Sha1);
+        let sha1 = key.sign(b"parustiko").unwrap();
+        assert_eq!(key.signature_algorithm(), "ssh-rsa");
+
+        assert_eq!(sha512.get_algorithm(), "rsa-sha2-512");
+        assert_eq!(sha512.get_bytes(), hex(SHA512_SIGNATURE));
+        assert_eq!(sha256.get_algorithm(), "rsa-sha2-256");
+        assert_eq!(sha256.get_bytes(), hex(SHA256_SIGNATURE));
+        assert_eq!(sha1.get_algorithm(), "ssh-rsa");
+        assert_eq!(sha1.get_bytes(), hex(SHA1_SIGNATURE));
+    }
+
+    #[test]
+    fn test_verify() {
+        let key = RsaPublicKey::from_blob(&hex(BLOB), &AlgorithmPolicy::default()).unwrap();
+        let sha256 = Signature::new("rsa-sha2-256", hex(SHA256_SIGNATURE));
+        let sha512 = Signature::new("rsa-sha2-512", hex(SHA512_SIGNATURE));
+
+        assert!(key.verify(b"parustiko", &sha256).is_ok());
+        assert!(key.verify(b"parustiko", &sha512).is_ok());
+        assert_eq!(
+            key.verify(b"parustik0", &sha512).unwrap_err().to_string(),
+            "signature verification failed"
+        );
+        assert_eq!(
+            key.verify(
+                b"parustiko",
+                &Signature::new("rsa-sha2-512", hex(SHA256_SIGNATURE))
+            )
+            .unwrap_err()
+            .to_string(),
+            "signature verification failed"
+        );
+    }
+
+    #[test]
+    fn test_sha1_signatures_need_opt_in() {
+        let signature = Signature::new("ssh-rsa", hex(SHA1_SIGNATURE));
+        let default = RsaPublicKey::from_blob(&hex(BLOB), &AlgorithmPolicy::default()).unwrap();
+        let opted_in = sha1_policy().parse_public_key(&hex(BLOB)).unwrap();
+
+        let err = default.verify(b"parustiko", &signature).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "signature algorithm is not allowed by the policy: (ssh-rsa)"
+        );
+        assert!(opted_in.verify(b"parustiko", &signature).is_ok());
+    }
+
+    #[test]
+    fn test_public_key_of_sha1_signer_needs_opt_in() {
+        let mut key = private_key();
+        key.set_hash(RsaHash::Sha1);
+        let signature = key.sign(b"parustiko").unwrap();
+
+        let err = key.public().verify(b"parustiko", &signature).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "signature algorithm is not allowed by the policy: (ssh-rsa)"
+        );
+        assert!(key
+            .public()
+            .with_sha1_signatures(true)
+            .verify(b"parustiko", &signature)
+            .is_ok());
+        assert_eq!(key.public().with_sha1_signatures(true), key.public());
+    }
+
+    #[test]
+    fn test_verify_accepts_signature_without_leading_zeros() {
+        let mut key = private_key();
+        key.set_hash(RsaHash::Sha256);
+        // Find a message whose signature starts with a zero byte.
+        let (data, signature) = (0u32..)
+            .map(|i| i.to_be_bytes())
+            .map(|data| (data, key.sign(&data).unwrap()))
+            .find(|(_, signature)| signature.get_bytes()[0] == 0)
+            .expect("Should not be reachable");
+        let stripped = Signature::new("rsa-sha2-256", signature.get_bytes()[1..].to_vec());
+
+        assert!(key.public().verify(&data, &stripped).is_ok());
+    }
+
+    #[test]
+    fn test_verify_rejects_other_algorithms() {
+        let key = RsaPublicKey::from_blob(&hex(BLOB), &AlgorithmPolicy::default()).unwrap();
+        let signature = Signature::new("ssh-ed25519", hex(SHA256_SIGNATURE));
+
+        let err = key
+            .verify(b"parustiko", &signature)
+            .unwrap_err()
+            .to_string();
+
+        assert_eq!(
+            err,
+            "signature algorithm does not match the key: (ssh-ed25519)"
+        );
+    }
+
+    #[test]
+    fn test_minimum_modulus_size() {
+        let policy = AlgorithmPolicy {
+            min_rsa_modulus_bits: 4096,
+            ..Default::default()
+        };
+
+        let err = RsaPublicKey::from_blob(&hex(BLOB), &policy)
+            .unwrap_err()
+            .to_string();
+
+        assert_eq!(
+            err,
+            "rsa modulus is smaller than allowed by the policy: (3072 bits)"
+        );
+    }
+
+    #[test]
+    fn test_invalid_private_key() {
+        let err = RsaPrivateKey::from_components(&hex(N), &[1, 0, 1], &hex(D), &hex(Q), &[7])
+            .unwrap_err()
+            .to_string();
+
+        assert_eq!(
+            err,
+            "key has invalid format: (rsa private key parameters are invalid)"
+        );
+    }
+
+    #[test]
+    fn test_debug_does_not_expose_the_private_key() {
+        let debug = format!("{:?}", private_key());
+
+        assert_eq!(
+            debug,
+            "RsaPrivateKey { modulus_bits: 3072, hash: Sha512, .. }"
+        );
+    }
+}
@@ -0,0 +1,571 @@
+use crate::errors::AgentError;
+use crypto::encoding::{Reader, Writer};
+use crypto::keys::{openssh, PrivateKey};
+use crypto::registry::AlgorithmPolicy;
+use crypto::secret::SecretBytes;
+use crypto::signature::certificate::{self, Certificate};
+use crypto::signature::errors::SignatureError;
+use crypto::signature::rsa::{self, RsaHash};
+use crypto::signature::{parse_public_key, PublicKey, Signature, Signer};
+#[cfg(unix)]
+use std::env;
+use std::fmt;
+use std::io::{Read, Write};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(unix)]
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+// Unix socket of the agent of the session.
+pub const AUTH_SOCK_ENV: &str = "SSH_AUTH_SOCK";
+
+// Flags of sign requests selecting the hash of RSA signatures
+// (draft-miller-ssh-agent, section 5.3).
+pub const SSH_AGENT_RSA_SHA2_256: u32 = 2;
+pub const SSH_AGENT_RSA_SHA2_512: u32 = 4;
+
+// Agents refuse longer messages as well.
+const MAX_MESSAGE_LENGTH: usize = 256 * 1024;
+
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENT_SUCCESS: u8 = 6;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+const SSH_AGENTC_ADD_IDENTITY: u8 = 17;
+const SSH_AGENTC_REMOVE_IDENTITY: u8 = 18;
+const SSH_AGENTC_REMOVE_ALL_IDENTITIES: u8 = 19;
+const SSH_AGENTC_LOCK: u8 = 22;
+const SSH_AGENTC_UNLOCK: u8 = 23;
+// Failure codes of old agents, still answered by OpenSSH.
+const SSH2_AGENT_FAILURE: u8 = 30;
+const SSH_COM_AGENT2_FAILURE: u8 = 102;
+
+// Public key held by the agent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentIdentity {
+    blob: Vec<u8>,
+    comment: String,
+}
+
+impl AgentIdentity {
+    pub fn get_blob(&self) -> &[u8] {
+        &self.blob
+    }
+
+    pub fn get_comment(&self) -> &str {
+        &self.comment
+    }
+}
+
+// Client of an ssh-agent, speaking the protocol of draft-miller-ssh-agent
+// over any stream, usually the Unix socket in `SSH_AUTH_SOCK`.
+pub struct AgentClient<S> {
+    stream: S,
+}
+
+#[cfg(unix)]
+impl AgentClient<UnixStream> {
+    pub fn connect(path: &Path) -> Result<Self, AgentError> {
+        Ok(Self::new(UnixStream::connect(path)?))
+    }
+
+    // Connect to the agent of the session.
+    pub fn connect_env() -> Result<Self, AgentError> {
+        let path = env::var_os(AUTH_SOCK_ENV).ok_or(AgentError::NotConfigured)?;
+
+        Self::connect(Path::new(&path))
+    }
+}
+
+impl<S: Read + Write> AgentClient<S> {
+    pub fn new(stream: S) -> Self {
+        Self { stream }
+    }
+
+    pub fn identities(&mut self) -> Result<Vec<AgentIdentity>, AgentError> {
+        let response = self.request(&[SSH_AGENTC_REQUEST_IDENTITIES])?;
+        let mut reader = Reader::new(&response);
+        expect_message(&mut reader, SSH_AGENT_IDENTITIES_ANSWER)?;
+
+        let identities = (0..reader.read_u32()?)
+            .map(|_| {
+                Ok(AgentIdentity {
+                    blob: reader.read_string()?.to_vec(),
+                    comment: String::from_utf8_lossy(reader.read_string()?).into_owned(),
+                })
+            })
+            .collect::<Result<_, AgentError>>()?;
+        reader.finish()?;
+
+        Ok(identities)
+    }
+
+    // Sign the data with the key of the blob. RSA keys sign with SHA-1
+    // unless one of the rsa-sha2 flags is given.
+    pub fn sign(&mut self, blob: &[u8], data: &[u8], flags: u32) -> Result<Signature, AgentError> {
+        let mut writer = Writer::new();
+        writer
+            .put_u8(SSH_AGENTC_SIGN_REQUEST)
+            .put_string(blob)
+            .put_string(data)
+            .put_u32(flags);
+
+        let response = self.request(&writer.into_bytes())?;
+        let mut reader = Reader::new(&response);
+        expect_message(&mut reader, SSH_AGENT_SIGN_RESPONSE)?;
+        let signature = Signature::from_blob(reader.read_string()?)?;
+        reader.finish()?;
+
+        Ok(signature)
+    }
+
+    pub fn add_identity(&mut self, key: &PrivateKey, comment: &str) -> Result<(), AgentError> {
+        let mut writer = Writer::new();
+        writer.put_u8(SSH_AGENTC_ADD_IDENTITY);
+        openssh::put_private_key(&mut writer, key);
+        writer.put_string(comment.as_bytes());
+
+        let request = SecretBytes::new(writer.into_bytes());
+        self.request_success(request.expose_secret(), "key was not added")
+    }
+
+    pub fn remove_identity(&mut self, blob: &[u8]) -> Result<(), AgentError> {
+        let mut writer = Writer::new();
+        writer.put_u8(SSH_AGENTC_REMOVE_IDENTITY).put_string(blob);
+
+        self.request_success(&writer.into_bytes(), "key was not removed")
+    }
+
+    pub fn remove_all_identities(&mut self) -> Result<(), AgentError> {
+        self.request_success(&[SSH_AGENTC_REMOVE_ALL_IDENTITIES], "keys were not removed")
+    }
+
+    // A locked agent hides its keys until it is unlocked with the same
+    // passphrase.
+    pub fn lock(&mut self, passphrase: &SecretBytes) -> Result<(), AgentError> {
+        self.lock_request(SSH_AGENTC_LOCK, passphrase, "agent was not locked")
+    }
+
+    pub fn unlock(&mut self, passphrase: &SecretBytes) -> Result<(), AgentError> {
+        self.lock_request(SSH_AGENTC_UNLOCK, passphrase, "agent was not unlocked")
+    }
+
+    // Signers for all keys of the agent which are supported, e.g. for
+    // publickey authentication. They share the connection.
+    pub fn into_signers(mut self) -> Result<Vec<AgentSigner<S>>, AgentError> {
+        let identities = self.identities()?;
+        let agent = Arc::new(Mutex::new(self));
+
+        Ok(identities
+            .into_iter()
+            .filter_map(|identity| {
+                let algorithm = parse_public_key(&identity.blob).ok()?.algorithm();
+                let key_algorithm = match certificate::is_certificate(algorithm) {
+                    true => Certificate::from_blob(&identity.blob, &AlgorithmPolicy::default())
+                        .ok()?
+                        .get_key()
+                        .algorithm(),
+                    false => algorithm,
+                };
+
+                Some(AgentSigner {
+                    agent: Arc::clone(&agent),
+                    identity,
+                    algorithm,
+                    key_algorithm,
+                    hash: RsaHash::Sha512,
+                })
+            })
+            .collect())
+    }
+
+    fn lock_request(
+        &mut self,
+        id: u8,
+        passphrase: &SecretBytes,
+        refused: &'static str,
+    ) -> Result<(), AgentError> {
+        let mut writer = Writer::new();
+        writer.put_u8(id).put_string(passphrase.expose_secret());
+
+        let request = SecretBytes::new(writer.into_bytes());
+        self.request_success(request.expose_secret(), refused)
+    }
+
+    fn request_success(&mut self, message: &[u8], refused: &'static str) -> Result<(), AgentError> {
+        let response = self.request(message)?;
+        let mut reader = Reader::new(&response);
+        match expect_message(&mut reader, SSH_AGENT_SUCCESS) {
+            Err(AgentError::Refused(_)) => Err(AgentError::Refused(refused)),
+            result => result,
+        }
+    }
+
+    // Messages are framed by their length.
+    fn request(&mut self, message: &[u8]) -> Result<Vec<u8>, AgentError> {
+        let mut framed = Writer::new();
+        framed.put_string(message);
+        let framed = SecretBytes::new(framed.into_bytes());
+        self.stream.write_all(framed.expose_secret())?;
+        self.stream.flush()?;
+
+        let mut length = [0u8; 4];
+        self.stream.read_exact(&mut length)?;
+        let length = u32::from_be_bytes(length) as usize;
+        if length == 0 || length > MAX_MESSAGE_LENGTH {
+            return Err(AgentError::InvalidLength(length));
+        }
+
+        let mut response = vec![0u8; length];
+        self.stream.read_exact(&mut response)?;
+
+        Ok(response)
+    }
+}
+
+impl<S> fmt::Debug for AgentClient<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AgentClient").finish_non_exhaustive()
+    }
+}
+
+fn expect_message(reader: &mut Reader, expected: u8) -> Result<(), AgentError> {
+    match reader.read_u8()? {
+        id if id == expected => Ok(()),
+        SSH_AGENT_FAILURE | SSH2_AGENT_FAILURE | SSH_COM_AGENT2_FAILURE => {
+            Err(AgentError::Refused("agent reported a failure"))
+        }
+        id => Err(AgentError::UnexpectedMessage(id)),
+    }
+}
+
+// Key of the agent, which signs without the private key ever leaving the
+// agent. RSA keys sign with rsa-sha2-512 by default. Certificates are
+// signed by their certified key, so the signatures carry its algorithm.
+pub struct AgentSigner<S> {
+    agent: Arc<Mutex<AgentClient<S>>>,
+    identity: AgentIdentity,
+    algorithm: &'static str,
+    key_algorithm: &'static str,
+    hash: RsaHash,
+}
+
+impl<S> AgentSigner<S> {
+    pub fn with_rsa_hash(mut self, hash: RsaHash) -> Self {
+        self.hash = hash;
+        self
+    }
+
+    pub fn get_identity(&self) -> &AgentIdentity {
+        &self.identity
+    }
+
+    fn is_rsa(&self) -> bool {
+        self.key_algorithm == rsa::ALGORITHM
+    }
+}
+
+impl<S: Read + Write> Signer for AgentSigner<S> {
+    fn public_key(&self) -> Box<dyn PublicKey> {
+        parse_public_key(&self.identity.blob).expect("Should not be reachable")
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<Signature, SignatureError> {
+        let flags = match (self.is_rsa(), self.hash) {
+            (true, RsaHash::Sha256) => SSH_AGENT_RSA_SHA2_256,
+            (true, RsaHash::Sha512) => SSH_AGENT_RSA_SHA2_512,
+            _ => 0,
+        };
+
+        let signature = self
+            .agent
+            .lock()
+            .map_err(|_| SignatureError::SigningFailed("agent connection is poisoned".into()))?
+            .sign(&self.identity.blob, data, flags)
+            .map_err(|e| SignatureError::SigningFailed(e.to_string()))?;
+
+        // Old agents ignore the flags and sign with SHA-1.
+        let expected = match self.is_rsa() {
+            true => self.hash.name(),
+            false => self.key_algorithm,
+        };
+        if signature.get_algorithm() != expected {
+            return Err(SignatureError::AlgorithmMismatch(
+                signature.get_algorithm().to_string(),
+            ));
+        }
+
+        Ok(signature)
+    }
+
+    fn signature_algorithm(&self) -> &'static str {
+        match (self.algorithm, self.hash) {
+            (certificate::RSA_CERT, RsaHash::Sha256) => certificate::RSA_SHA2_256_CERT,
+            (certificate::RSA_CERT, RsaHash::Sha512) => certificate::RSA_SHA2_512_CERT,
+            (rsa::ALGORITHM, hash) => hash.name(),
+            (algorithm, _) => algorithm,
+        }
+    }
+}
+
+impl<S> fmt::Debug for AgentSigner<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AgentSigner")
+            .field("identity", &self.identity)
+            .field("hash", &self.hash)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::publickey::tests::authorized;
+    use crate::auth::publickey::PublicKeyAuth;
+    use crate::auth::tests::server;
+    use crate::auth::UserAuth;
+    use crate::rng::SharedRng;
+    use crypto::keys;
+    use crypto::signature::certificate::{CertificateBuilder, CertificateType};
+    use crypto::signature::ed25519::Ed25519PrivateKey;
+    use rstest::rstest;
+    use std::thread;
+
+    const ID_RSA: &str = include_str!("../../rsa_keys/id_rsa");
+
+    fn id_rsa() -> PrivateKey {
+        keys::decode(ID_RSA, || None).unwrap().0
+    }
+
+    fn ed25519(seed: u64) -> PrivateKey {
+        PrivateKey::Ed25519(Ed25519PrivateKey::generate(&mut SharedRng::seeded(seed)))
+    }
+
+    fn passphrase(text: &str) -> SecretBytes {
+        SecretBytes::from(text.as_bytes())
+    }
+
+    // Agent answering like ssh-agent, holding its keys in memory with the
+    // blobs they are listed with, which are certificates for some keys.
+    fn fake_agent(mut stream: UnixStream, mut keys: Vec<(Vec<u8>, PrivateKey, String)>) {
+        let mut lock: Option<Vec<u8>> = None;
+
+        loop {
+            let mut length = [0u8; 4];
+            if stream.read_exact(&mut length).is_err() {
+                return;
+            }
+            let mut request = vec![0u8; u32::from_be_bytes(length) as usize];
+            stream.read_exact(&mut request).unwrap();
+            let mut reader = Reader::new(&request);
+            let mut response = Writer::new();
+
+            let success = match (reader.read_u8().unwrap(), &lock) {
+                (SSH_AGENTC_REQUEST_IDENTITIES, _) => {
+                    let visible = if lock.is_some() { &keys[..0] } else { &keys };
+                    response
+                        .put_u8(SSH_AGENT_IDENTITIES_ANSWER)
+                        .put_u32(visible.len() as u32);
+                    for (blob, _, comment) in visible {
+                        response.put_string(blob).put_string(comment.as_bytes());
+                    }
+                    None
+                }
+                (SSH_AGENTC_SIGN_REQUEST, None) => {
+                    let blob = reader.read_string().unwrap();
+                    let data = reader.read_string().unwrap();
+                    let flags = reader.read_u32().unwrap();
+                    match keys.iter_mut().find(|(b, _, _)| b == blob) {
+                        Some((_, key, _)) => {
+                            if let PrivateKey::Rsa(key) = key {
+                                key.set_hash(match flags {
+                                    SSH_AGENT_RSA_SHA2_256 => RsaHash::Sha256,
+                                    SSH_AGENT_RSA_SHA2_512 => RsaHash::Sha512,
+                                    _ => RsaHash::Sha1,
+                                });
+                            }
+                            let signature = key.sign(data).unwrap();
+                            response
+                                .put_u8(SSH_AGENT_SIGN_RESPONSE)
+                                .put_string(&signature.to_blob());
+                            None
+                        }
+                        None => Some(false),
+                    }
+                }
+                (SSH_AGENTC_ADD_IDENTITY, None) => {
+                    let key = openssh::read_private_key(&mut reader).unwrap();
+                    let comment = reader.read_utf8().unwrap().to_string();
+                    keys.push((key.public_key().to_blob(), key, comment));
+                    Some(true)
+                }
+                (SSH_AGENTC_REMOVE_IDENTITY, None) => {
+                    let blob = reader.read_string().unwrap();
+                    let count = keys.len();
+                    keys.retain(|(b, _, _)| b != blob);
+                    Some(keys.len() < count)
+                }
+                (SSH_AGENTC_REMOVE_ALL_IDENTITIES, None) => {
+                    keys.clear();
+                    Some(true)
+                }
+                (SSH_AGENTC_LOCK, None) => {
+                    lock = Some(reader.read_string().unwrap().to_vec());
+                    Some(true)
+                }
+                (SSH_AGENTC_UNLOCK, Some(locked)) => {
+                    let unlocked = reader.read_string().unwrap() == locked.as_slice();
+                    if unlocked {
+                        lock = None;
+                    }
+                    Some(unlocked)
+                }
+                _ => Some(false),
+            };
+            if let Some(success) = success {
+                response.put_u8(match success {
+                    true => SSH_AGENT_SUCCESS,
+                    false => SSH_AGENT_FAILURE,
+                });
+            }
+
+            let mut framed = Writer::new();
+            framed.put_string(&response.into_bytes());
+            stream.write_all(&framed.into_bytes()).unwrap();
+        }
+    }
+
+    fn agent() -> AgentClient<UnixStream> {
+        agent_with(Vec::new())
+    }
+
+    fn agent_with(keys: Vec<(Vec<u8>, PrivateKey, String)>) -> AgentClient<UnixStream> {
+        let (client, server) = UnixStream::pair().unwrap();
+        thread::spawn(move || fake_agent(server, keys));
+
+        AgentClient::new(client)
+    }
+
+    fn comments(agent: &mut AgentClient<UnixStream>) -> Vec<String> {
+        agent
+            .identities()
+            .unwrap()
+            .iter()
+            .map(|i| i.get_comment().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_add_and_remove_identities() {
+        let mut agent = agent();
+        agent.add_identity(&id_rsa(), "rsa").unwrap();
+        agent.add_identity(&ed25519(1), "ed25519").unwrap();
+
+        let identities = agent.identities().unwrap();
+        assert_eq!(comments(&mut agent), ["rsa", "ed25519"]);
+        assert_eq!(identities[1].get_blob(), ed25519(1).public_key().to_blob());
+
+        agent.remove_identity(identities[0].get_blob()).unwrap();
+        assert_eq!(comments(&mut agent), ["ed25519"]);
+        assert_eq!(
+            agent
+                .remove_identity(identities[0].get_blob())
+                .unwrap_err()
+                .to_string(),
+            "agent refused the request: (key was not removed)"
+        );
+
+        agent.remove_all_identities().unwrap();
+        assert!(comments(&mut agent).is_empty());
+    }
+
+    #[rstest]
+    #[case(RsaHash::Sha256, "rsa-sha2-256")]
+    #[case(RsaHash::Sha512, "rsa-sha2-512")]
+    fn test_rsa_signature_flags(#[case] hash: RsaHash, #[case] expected: &str) {
+        let mut agent = agent();
+        agent.add_identity(&id_rsa(), "rsa").unwrap();
+
+        let signers = agent.into_signers().unwrap();
+        let signer = signers.into_iter().next().unwrap().with_rsa_hash(hash);
+        let signature = signer.sign(b"data").unwrap();
+
+        assert_eq!(signer.signature_algorithm(), expected);
+        assert_eq!(signature.get_algorithm(), expected);
+        signer.public_key().verify(b"data", &signature).unwrap();
+    }
+
+    #[test]
+    fn test_certificate_signature() {
+        let key = ed25519(1);
+        let cert = CertificateBuilder::new(&*key.public_key(), CertificateType::User)
+            .with_principal("user")
+            .sign(&ed25519(2), &mut SharedRng::seeded(3))
+            .unwrap();
+        let agent = agent_with(vec![(cert.to_blob(), key, "cert".into())]);
+
+        let signer = agent.into_signers().unwrap().pop().unwrap();
+        let signature = signer.sign(b"data").unwrap();
+
+        assert_eq!(signer.signature_algorithm(), certificate::ED25519_CERT);
+        assert_eq!(signature.get_algorithm(), "ssh-ed25519");
+        signer.public_key().verify(b"data", &signature).unwrap();
+    }
+
+    #[test]
+    fn test_lock() {
+        let mut agent = agent();
+        agent.add_identity(&ed25519(1), "ed25519").unwrap();
+        let blob = ed25519(1).public_key().to_blob();
+
+        agent.lock(&passphrase("secret")).unwrap();
+        assert!(comments(&mut agent).is_empty());
+        assert_eq!(
+            agent.sign(&blob, b"data", 0).unwrap_err().to_string(),
+            "agent refused the request: (agent reported a failure)"
+        );
+        assert_eq!(
+            agent.unlock(&passphrase("wrong")).unwrap_err().to_string(),
+            "agent refused the request: (agent was not unlocked)"
+        );
+
+        agent.unlock(&passphrase("secret")).unwrap();
+        assert_eq!(comments(&mut agent), ["ed25519"]);
+        assert!(agent.sign(&blob, b"data", 0).is_ok());
+    }
+
+    #[test]
+    fn test_publickey_authentication() {
+        let mut agent = agent();
+        agent.add_identity(&ed25519(1), "other").unwrap();
+        agent.add_identity(&id_rsa(), "rsa").unwrap();
+        let transport = server(authorized(id_rsa().public_key().to_blob()));
+
+        let method = agent
+            .into_signers()
+            .unwrap()
+            .into_iter()
+            .fold(PublicKeyAuth::new(), |method, signer| {
+                method.with_key(signer)
+            });
+        let session = UserAuth::new(transport, "user")
+            .with_method(method)
+            .authenticate()
+            .unwrap();
+
+        assert_eq!(session.get_methods(), ["publickey"]);
+    }
+
+    #[test]
+    fn test_connect_without_socket() {
+        let path = env::temp_dir().join(format!("parustiko-agent-{}", std::process::id()));
+
+        assert!(matches!(
+            AgentClient::connect(&path),
+            Err(AgentError::Io(_))
+        ));
+    }
+}
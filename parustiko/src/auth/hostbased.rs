@@ -0,0 +1,410 @@
+use super::{AuthContext, AuthMethod, AuthOutcome};
+use crate::errors::AuthError;
+use crate::known_hosts::{HostKeyStatus, KnownHosts};
+use crate::protocol::message_ids::SshMessageID;
+use crypto::encoding::{Reader, Writer};
+use crypto::registry::AlgorithmPolicy;
+use crypto::signature::{Signature, Signer};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+const HOSTBASED_METHOD: &str = "hostbased";
+// Client hosts are listed in the known hosts under their plain names.
+const CLIENT_HOST_PORT: u16 = 22;
+
+// The `hostbased` method (RFC 4252, section 9). The client host signs the
+// request with its host key, vouching that the local user is who they
+// claim to be.
+pub struct HostBasedAuth {
+    host_key: Box<dyn Signer + Send>,
+    hostname: String,
+    local_user: String,
+}
+
+impl HostBasedAuth {
+    // The host name should be the fully qualified name the server knows
+    // the host key under. It is sent with a trailing dot, like OpenSSH.
+    pub fn new<S>(host_key: S, hostname: &str, local_user: &str) -> Self
+    where
+        S: Signer + Send + 'static,
+    {
+        Self {
+            host_key: Box::new(host_key),
+            hostname: format!("{}.", normalize_hostname(hostname)),
+            local_user: local_user.to_string(),
+        }
+    }
+}
+
+impl AuthMethod for HostBasedAuth {
+    fn name(&self) -> &'static str {
+        HOSTBASED_METHOD
+    }
+
+    fn authenticate(
+        &mut self,
+        context: &mut AuthContext<'_>,
+    ) -> Result<Option<AuthOutcome>, AuthError> {
+        let mut request = context.request(HOSTBASED_METHOD);
+        request
+            .put_string(self.host_key.signature_algorithm().as_bytes())
+            .put_string(&self.host_key.public_key().to_blob())
+            .put_string(self.hostname.as_bytes())
+            .put_string(self.local_user.as_bytes());
+        let mut request = request.into_bytes();
+        let signature = self.host_key.sign(&context.signed_data(&request))?;
+
+        let mut writer = Writer::new();
+        writer.put_string(&signature.to_blob());
+        request.extend(writer.into_bytes());
+        context.send(&request)?;
+
+        Ok(Some(context.receive_outcome()?))
+    }
+}
+
+impl fmt::Debug for HostBasedAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HostBasedAuth")
+            .field("hostname", &self.hostname)
+            .field("local_user", &self.local_user)
+            .finish_non_exhaustive()
+    }
+}
+
+// Host or user field of a shosts line: `+` for any, `-name` to deny.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ShostsPattern {
+    name: Option<String>,
+    negated: bool,
+}
+
+impl ShostsPattern {
+    fn parse(field: &str) -> Self {
+        let (negated, name) = match field.strip_prefix('-') {
+            Some(name) => (true, name),
+            None => (false, field.strip_prefix('+').unwrap_or(field)),
+        };
+
+        Self {
+            name: (!name.is_empty()).then(|| name.to_string()),
+            negated,
+        }
+    }
+
+    fn matches(&self, value: &str) -> bool {
+        self.name.as_ref().is_none_or(|name| name == value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ShostsEntry {
+    host: ShostsPattern,
+    user: Option<ShostsPattern>,
+}
+
+// Hosts and users allowed to log in, in the format of `shosts.equiv` and
+// `~/.shosts`: `host [user]` lines, where `+` matches anything and a `-`
+// prefix denies. Without a user, the client user has to have the same
+// name as the user on the server. The first matching line decides, net
+// groups are not supported and skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShostsAllowList {
+    entries: Vec<ShostsEntry>,
+}
+
+impl ShostsAllowList {
+    pub fn parse(text: &str) -> Self {
+        let entries = text
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let host = fields.next().filter(|f| !f.starts_with('#'))?;
+                let user = fields.next();
+                if host.contains('@') || user.is_some_and(|u| u.contains('@')) {
+                    return None;
+                }
+
+                Some(ShostsEntry {
+                    host: ShostsPattern::parse(host),
+                    user: user.map(ShostsPattern::parse),
+                })
+            })
+            .collect();
+
+        Self { entries }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, std::io::Error> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn is_allowed(&self, client_host: &str, client_user: &str, server_user: &str) -> bool {
+        let client_host = normalize_hostname(client_host);
+
+        for entry in &self.entries {
+            let host_matches = match &entry.host.name {
+                Some(name) => normalize_hostname(name).eq_ignore_ascii_case(client_host),
+                None => true,
+            };
+            let user_matches = match &entry.user {
+                Some(user) => user.matches(client_user),
+                None => client_user == server_user,
+            };
+
+            if host_matches && user_matches {
+                return !entry.host.negated && !entry.user.as_ref().is_some_and(|u| u.negated);
+            }
+        }
+
+        false
+    }
+}
+
+// Client of an accepted hostbased request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostBasedClient {
+    pub user: String,
+    pub hostname: String,
+    pub local_user: String,
+}
+
+// Server side of the method. The request is accepted when the client is
+// on the allow list, its host key is known for its host name, and the
+// signature is valid.
+#[derive(Debug)]
+pub struct HostBasedVerifier {
+    allow_list: ShostsAllowList,
+    known_hosts: KnownHosts,
+    policy: AlgorithmPolicy,
+}
+
+impl HostBasedVerifier {
+    pub fn new(allow_list: ShostsAllowList, known_hosts: KnownHosts) -> Self {
+        Self {
+            allow_list,
+            known_hosts,
+            policy: AlgorithmPolicy::default(),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: AlgorithmPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    // Check a hostbased SSH_MSG_USERAUTH_REQUEST. Whether the host name
+    // belongs to the address of the connection is up to the caller.
+    pub fn verify(&self, session_id: &[u8], request: &[u8]) -> Result<HostBasedClient, AuthError> {
+        let mut reader = Reader::new(request);
+        if reader.read_u8()? != SshMessageID::UserauthRequest as u8 {
+            return Err(AuthError::UnexpectedMessage(request[0]));
+        }
+
+        let user = reader.read_utf8()?;
+        reader.read_string()?;
+        if reader.read_utf8()? != HOSTBASED_METHOD {
+            return Err(AuthError::InvalidMessage("request is not for hostbased"));
+        }
+        let algorithm = reader.read_utf8()?;
+        let blob = reader.read_string()?;
+        let hostname = reader.read_utf8()?;
+        let local_user = reader.read_utf8()?;
+        let unsigned = &request[..request.len() - reader.remaining().len()];
+        let signature = reader.read_string()?;
+        reader.finish()?;
+
+        if !self.allow_list.is_allowed(hostname, local_user, user) {
+            return Err(AuthError::HostBasedRejected("client is not allowed"));
+        }
+        if self
+            .known_hosts
+            .check(normalize_hostname(hostname), CLIENT_HOST_PORT, blob)
+            != HostKeyStatus::Match
+        {
+            return Err(AuthError::HostBasedRejected("client host key is not known"));
+        }
+
+        let signature = Signature::from_blob(signature)
+            .map_err(|_| AuthError::HostBasedRejected("signature is invalid"))?;
+        if signature.get_algorithm() != algorithm {
+            return Err(AuthError::HostBasedRejected(
+                "signature does not match the algorithm",
+            ));
+        }
+
+        let mut data = Writer::new();
+        data.put_string(session_id).put_bytes(unsigned);
+        self.policy
+            .parse_public_key(blob)
+            .ok()
+            .and_then(|key| key.verify(&data.into_bytes(), &signature).ok())
+            .ok_or(AuthError::HostBasedRejected("signature is invalid"))?;
+
+        Ok(HostBasedClient {
+            user: user.to_string(),
+            hostname: hostname.to_string(),
+            local_user: local_user.to_string(),
+        })
+    }
+}
+
+// Clients send fully qualified names with a trailing dot, like OpenSSH.
+fn normalize_hostname(hostname: &str) -> &str {
+    hostname.strip_suffix('.').unwrap_or(hostname)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::tests::{failure, parse_request, service_accept, success};
+    use crate::auth::UserAuth;
+    use crate::rng::SharedRng;
+    use crate::transport::mock::{MockTransport, SESSION_ID};
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use crypto::keys::PrivateKey;
+    use crypto::signature::ed25519::Ed25519PrivateKey;
+    use rstest::rstest;
+
+    const SHOSTS: &str = "\
+# batch cluster
+node1.example.com
+node2.example.com batch
+-node3.example.com
++ -root
++@trusted
+";
+
+    fn host_key(seed: u64) -> PrivateKey {
+        PrivateKey::Ed25519(Ed25519PrivateKey::generate(&mut SharedRng::seeded(seed)))
+    }
+
+    fn verifier() -> HostBasedVerifier {
+        let blob = host_key(1).public_key().to_blob();
+        let known_hosts = KnownHosts::parse(&format!(
+            "node1.example.com,node2.example.com ssh-ed25519 {}\n",
+            STANDARD.encode(blob)
+        ));
+
+        HostBasedVerifier::new(ShostsAllowList::parse(SHOSTS), known_hosts)
+    }
+
+    fn transport() -> MockTransport {
+        let verifier = verifier();
+
+        MockTransport::new(move |payload| match payload[0] {
+            id if id == SshMessageID::ServiceRequest as u8 => vec![service_accept()],
+            _ => match verifier.verify(SESSION_ID, payload) {
+                Ok(_) => vec![success()],
+                Err(_) => vec![failure(&["hostbased"], false)],
+            },
+        })
+    }
+
+    #[rstest]
+    #[case("node1.example.com", "alice", "alice", true)]
+    #[case("NODE1.example.com.", "alice", "alice", true)]
+    #[case("node1.example.com", "alice", "bob", false)]
+    #[case("node2.example.com", "batch", "alice", true)]
+    #[case("node2.example.com", "alice", "alice", false)]
+    #[case("node3.example.com", "alice", "alice", false)]
+    #[case("node4.example.com", "root", "root", false)]
+    fn test_allow_list(
+        #[case] host: &str,
+        #[case] client_user: &str,
+        #[case] server_user: &str,
+        #[case] expected: bool,
+    ) {
+        let allow_list = ShostsAllowList::parse(SHOSTS);
+
+        assert_eq!(
+            allow_list.is_allowed(host, client_user, server_user),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case(1, "node1.example.com", "user", Ok(vec!["hostbased"]))]
+    #[case(1, "node1.example.com.", "user", Ok(vec!["hostbased"]))]
+    #[case(
+        2,
+        "node1.example.com.",
+        "user",
+        Err("authentication failed, methods that can continue: (hostbased)")
+    )]
+    #[case(
+        1,
+        "node1.example.com.",
+        "alice",
+        Err("authentication failed, methods that can continue: (hostbased)")
+    )]
+    fn test_hostbased(
+        #[case] seed: u64,
+        #[case] hostname: &str,
+        #[case] local_user: &str,
+        #[case] expected: Result<Vec<&str>, &str>,
+    ) {
+        let result = UserAuth::new(transport(), "user")
+            .with_method(HostBasedAuth::new(host_key(seed), hostname, local_user))
+            .authenticate();
+
+        assert_eq!(
+            result
+                .as_ref()
+                .map(|s| s.get_methods().to_vec())
+                .map_err(|e| e.to_string()),
+            expected.map_err(str::to_string)
+        );
+    }
+
+    #[rstest]
+    #[case("node1.example.com")]
+    #[case("node1.example.com.")]
+    fn test_hostname_has_trailing_dot(#[case] hostname: &str) {
+        let mut transport = transport();
+        let _ = UserAuth::new(&mut transport, "user")
+            .with_method(HostBasedAuth::new(host_key(1), hostname, "user"))
+            .authenticate();
+        let (_, _, _, fields) = parse_request(transport.sent.last().unwrap()).unwrap();
+        let mut reader = Reader::new(&fields);
+        reader.read_string().unwrap();
+        reader.read_string().unwrap();
+
+        assert_eq!(reader.read_utf8().unwrap(), "node1.example.com.");
+    }
+
+    #[rstest]
+    #[case("node1.example.com", "user", "client host key is not known")]
+    #[case("node3.example.com", "user", "client is not allowed")]
+    #[case("node2.example.com", "batch", "signature is invalid")]
+    fn test_verifier_rejects(
+        #[case] hostname: &str,
+        #[case] local_user: &str,
+        #[case] reason: &str,
+    ) {
+        let key = match reason {
+            "client host key is not known" => host_key(2),
+            _ => host_key(1),
+        };
+        let mut transport = transport();
+        let _ = UserAuth::new(&mut transport, "user")
+            .with_method(HostBasedAuth::new(key, hostname, local_user))
+            .authenticate();
+        let mut request = transport.sent.pop().unwrap();
+        // Corrupt the signature, the rest of the request is fine.
+        if reason == "signature is invalid" {
+            let last = request.len() - 1;
+            request[last] ^= 1;
+        }
+
+        assert_eq!(
+            verifier()
+                .verify(SESSION_ID, &request)
+                .unwrap_err()
+                .to_string(),
+            format!("hostbased authentication was rejected: ({})", reason)
+        );
+    }
+}
@@ -0,0 +1,444 @@
+use super::{AuthContext, AuthMethod, AuthOutcome};
+use crate::errors::AuthError;
+use crate::protocol::message_ids::SshMessageID;
+use crypto::encoding::{Reader, Writer};
+use crypto::secret::SecretBytes;
+use std::fmt;
+
+const KEYBOARD_INTERACTIVE_METHOD: &str = "keyboard-interactive";
+const USERAUTH_INFO_REQUEST: u8 = SshMessageID::UserauthMethodSpecific as u8;
+const USERAUTH_INFO_RESPONSE: u8 = SshMessageID::UserauthInfoResponse as u8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prompt {
+    pub text: String,
+    // Whether the answer may be shown while it is typed.
+    pub echo: bool,
+}
+
+// One round of prompts (RFC 4256, section 3.2). Rounds without prompts
+// only show the name and instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfoRequest {
+    pub name: String,
+    pub instruction: String,
+    pub language: String,
+    pub prompts: Vec<Prompt>,
+}
+
+impl InfoRequest {
+    pub fn from_payload(payload: &[u8]) -> Result<Self, AuthError> {
+        let mut reader = Reader::new(payload);
+        if reader.read_u8()? != USERAUTH_INFO_REQUEST {
+            return Err(AuthError::UnexpectedMessage(payload[0]));
+        }
+
+        let name = reader.read_utf8()?.to_string();
+        let instruction = reader.read_utf8()?.to_string();
+        let language = reader.read_utf8()?.to_string();
+        let prompts = (0..reader.read_u32()?)
+            .map(|_| {
+                Ok(Prompt {
+                    text: reader.read_utf8()?.to_string(),
+                    echo: reader.read_bool()?,
+                })
+            })
+            .collect::<Result<_, AuthError>>()?;
+        reader.finish()?;
+
+        Ok(Self {
+            name,
+            instruction,
+            language,
+            prompts,
+        })
+    }
+
+    pub fn to_payload(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer
+            .put_u8(USERAUTH_INFO_REQUEST)
+            .put_string(self.name.as_bytes())
+            .put_string(self.instruction.as_bytes())
+            .put_string(self.language.as_bytes())
+            .put_u32(self.prompts.len() as u32);
+        for prompt in &self.prompts {
+            writer
+                .put_string(prompt.text.as_bytes())
+                .put_bool(prompt.echo);
+        }
+
+        writer.into_bytes()
+    }
+}
+
+// Answers the prompts of the server, usually by asking the user. The
+// responses are in the order of the prompts, None cancels the
+// authentication.
+pub trait PromptHandler {
+    fn respond(&mut self, request: &InfoRequest) -> Option<Vec<SecretBytes>>;
+}
+
+impl<F> PromptHandler for F
+where
+    F: FnMut(&InfoRequest) -> Option<Vec<SecretBytes>>,
+{
+    fn respond(&mut self, request: &InfoRequest) -> Option<Vec<SecretBytes>> {
+        self(request)
+    }
+}
+
+// The `keyboard-interactive` method of the client.
+pub struct KeyboardInteractiveAuth {
+    handler: Box<dyn PromptHandler + Send>,
+    submethods: Vec<String>,
+}
+
+impl KeyboardInteractiveAuth {
+    pub fn new<H>(handler: H) -> Self
+    where
+        H: PromptHandler + Send + 'static,
+    {
+        Self {
+            handler: Box::new(handler),
+            submethods: Vec::new(),
+        }
+    }
+
+    // Hint for the server which kind of prompts to use, e.g. `pam`.
+    pub fn with_submethod(mut self, submethod: &str) -> Self {
+        self.submethods.push(submethod.to_string());
+        self
+    }
+}
+
+impl AuthMethod for KeyboardInteractiveAuth {
+    fn name(&self) -> &'static str {
+        KEYBOARD_INTERACTIVE_METHOD
+    }
+
+    fn authenticate(
+        &mut self,
+        context: &mut AuthContext<'_>,
+    ) -> Result<Option<AuthOutcome>, AuthError> {
+        // The language tag is deprecated and left empty.
+        let mut request = context.request(KEYBOARD_INTERACTIVE_METHOD);
+        request
+            .put_string(&[])
+            .put_string(self.submethods.join(",").as_bytes());
+        context.send(&request.into_bytes())?;
+
+        loop {
+            let payload = context.receive()?;
+            if let Some(outcome) = AuthOutcome::from_payload(&payload)? {
+                return Ok(Some(outcome));
+            }
+
+            let request = InfoRequest::from_payload(&payload)?;
+            let responses = self
+                .handler
+                .respond(&request)
+                .ok_or(AuthError::PromptCancelled)?;
+            if responses.len() != request.prompts.len() {
+                return Err(AuthError::InvalidMessage(
+                    "number of responses does not match the prompts",
+                ));
+            }
+
+            let mut writer = Writer::new();
+            writer
+                .put_u8(USERAUTH_INFO_RESPONSE)
+                .put_u32(responses.len() as u32);
+            for response in &responses {
+                writer.put_string(response.expose_secret());
+            }
+
+            let response = SecretBytes::new(writer.into_bytes());
+            context.send(response.expose_secret())?;
+        }
+    }
+}
+
+impl fmt::Debug for KeyboardInteractiveAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyboardInteractiveAuth")
+            .field("submethods", &self.submethods)
+            .finish_non_exhaustive()
+    }
+}
+
+// What a server side challenge does next: ask another round of prompts
+// or decide.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChallengeStep {
+    Prompt(InfoRequest),
+    Accept,
+    Reject,
+}
+
+// Server side conversation with the user, e.g. asking for a password and
+// then for a one-time password.
+pub trait Challenge {
+    fn start(&mut self, user: &str, submethods: &[&str]) -> ChallengeStep;
+
+    // Responses to the last round, in the order of its prompts.
+    fn respond(&mut self, responses: &[SecretBytes]) -> ChallengeStep;
+}
+
+// Server side of the method, which turns the messages of the client into
+// calls of the challenge. Every prompt step has to be sent to the client
+// as SSH_MSG_USERAUTH_INFO_REQUEST; accepting or rejecting is up to the
+// server, which may require more methods.
+#[derive(Debug)]
+pub struct ChallengeServer<C> {
+    challenge: C,
+    // Number of prompts waiting for responses.
+    pending: Option<usize>,
+}
+
+impl<C: Challenge> ChallengeServer<C> {
+    pub fn new(challenge: C) -> Self {
+        Self {
+            challenge,
+            pending: None,
+        }
+    }
+
+    // Start with the keyboard-interactive SSH_MSG_USERAUTH_REQUEST.
+    pub fn handle_request(&mut self, request: &[u8]) -> Result<ChallengeStep, AuthError> {
+        let mut reader = Reader::new(request);
+        if reader.read_u8()? != SshMessageID::UserauthRequest as u8 {
+            return Err(AuthError::UnexpectedMessage(request[0]));
+        }
+
+        let user = reader.read_utf8()?;
+        reader.read_string()?;
+        if reader.read_utf8()? != KEYBOARD_INTERACTIVE_METHOD {
+            return Err(AuthError::InvalidMessage(
+                "request is not for keyboard-interactive",
+            ));
+        }
+        reader.read_string()?;
+        let submethods: Vec<_> = reader
+            .read_utf8()?
+            .split(',')
+            .filter(|s| !s.is_empty())
+            .collect();
+        reader.finish()?;
+
+        let step = self.challenge.start(user, &submethods);
+        Ok(self.track(step))
+    }
+
+    // Continue with the SSH_MSG_USERAUTH_INFO_RESPONSE of the client.
+    pub fn handle_response(&mut self, response: &[u8]) -> Result<ChallengeStep, AuthError> {
+        let pending = self.pending.take().ok_or(AuthError::InvalidMessage(
+            "response without a pending request",
+        ))?;
+
+        let mut reader = Reader::new(response);
+        if reader.read_u8()? != USERAUTH_INFO_RESPONSE {
+            return Err(AuthError::UnexpectedMessage(response[0]));
+        }
+        if reader.read_u32()? as usize != pending {
+            return Err(AuthError::InvalidMessage(
+                "number of responses does not match the prompts",
+            ));
+        }
+        let responses = (0..pending)
+            .map(|_| Ok(SecretBytes::from(reader.read_string()?)))
+            .collect::<Result<Vec<_>, AuthError>>()?;
+        reader.finish()?;
+
+        let step = self.challenge.respond(&responses);
+        Ok(self.track(step))
+    }
+
+    fn track(&mut self, step: ChallengeStep) -> ChallengeStep {
+        if let ChallengeStep::Prompt(request) = &step {
+            self.pending = Some(request.prompts.len());
+        }
+
+        step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::tests::{failure, service_accept, success};
+    use crate::auth::UserAuth;
+    use crate::transport::mock::MockTransport;
+    use rstest::rstest;
+    use std::sync::{Arc, Mutex};
+
+    fn prompt(text: &str, echo: bool) -> Prompt {
+        Prompt {
+            text: text.to_string(),
+            echo,
+        }
+    }
+
+    fn round(name: &str, prompts: Vec<Prompt>) -> ChallengeStep {
+        ChallengeStep::Prompt(InfoRequest {
+            name: name.to_string(),
+            instruction: String::new(),
+            language: String::new(),
+            prompts,
+        })
+    }
+
+    // Password and one-time password in two rounds, like PAM with an OTP
+    // module.
+    struct PasswordAndOtp {
+        round: usize,
+    }
+
+    impl Challenge for PasswordAndOtp {
+        fn start(&mut self, user: &str, _: &[&str]) -> ChallengeStep {
+            match user {
+                "user" => round("Password", vec![prompt("Password: ", false)]),
+                _ => ChallengeStep::Reject,
+            }
+        }
+
+        fn respond(&mut self, responses: &[SecretBytes]) -> ChallengeStep {
+            self.round += 1;
+            match (self.round, responses[0].expose_secret()) {
+                (1, b"1234") => round("OTP", vec![prompt("Verification code: ", true)]),
+                (2, b"424242") => ChallengeStep::Accept,
+                _ => ChallengeStep::Reject,
+            }
+        }
+    }
+
+    // Client facing side of a server which only allows keyboard-interactive.
+    fn transport() -> MockTransport {
+        let mut server = ChallengeServer::new(PasswordAndOtp { round: 0 });
+
+        MockTransport::new(move |payload| {
+            let step = match payload[0] {
+                id if id == SshMessageID::ServiceRequest as u8 => return vec![service_accept()],
+                id if id == USERAUTH_INFO_RESPONSE => server.handle_response(payload),
+                _ => match server.handle_request(payload) {
+                    Err(AuthError::InvalidMessage(_)) => Ok(ChallengeStep::Reject),
+                    result => result,
+                },
+            };
+
+            match step.unwrap() {
+                ChallengeStep::Prompt(request) => vec![request.to_payload()],
+                ChallengeStep::Accept => vec![success()],
+                ChallengeStep::Reject => vec![failure(&["keyboard-interactive"], false)],
+            }
+        })
+    }
+
+    #[rstest]
+    #[case(&["1234", "424242"], Ok(vec!["keyboard-interactive"]))]
+    #[case(
+        &["1234", "000000"],
+        Err("authentication failed, methods that can continue: (keyboard-interactive)")
+    )]
+    #[case(&["1234"], Err("keyboard-interactive prompts were cancelled"))]
+    fn test_multiple_rounds(
+        #[case] answers: &'static [&'static str],
+        #[case] expected: Result<Vec<&str>, &str>,
+    ) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&requests);
+        let mut answers = answers.iter();
+        let method = KeyboardInteractiveAuth::new(move |request: &InfoRequest| {
+            seen.lock().unwrap().push(request.clone());
+            answers
+                .next()
+                .map(|answer| vec![SecretBytes::from(answer.as_bytes())])
+        });
+
+        let result = UserAuth::new(transport(), "user")
+            .with_method(method)
+            .authenticate();
+
+        assert_eq!(
+            result
+                .as_ref()
+                .map(|s| s.get_methods().to_vec())
+                .map_err(|e| e.to_string()),
+            expected.map_err(str::to_string)
+        );
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0].name, "Password");
+        assert_eq!(requests[0].prompts, [prompt("Password: ", false)]);
+        assert_eq!(requests[1].prompts, [prompt("Verification code: ", true)]);
+    }
+
+    #[test]
+    fn test_handler_response_count_must_match_prompts() {
+        // The server of the transport fails on a mismatched response, so
+        // it must never be sent.
+        let method = KeyboardInteractiveAuth::new(|_: &InfoRequest| {
+            Some(vec![
+                SecretBytes::from(&b"1234"[..]),
+                SecretBytes::from(&b"extra"[..]),
+            ])
+        });
+
+        let err = UserAuth::new(transport(), "user")
+            .with_method(method)
+            .authenticate()
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "invalid authentication message: (number of responses does not match the prompts)"
+        );
+    }
+
+    #[test]
+    fn test_info_request_roundtrip() {
+        let ChallengeStep::Prompt(request) = round(
+            "Login",
+            vec![prompt("Password: ", false), prompt("Code: ", true)],
+        ) else {
+            unreachable!()
+        };
+
+        assert_eq!(
+            InfoRequest::from_payload(&request.to_payload()).unwrap(),
+            request
+        );
+    }
+
+    #[test]
+    fn test_response_count_must_match_prompts() {
+        let mut server = ChallengeServer::new(PasswordAndOtp { round: 0 });
+        let mut request = Writer::new();
+        request
+            .put_u8(SshMessageID::UserauthRequest as u8)
+            .put_string(b"user")
+            .put_string(b"ssh-connection")
+            .put_string(KEYBOARD_INTERACTIVE_METHOD.as_bytes())
+            .put_string(b"")
+            .put_string(b"pam");
+        let mut response = Writer::new();
+        response
+            .put_u8(USERAUTH_INFO_RESPONSE)
+            .put_u32(2)
+            .put_string(b"1234")
+            .put_string(b"extra");
+        let response = response.into_bytes();
+
+        assert!(matches!(
+            server.handle_response(&response),
+            Err(AuthError::InvalidMessage(_))
+        ));
+        assert!(matches!(
+            server.handle_request(&request.into_bytes()).unwrap(),
+            ChallengeStep::Prompt(_)
+        ));
+        assert_eq!(
+            server.handle_response(&response).unwrap_err().to_string(),
+            "invalid authentication message: (number of responses does not match the prompts)"
+        );
+    }
+}
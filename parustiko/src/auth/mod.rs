@@ -0,0 +1,577 @@
+pub mod hostbased;
+pub mod keyboard_interactive;
+pub mod password;
+pub mod publickey;
+
+use crate::errors::AuthError;
+use crate::protocol::message_ids::SshMessageID;
+use crate::transport::Transport;
+use crypto::encoding::{Reader, Writer};
+use std::fmt;
+
+pub const USERAUTH_SERVICE: &str = "ssh-userauth";
+pub const CONNECTION_SERVICE: &str = "ssh-connection";
+const NONE_METHOD: &str = "none";
+
+// Answer of the server to an authentication request (RFC 4252, section
+// 5.1). A failure lists the methods that can continue, with partial
+// success when the method was accepted but more methods are required.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthOutcome {
+    Success,
+    Failure {
+        methods: Vec<String>,
+        partial_success: bool,
+    },
+}
+
+impl AuthOutcome {
+    // None for messages which are neither a success nor a failure, e.g.
+    // method specific ones.
+    pub fn from_payload(payload: &[u8]) -> Result<Option<Self>, AuthError> {
+        let mut reader = Reader::new(payload);
+
+        match reader.read_u8()? {
+            id if id == SshMessageID::UserauthSuccess as u8 => {
+                reader.finish()?;
+                Ok(Some(Self::Success))
+            }
+            id if id == SshMessageID::UserauthFailure as u8 => {
+                let methods = reader.read_utf8()?;
+                let partial_success = reader.read_bool()?;
+                reader.finish()?;
+
+                Ok(Some(Self::Failure {
+                    methods: methods
+                        .split(',')
+                        .filter(|m| !m.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                    partial_success,
+                }))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+// Text the server wants to show before authentication, e.g. a legal
+// notice (RFC 4252, section 5.4).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Banner {
+    pub message: String,
+    pub language: String,
+}
+
+type BannerCallback = Box<dyn FnMut(&Banner) + Send>;
+
+// Method independent part of the authentication, handed to the methods
+// to send their requests and to receive the replies.
+pub struct AuthContext<'a> {
+    transport: &'a mut dyn Transport,
+    user: &'a str,
+    service: &'a str,
+    banner: &'a mut Option<BannerCallback>,
+}
+
+impl AuthContext<'_> {
+    pub fn get_user(&self) -> &str {
+        self.user
+    }
+
+    pub fn get_service(&self) -> &str {
+        self.service
+    }
+
+    pub fn session_id(&self) -> &[u8] {
+        self.transport.session_id()
+    }
+
+    // SSH_MSG_USERAUTH_REQUEST up to the method name, the method specific
+    // fields are added by the caller.
+    pub fn request(&self, method: &str) -> Writer {
+        let mut writer = Writer::new();
+        writer
+            .put_u8(SshMessageID::UserauthRequest as u8)
+            .put_string(self.user.as_bytes())
+            .put_string(self.service.as_bytes())
+            .put_string(method.as_bytes());
+
+        writer
+    }
+
+    // Data signed by methods proving the possession of a key: the
+    // session identifier followed by the request up to the signature.
+    pub fn signed_data(&self, request: &[u8]) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer.put_string(self.session_id()).put_bytes(request);
+
+        writer.into_bytes()
+    }
+
+    pub fn send(&mut self, payload: &[u8]) -> Result<(), AuthError> {
+        Ok(self.transport.send(payload)?)
+    }
+
+    // Next message for the method. Banners go to the callback, messages
+    // without meaning for the authentication are skipped.
+    pub fn receive(&mut self) -> Result<Vec<u8>, AuthError> {
+        loop {
+            let payload = self.transport.receive()?;
+            let mut reader = Reader::new(&payload);
+
+            match reader.read_u8()? {
+                id if id == SshMessageID::Ignore as u8 || id == SshMessageID::Debug as u8 => {}
+                id if id == SshMessageID::UserauthBanner as u8 => {
+                    let banner = Banner {
+                        message: reader.read_utf8()?.to_string(),
+                        language: reader.read_utf8()?.to_string(),
+                    };
+
+                    if let Some(callback) = self.banner {
+                        callback(&banner);
+                    }
+                }
+                id if id == SshMessageID::Disconnect as u8 => return Err(disconnected(reader)?),
+                _ => return Ok(payload),
+            }
+        }
+    }
+
+    // Receive the final answer to a request, for methods without
+    // messages of their own.
+    pub fn receive_outcome(&mut self) -> Result<AuthOutcome, AuthError> {
+        let payload = self.receive()?;
+
+        AuthOutcome::from_payload(&payload)?.ok_or(AuthError::UnexpectedMessage(payload[0]))
+    }
+}
+
+// Authentication method of the client, like `password` or `publickey`.
+pub trait AuthMethod {
+    // Method name, matched against the methods that can continue.
+    fn name(&self) -> &'static str;
+
+    // Send requests until the server answers with a success or failure.
+    // Returns None when the method has nothing to try.
+    fn authenticate(
+        &mut self,
+        context: &mut AuthContext<'_>,
+    ) -> Result<Option<AuthOutcome>, AuthError>;
+}
+
+// Client side of the user authentication protocol (RFC 4252). After the
+// `none` request, the methods are tried in the order they were added,
+// each at most once, as long as the server lists them as able to
+// continue.
+pub struct UserAuth<T: Transport> {
+    transport: T,
+    user: String,
+    service: String,
+    methods: Vec<Box<dyn AuthMethod + Send>>,
+    banner: Option<BannerCallback>,
+}
+
+impl<T: Transport> UserAuth<T> {
+    pub fn new(transport: T, user: &str) -> Self {
+        Self {
+            transport,
+            user: user.to_string(),
+            service: CONNECTION_SERVICE.to_string(),
+            methods: Vec::new(),
+            banner: None,
+        }
+    }
+
+    // Service started after the authentication, `ssh-connection` unless
+    // changed.
+    pub fn with_service(mut self, service: &str) -> Self {
+        self.service = service.to_string();
+        self
+    }
+
+    pub fn with_method<M>(mut self, method: M) -> Self
+    where
+        M: AuthMethod + Send + 'static,
+    {
+        self.methods.push(Box::new(method));
+        self
+    }
+
+    pub fn with_banner_callback<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&Banner) + Send + 'static,
+    {
+        self.banner = Some(Box::new(callback));
+        self
+    }
+
+    pub fn authenticate(mut self) -> Result<AuthenticatedSession<T>, AuthError> {
+        self.request_service()?;
+
+        let mut context = AuthContext {
+            transport: &mut self.transport,
+            user: &self.user,
+            service: &self.service,
+            banner: &mut self.banner,
+        };
+        let request = context.request(NONE_METHOD).into_bytes();
+        context.send(&request)?;
+
+        let mut outcome = context.receive_outcome()?;
+        let mut last = None;
+        let mut tried = vec![false; self.methods.len()];
+        let mut methods = Vec::new();
+
+        let can_continue = loop {
+            let can_continue = match outcome {
+                AuthOutcome::Success => {
+                    methods.extend(last);
+                    break None;
+                }
+                AuthOutcome::Failure {
+                    methods: can_continue,
+                    partial_success,
+                } => {
+                    if partial_success {
+                        methods.extend(last);
+                    }
+                    can_continue
+                }
+            };
+
+            let next = self
+                .methods
+                .iter_mut()
+                .zip(tried.iter_mut())
+                .find(|(m, tried)| !**tried && can_continue.iter().any(|c| c == m.name()));
+            let Some((method, tried)) = next else {
+                break Some(can_continue);
+            };
+            *tried = true;
+
+            outcome = match method.authenticate(&mut context)? {
+                Some(outcome) => {
+                    last = Some(method.name());
+                    outcome
+                }
+                None => AuthOutcome::Failure {
+                    methods: can_continue,
+                    partial_success: false,
+                },
+            };
+        };
+
+        match can_continue {
+            Some(can_continue) => Err(AuthError::NoMethodsLeft(can_continue)),
+            None => Ok(AuthenticatedSession {
+                transport: self.transport,
+                user: self.user,
+                service: self.service,
+                methods,
+            }),
+        }
+    }
+
+    fn request_service(&mut self) -> Result<(), AuthError> {
+        let mut writer = Writer::new();
+        writer
+            .put_u8(SshMessageID::ServiceRequest as u8)
+            .put_string(USERAUTH_SERVICE.as_bytes());
+        self.transport.send(&writer.into_bytes())?;
+
+        loop {
+            let payload = self.transport.receive()?;
+            let mut reader = Reader::new(&payload);
+
+            match reader.read_u8()? {
+                id if id == SshMessageID::Ignore as u8 || id == SshMessageID::Debug as u8 => {}
+                id if id == SshMessageID::ServiceAccept as u8 => {
+                    return match reader.read_utf8()? {
+                        USERAUTH_SERVICE => Ok(()),
+                        other => Err(AuthError::ServiceNotAccepted(other.to_string())),
+                    }
+                }
+                id if id == SshMessageID::Disconnect as u8 => return Err(disconnected(reader)?),
+                id => return Err(AuthError::UnexpectedMessage(id)),
+            }
+        }
+    }
+}
+
+impl<T: Transport> fmt::Debug for UserAuth<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserAuth")
+            .field("user", &self.user)
+            .field("service", &self.service)
+            .field(
+                "methods",
+                &self.methods.iter().map(|m| m.name()).collect::<Vec<_>>(),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+// Error for SSH_MSG_DISCONNECT, read after the message ID.
+fn disconnected(mut reader: Reader<'_>) -> Result<AuthError, AuthError> {
+    reader.read_u32()?;
+
+    Ok(AuthError::Disconnected(reader.read_utf8()?.to_string()))
+}
+
+// Transport of a session in which the user is authenticated, ready for
+// the requested service.
+#[derive(Debug)]
+pub struct AuthenticatedSession<T> {
+    transport: T,
+    user: String,
+    service: String,
+    methods: Vec<&'static str>,
+}
+
+impl<T> AuthenticatedSession<T> {
+    pub fn get_user(&self) -> &str {
+        &self.user
+    }
+
+    pub fn get_service(&self) -> &str {
+        &self.service
+    }
+
+    // Methods which were accepted, in order. Empty when the server let
+    // the user in without authentication.
+    pub fn get_methods(&self) -> &[&'static str] {
+        &self.methods
+    }
+
+    pub fn get_transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    pub fn into_transport(self) -> T {
+        self.transport
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+    use rstest::rstest;
+    use std::sync::{Arc, Mutex};
+
+    pub(crate) fn service_accept() -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer
+            .put_u8(SshMessageID::ServiceAccept as u8)
+            .put_string(USERAUTH_SERVICE.as_bytes());
+
+        writer.into_bytes()
+    }
+
+    pub(crate) fn success() -> Vec<u8> {
+        vec![SshMessageID::UserauthSuccess as u8]
+    }
+
+    pub(crate) fn failure(methods: &[&str], partial_success: bool) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer
+            .put_u8(SshMessageID::UserauthFailure as u8)
+            .put_string(methods.join(",").as_bytes())
+            .put_bool(partial_success);
+
+        writer.into_bytes()
+    }
+
+    // User, service, method and the method specific fields of a request,
+    // None for other messages.
+    pub(crate) fn parse_request(payload: &[u8]) -> Option<(String, String, String, Vec<u8>)> {
+        let mut reader = Reader::new(payload);
+        if reader.read_u8().ok()? != SshMessageID::UserauthRequest as u8 {
+            return None;
+        }
+
+        Some((
+            reader.read_utf8().ok()?.to_string(),
+            reader.read_utf8().ok()?.to_string(),
+            reader.read_utf8().ok()?.to_string(),
+            reader.remaining().to_vec(),
+        ))
+    }
+
+    // Server which accepts the service request and then answers requests
+    // by method name.
+    pub(crate) fn server<F>(mut answer: F) -> MockTransport
+    where
+        F: FnMut(&str, &[u8]) -> Vec<Vec<u8>> + 'static,
+    {
+        MockTransport::new(move |payload| match parse_request(payload) {
+            Some((_, _, method, fields)) => answer(&method, &fields),
+            None => vec![service_accept()],
+        })
+    }
+
+    // Method which sends a single request without method specific fields.
+    struct TestMethod(&'static str);
+
+    impl AuthMethod for TestMethod {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn authenticate(
+            &mut self,
+            context: &mut AuthContext<'_>,
+        ) -> Result<Option<AuthOutcome>, AuthError> {
+            if self.0 == "empty" {
+                return Ok(None);
+            }
+
+            let request = context.request(self.0).into_bytes();
+            context.send(&request)?;
+            context.receive_outcome().map(Some)
+        }
+    }
+
+    #[test]
+    fn test_none_succeeds() {
+        let session = UserAuth::new(server(|_, _| vec![success()]), "user")
+            .authenticate()
+            .unwrap();
+
+        assert_eq!(session.get_user(), "user");
+        assert_eq!(session.get_service(), CONNECTION_SERVICE);
+        assert!(session.get_methods().is_empty());
+
+        let sent = session.into_transport().sent;
+        assert_eq!(sent[0], [&[5, 0, 0, 0, 12][..], b"ssh-userauth"].concat());
+        assert_eq!(
+            parse_request(&sent[1]).unwrap(),
+            (
+                "user".to_string(),
+                "ssh-connection".to_string(),
+                "none".to_string(),
+                Vec::new()
+            )
+        );
+    }
+
+    #[rstest]
+    #[case(&["a", "b"], &["b"], Ok(vec!["b"]))]
+    #[case(&["empty", "b"], &["empty", "b"], Ok(vec!["b"]))]
+    #[case(&["a", "b"], &["c"], Err("authentication failed, methods that can continue: (c)"))]
+    #[case(&["a"], &["a"], Err("authentication failed, methods that can continue: (a,b)"))]
+    fn test_methods_follow_server_list(
+        #[case] client: &[&'static str],
+        #[case] allowed: &'static [&'static str],
+        #[case] expected: Result<Vec<&str>, &str>,
+    ) {
+        let transport = server(move |method, _| match method {
+            "none" => vec![failure(allowed, false)],
+            "b" => vec![success()],
+            _ => vec![failure(&["a", "b"], false)],
+        });
+        let auth = client
+            .iter()
+            .fold(UserAuth::new(transport, "user"), |auth, name| {
+                auth.with_method(TestMethod(name))
+            });
+
+        let result = auth.authenticate();
+
+        assert_eq!(
+            result
+                .as_ref()
+                .map(|s| s.get_methods().to_vec())
+                .map_err(|e| e.to_string()),
+            expected.map_err(str::to_string)
+        );
+    }
+
+    #[test]
+    fn test_partial_success() {
+        let transport = server(|method, _| match method {
+            "none" => vec![failure(&["a"], false)],
+            "a" => vec![failure(&["b"], true)],
+            _ => vec![success()],
+        });
+
+        let session = UserAuth::new(transport, "user")
+            .with_method(TestMethod("b"))
+            .with_method(TestMethod("a"))
+            .authenticate()
+            .unwrap();
+
+        assert_eq!(session.get_methods(), ["a", "b"]);
+    }
+
+    #[test]
+    fn test_banner_and_ignored_messages() {
+        let mut banner = Writer::new();
+        banner
+            .put_u8(SshMessageID::UserauthBanner as u8)
+            .put_string(b"Authorized use only\n")
+            .put_string(b"en");
+        let banner = banner.into_bytes();
+        let transport = server(move |method, _| match method {
+            "none" => vec![
+                vec![SshMessageID::Ignore as u8, 0, 0, 0, 0],
+                banner.clone(),
+                failure(&["a"], false),
+            ],
+            _ => vec![success()],
+        });
+        let banners = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&banners);
+
+        UserAuth::new(transport, "user")
+            .with_method(TestMethod("a"))
+            .with_banner_callback(move |banner| seen.lock().unwrap().push(banner.clone()))
+            .authenticate()
+            .unwrap();
+
+        assert_eq!(
+            *banners.lock().unwrap(),
+            [Banner {
+                message: "Authorized use only\n".to_string(),
+                language: "en".to_string()
+            }]
+        );
+    }
+
+    #[rstest]
+    #[case(
+        vec![SshMessageID::ServiceAccept as u8, 0, 0, 0, 1, b'x'],
+        "service was not accepted: (x)"
+    )]
+    #[case(
+        vec![SshMessageID::UserauthSuccess as u8],
+        "unexpected message during authentication: (52)"
+    )]
+    fn test_service_not_accepted(#[case] reply: Vec<u8>, #[case] expected: &str) {
+        let transport = MockTransport::new(move |_| vec![reply.clone()]);
+
+        let err = UserAuth::new(transport, "user").authenticate().unwrap_err();
+
+        assert_eq!(err.to_string(), expected);
+    }
+
+    #[test]
+    fn test_disconnect_during_authentication() {
+        let mut disconnect = Writer::new();
+        disconnect
+            .put_u8(SshMessageID::Disconnect as u8)
+            .put_u32(14)
+            .put_string(b"no more auth methods available")
+            .put_string(b"");
+        let disconnect = disconnect.into_bytes();
+        let transport = server(move |_, _| vec![disconnect.clone()]);
+
+        let err = UserAuth::new(transport, "user").authenticate().unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "server disconnected: (no more auth methods available)"
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use super::{AuthContext, AuthMethod, AuthOutcome};
+use crate::errors::AuthError;
+use crate::protocol::message_ids::SshMessageID;
+use crypto::encoding::Reader;
+use crypto::secret::SecretBytes;
+use std::fmt;
+
+const PASSWORD_METHOD: &str = "password";
+const USERAUTH_PASSWD_CHANGEREQ: u8 = SshMessageID::UserauthMethodSpecific as u8;
+
+// Request of the server to change an expired password (RFC 4252,
+// section 8).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordChangeRequest {
+    pub prompt: String,
+    pub language: String,
+}
+
+type ChangeCallback = Box<dyn FnMut(&PasswordChangeRequest) -> Option<SecretBytes> + Send>;
+
+// The `password` method. Change requests go to the callback, which
+// returns the new password or None to give up. Without a callback, an
+// expired password fails the authentication.
+pub struct PasswordAuth {
+    password: SecretBytes,
+    change: Option<ChangeCallback>,
+}
+
+impl PasswordAuth {
+    pub fn new(password: SecretBytes) -> Self {
+        Self {
+            password,
+            change: None,
+        }
+    }
+
+    pub fn with_change_callback<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&PasswordChangeRequest) -> Option<SecretBytes> + Send + 'static,
+    {
+        self.change = Some(Box::new(callback));
+        self
+    }
+
+    // Requests hold the password, so they are wiped after sending.
+    fn send_request(
+        &self,
+        context: &mut AuthContext<'_>,
+        new_password: Option<&SecretBytes>,
+    ) -> Result<(), AuthError> {
+        let mut writer = context.request(PASSWORD_METHOD);
+        writer
+            .put_bool(new_password.is_some())
+            .put_string(self.password.expose_secret());
+        if let Some(new_password) = new_password {
+            writer.put_string(new_password.expose_secret());
+        }
+
+        let request = SecretBytes::new(writer.into_bytes());
+        context.send(request.expose_secret())
+    }
+}
+
+impl AuthMethod for PasswordAuth {
+    fn name(&self) -> &'static str {
+        PASSWORD_METHOD
+    }
+
+    fn authenticate(
+        &mut self,
+        context: &mut AuthContext<'_>,
+    ) -> Result<Option<AuthOutcome>, AuthError> {
+        self.send_request(context, None)?;
+
+        // The old password stays valid until the server accepts the new
+        // one, it may ask again when the new one is not good enough.
+        let mut new_password = None;
+        loop {
+            let payload = context.receive()?;
+            if let Some(outcome) = AuthOutcome::from_payload(&payload)? {
+                // A partial success also means the password was changed.
+                let changed = match &outcome {
+                    AuthOutcome::Success => true,
+                    AuthOutcome::Failure {
+                        partial_success, ..
+                    } => *partial_success,
+                };
+                if let (true, Some(new)) = (changed, new_password) {
+                    self.password = new;
+                }
+
+                return Ok(Some(outcome));
+            }
+
+            let mut reader = Reader::new(&payload);
+            if reader.read_u8()? != USERAUTH_PASSWD_CHANGEREQ {
+                return Err(AuthError::UnexpectedMessage(payload[0]));
+            }
+            let request = PasswordChangeRequest {
+                prompt: reader.read_utf8()?.to_string(),
+                language: reader.read_utf8()?.to_string(),
+            };
+
+            let new = match &mut self.change {
+                Some(callback) => callback(&request),
+                None => None,
+            }
+            .ok_or(AuthError::PasswordChangeRequired(request.prompt))?;
+
+            self.send_request(context, Some(&new))?;
+            new_password = Some(new);
+        }
+    }
+}
+
+impl fmt::Debug for PasswordAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PasswordAuth")
+            .field("password", &self.password)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::tests::{failure, parse_request, server, success};
+    use crate::auth::UserAuth;
+    use crypto::encoding::Writer;
+    use rstest::rstest;
+    use std::sync::{Arc, Mutex};
+
+    fn change_request(prompt: &str) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer
+            .put_u8(USERAUTH_PASSWD_CHANGEREQ)
+            .put_string(prompt.as_bytes())
+            .put_string(b"");
+
+        writer.into_bytes()
+    }
+
+    // Old and new password of a request.
+    fn passwords(fields: &[u8]) -> (String, Option<String>) {
+        let mut reader = Reader::new(fields);
+        let change = reader.read_bool().unwrap();
+        let old = reader.read_utf8().unwrap().to_string();
+        let new = change.then(|| reader.read_utf8().unwrap().to_string());
+
+        (old, new)
+    }
+
+    fn password(text: &str) -> SecretBytes {
+        SecretBytes::from(text.as_bytes())
+    }
+
+    #[rstest]
+    #[case("1234", Ok(vec!["password"]))]
+    #[case(
+        "12345",
+        Err("authentication failed, methods that can continue: (password)")
+    )]
+    fn test_password(#[case] text: &str, #[case] expected: Result<Vec<&str>, &str>) {
+        let transport = server(|method, fields| match method {
+            "password" if passwords(fields) == ("1234".to_string(), None) => vec![success()],
+            _ => vec![failure(&["password"], false)],
+        });
+
+        let result = UserAuth::new(transport, "user")
+            .with_method(PasswordAuth::new(password(text)))
+            .authenticate();
+
+        assert_eq!(
+            result
+                .as_ref()
+                .map(|s| s.get_methods().to_vec())
+                .map_err(|e| e.to_string()),
+            expected.map_err(str::to_string)
+        );
+    }
+
+    #[test]
+    fn test_change_expired_password() {
+        let transport = server(|method, fields| match method {
+            "password" => match passwords(fields) {
+                (old, None) if old == "1234" => vec![change_request("Password expired")],
+                (_, Some(new)) if new.len() < 8 => vec![change_request("Too short")],
+                (old, Some(_)) if old == "1234" => vec![success()],
+                _ => vec![failure(&["password"], false)],
+            },
+            _ => vec![failure(&["password"], false)],
+        });
+        let prompts = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&prompts);
+        let mut candidates = vec![password("correct horse"), password("short")];
+        let method = PasswordAuth::new(password("1234")).with_change_callback(move |request| {
+            seen.lock().unwrap().push(request.prompt.clone());
+            candidates.pop()
+        });
+
+        let session = UserAuth::new(transport, "user")
+            .with_method(method)
+            .authenticate()
+            .unwrap();
+
+        assert_eq!(*prompts.lock().unwrap(), ["Password expired", "Too short"]);
+        let sent = session.into_transport().sent;
+        let (_, _, _, fields) = parse_request(&sent[4]).unwrap();
+        assert_eq!(
+            passwords(&fields),
+            ("1234".to_string(), Some("correct horse".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_change_request_without_callback() {
+        let transport = server(|method, _| match method {
+            "password" => vec![change_request("Password expired")],
+            _ => vec![failure(&["password"], false)],
+        });
+
+        let err = UserAuth::new(transport, "user")
+            .with_method(PasswordAuth::new(password("1234")))
+            .authenticate()
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "password has to be changed: (Password expired)"
+        );
+    }
+
+    #[test]
+    fn test_debug_does_not_expose_the_password() {
+        let method = PasswordAuth::new(password("1234"));
+
+        assert_eq!(
+            format!("{:?}", method),
+            "PasswordAuth { password: SecretBytes([REDACTED; 4]), .. }"
+        );
+    }
+}
@@ -0,0 +1,369 @@
+use super::{AuthContext, AuthMethod, AuthOutcome};
+use crate::errors::AuthError;
+use crate::protocol::message_ids::SshMessageID;
+use crypto::encoding::{Reader, Writer};
+use crypto::signature::Signer;
+use std::fmt;
+
+const PUBLICKEY_METHOD: &str = "publickey";
+const USERAUTH_PK_OK: u8 = SshMessageID::UserauthMethodSpecific as u8;
+
+// The `publickey` method (RFC 4252, section 7). Keys are tried in the
+// order they were added. Each key is first offered without a signature,
+// so keys the server would not accept are never used for signing, e.g.
+// by an agent asking the user for confirmation. A key that fails to sign
+// is skipped in favour of the next one.
+#[derive(Default)]
+pub struct PublicKeyAuth {
+    keys: Vec<Box<dyn Signer + Send>>,
+}
+
+impl PublicKeyAuth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Loaded private key, or any other implementation of the signer.
+    pub fn with_key<S>(self, key: S) -> Self
+    where
+        S: Signer + Send + 'static,
+    {
+        self.with_signer(Box::new(key))
+    }
+
+    // Signer living outside of the process, e.g. in an agent or a token.
+    pub fn with_signer(mut self, signer: Box<dyn Signer + Send>) -> Self {
+        self.keys.push(signer);
+        self
+    }
+}
+
+impl AuthMethod for PublicKeyAuth {
+    fn name(&self) -> &'static str {
+        PUBLICKEY_METHOD
+    }
+
+    fn authenticate(
+        &mut self,
+        context: &mut AuthContext<'_>,
+    ) -> Result<Option<AuthOutcome>, AuthError> {
+        let mut last = None;
+        let mut failed = None;
+
+        for key in &self.keys {
+            let algorithm = key.signature_algorithm();
+            let blob = key.public_key().to_blob();
+
+            let mut query = context.request(PUBLICKEY_METHOD);
+            query
+                .put_bool(false)
+                .put_string(algorithm.as_bytes())
+                .put_string(&blob);
+            context.send(&query.into_bytes())?;
+
+            let payload = context.receive()?;
+            match AuthOutcome::from_payload(&payload)? {
+                Some(outcome) => {
+                    let done = !can_continue(&outcome);
+                    last = Some(outcome);
+                    if done {
+                        break;
+                    }
+                    continue;
+                }
+                None => {
+                    let mut reader = Reader::new(&payload);
+                    if reader.read_u8()? != USERAUTH_PK_OK
+                        || reader.read_utf8()? != algorithm
+                        || reader.read_string()? != blob
+                    {
+                        return Err(AuthError::UnexpectedMessage(payload[0]));
+                    }
+                }
+            }
+
+            let mut request = context.request(PUBLICKEY_METHOD);
+            request
+                .put_bool(true)
+                .put_string(algorithm.as_bytes())
+                .put_string(&blob);
+            let mut request = request.into_bytes();
+            let signature = match key.sign(&context.signed_data(&request)) {
+                Ok(signature) => signature,
+                Err(e) => {
+                    failed = Some(e);
+                    continue;
+                }
+            };
+
+            let mut writer = Writer::new();
+            writer.put_string(&signature.to_blob());
+            request.extend(writer.into_bytes());
+            context.send(&request)?;
+
+            let outcome = context.receive_outcome()?;
+            let done = !can_continue(&outcome);
+            last = Some(outcome);
+            if done {
+                break;
+            }
+        }
+
+        match (last, failed) {
+            (None, Some(e)) => Err(e.into()),
+            (last, _) => Ok(last),
+        }
+    }
+}
+
+// Whether to go on with the next key: after a plain failure, as long as
+// the server still accepts the method.
+fn can_continue(outcome: &AuthOutcome) -> bool {
+    match outcome {
+        AuthOutcome::Success => false,
+        AuthOutcome::Failure {
+            methods,
+            partial_success,
+        } => !partial_success && methods.iter().any(|m| m == PUBLICKEY_METHOD),
+    }
+}
+
+impl fmt::Debug for PublicKeyAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PublicKeyAuth")
+            .field(
+                "keys",
+                &self
+                    .keys
+                    .iter()
+                    .map(|k| k.signature_algorithm())
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::auth::tests::{failure, parse_request, server, success};
+    use crate::auth::UserAuth;
+    use crate::rng::SharedRng;
+    use crate::transport::mock::SESSION_ID;
+    use crypto::keys::{self, PrivateKey};
+    use crypto::signature::ed25519::Ed25519PrivateKey;
+    use crypto::signature::errors::SignatureError;
+    use crypto::signature::{parse_public_key, PublicKey, Signature};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const ID_RSA: &str = include_str!("../../../rsa_keys/id_rsa");
+
+    fn id_rsa() -> PrivateKey {
+        keys::decode(ID_RSA, || None).unwrap().0
+    }
+
+    fn ed25519(seed: u64) -> PrivateKey {
+        PrivateKey::Ed25519(Ed25519PrivateKey::generate(&mut SharedRng::seeded(seed)))
+    }
+
+    fn pk_ok(algorithm: &[u8], blob: &[u8]) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer
+            .put_u8(USERAUTH_PK_OK)
+            .put_string(algorithm)
+            .put_string(blob);
+
+        writer.into_bytes()
+    }
+
+    // Server with a single authorized key, which checks signatures like
+    // sshd does.
+    pub(crate) fn authorized(key: Vec<u8>) -> impl FnMut(&str, &[u8]) -> Vec<Vec<u8>> {
+        move |method, fields| {
+            if method != PUBLICKEY_METHOD {
+                return vec![failure(&["publickey", "password"], false)];
+            }
+
+            let mut reader = Reader::new(fields);
+            let signed = reader.read_bool().unwrap();
+            let algorithm = reader.read_string().unwrap();
+            let blob = reader.read_string().unwrap();
+            if blob != key {
+                return vec![failure(&["publickey", "password"], false)];
+            }
+            if !signed {
+                return vec![pk_ok(algorithm, blob)];
+            }
+
+            let unsigned = &fields[..fields.len() - reader.remaining().len()];
+            let signature = Signature::from_blob(reader.read_string().unwrap()).unwrap();
+            let request = [
+                &[SshMessageID::UserauthRequest as u8][..],
+                &[0, 0, 0, 4],
+                b"user",
+                &[0, 0, 0, 14],
+                b"ssh-connection",
+                &[0, 0, 0, 9],
+                b"publickey",
+                unsigned,
+            ]
+            .concat();
+            let mut data = Writer::new();
+            data.put_string(SESSION_ID).put_bytes(&request);
+
+            match signature.get_algorithm().as_bytes() == algorithm
+                && parse_public_key(blob)
+                    .unwrap()
+                    .verify(&data.into_bytes(), &signature)
+                    .is_ok()
+            {
+                true => vec![success()],
+                false => vec![failure(&["publickey", "password"], false)],
+            }
+        }
+    }
+
+    // Signer outside of the process, counting the signatures it made.
+    struct ExternalSigner {
+        key: PrivateKey,
+        signatures: Arc<AtomicUsize>,
+    }
+
+    impl Signer for ExternalSigner {
+        fn public_key(&self) -> Box<dyn PublicKey> {
+            self.key.public_key()
+        }
+
+        fn sign(&self, data: &[u8]) -> Result<Signature, SignatureError> {
+            self.signatures.fetch_add(1, Ordering::SeqCst);
+            self.key.sign(data)
+        }
+    }
+
+    #[test]
+    fn test_rsa_key() {
+        let transport = server(authorized(id_rsa().public_key().to_blob()));
+
+        let session = UserAuth::new(transport, "user")
+            .with_method(PublicKeyAuth::new().with_key(id_rsa()))
+            .authenticate()
+            .unwrap();
+
+        assert_eq!(session.get_methods(), ["publickey"]);
+        let sent = session.into_transport().sent;
+        let (_, _, method, fields) = parse_request(&sent[2]).unwrap();
+        let mut reader = Reader::new(&fields);
+        assert_eq!(method, "publickey");
+        assert!(!reader.read_bool().unwrap());
+        assert_eq!(reader.read_utf8().unwrap(), "rsa-sha2-512");
+    }
+
+    #[test]
+    fn test_only_accepted_key_is_signed() {
+        let rejected = Arc::new(AtomicUsize::new(0));
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let transport = server(authorized(ed25519(2).public_key().to_blob()));
+        let method = PublicKeyAuth::new()
+            .with_signer(Box::new(ExternalSigner {
+                key: ed25519(1),
+                signatures: Arc::clone(&rejected),
+            }))
+            .with_signer(Box::new(ExternalSigner {
+                key: ed25519(2),
+                signatures: Arc::clone(&accepted),
+            }));
+
+        let session = UserAuth::new(transport, "user")
+            .with_method(method)
+            .authenticate()
+            .unwrap();
+
+        assert_eq!(session.get_methods(), ["publickey"]);
+        assert_eq!(rejected.load(Ordering::SeqCst), 0);
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+    }
+
+    // Signer whose key is accepted, but which cannot sign, e.g. a token
+    // that was removed.
+    struct FailingSigner(PrivateKey);
+
+    impl Signer for FailingSigner {
+        fn public_key(&self) -> Box<dyn PublicKey> {
+            self.0.public_key()
+        }
+
+        fn sign(&self, _: &[u8]) -> Result<Signature, SignatureError> {
+            Err(SignatureError::SigningFailed("token removed".into()))
+        }
+    }
+
+    #[test]
+    fn test_failing_signer_is_skipped() {
+        let signatures = Arc::new(AtomicUsize::new(0));
+        let transport = server(authorized(ed25519(2).public_key().to_blob()));
+        let method = PublicKeyAuth::new()
+            .with_key(FailingSigner(ed25519(2)))
+            .with_signer(Box::new(ExternalSigner {
+                key: ed25519(2),
+                signatures: Arc::clone(&signatures),
+            }));
+
+        let session = UserAuth::new(transport, "user")
+            .with_method(method)
+            .authenticate()
+            .unwrap();
+
+        assert_eq!(session.get_methods(), ["publickey"]);
+        assert_eq!(signatures.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_only_failing_signer() {
+        let transport = server(authorized(ed25519(2).public_key().to_blob()));
+
+        let err = UserAuth::new(transport, "user")
+            .with_method(PublicKeyAuth::new().with_key(FailingSigner(ed25519(2))))
+            .authenticate()
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "signing authentication request failed: (signing with an external signer failed: (token removed))"
+        );
+    }
+
+    #[test]
+    fn test_no_key_is_accepted() {
+        let transport = server(authorized(id_rsa().public_key().to_blob()));
+
+        let err = UserAuth::new(transport, "user")
+            .with_method(
+                PublicKeyAuth::new()
+                    .with_key(ed25519(1))
+                    .with_key(ed25519(2)),
+            )
+            .authenticate()
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "authentication failed, methods that can continue: (publickey,password)"
+        );
+    }
+
+    #[test]
+    fn test_without_keys() {
+        let transport = server(authorized(id_rsa().public_key().to_blob()));
+
+        let err = UserAuth::new(transport, "user")
+            .with_method(PublicKeyAuth::new())
+            .authenticate()
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "authentication failed, methods that can continue: (publickey,password)"
+        );
+    }
+}
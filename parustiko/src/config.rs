@@ -0,0 +1,18 @@
+use crate::rng::SharedRng;
+use crypto::registry::AlgorithmPolicy;
+
+// Settings of a single transport connection.
+#[derive(Debug, Clone, Default)]
+pub struct TransportConfig {
+    pub algorithm_policy: AlgorithmPolicy,
+    // Source of all random values of the connection, the system RNG unless
+    // replaced for deterministic tests.
+    pub rng: SharedRng,
+}
+
+impl TransportConfig {
+    pub fn with_rng(mut self, rng: SharedRng) -> Self {
+        self.rng = rng;
+        self
+    }
+}
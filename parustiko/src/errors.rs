@@ -1,3 +1,6 @@
+use crypto::encoding::errors::DecodingError;
+use crypto::encryption::errors::{DecryptionError, EncryptionError};
+use crypto::signature::errors::{CertificateError, KeyError, SignatureError};
 use std::io;
 use thiserror::Error;
 
@@ -11,6 +14,18 @@ pub enum BppError {
 
     #[error("entity preconditions are not met: ({0})")]
     InvalidEntity(&'static str),
+
+    #[error("encrypting packet failed: ({0})")]
+    EncryptionFailed(#[from] EncryptionError),
+
+    #[error("decrypting packet failed: ({0})")]
+    DecryptionFailed(#[from] DecryptionError),
+
+    #[error("message authentication code mismatch in packet {0}")]
+    MacMismatch(u32),
+
+    #[error("cipher cannot process a part of the packet: ({0})")]
+    CipherModeMismatch(&'static str),
 }
 
 #[derive(Debug, Error)]
@@ -30,3 +45,111 @@ pub enum VersionExchangeError {
     #[error("{0}")]
     OffsetOutOfRange(&'static str),
 }
+
+#[derive(Debug, Error)]
+pub enum KnownHostsError {
+    #[error("reading or writing known hosts failed: ({0})")]
+    Io(#[from] io::Error),
+
+    #[error("host key is invalid: ({0})")]
+    InvalidKey(&'static str),
+
+    #[error("host key of {0} is not known")]
+    UnknownHost(String),
+
+    #[error("host key of {0} does not match the known hosts")]
+    HostKeyMismatch(String),
+
+    #[error("host key of {0} is revoked")]
+    RevokedHostKey(String),
+
+    #[error("host key of {0} was rejected")]
+    HostKeyRejected(String),
+
+    #[error("host certificate is invalid: ({0})")]
+    InvalidCertificate(#[from] CertificateError),
+}
+
+#[derive(Debug, Error)]
+pub enum HostKeysError {
+    #[error("decoding host keys message failed: ({0})")]
+    DecodingFailed(#[from] DecodingError),
+
+    #[error("unexpected host keys message: ({0})")]
+    UnexpectedMessage(&'static str),
+
+    #[error("announced host key is invalid: ({0})")]
+    InvalidKey(#[from] KeyError),
+
+    #[error("host key proof failed: ({0})")]
+    ProofFailed(&'static str),
+
+    #[error("host key proof signature is invalid: ({0})")]
+    InvalidSignature(#[from] SignatureError),
+
+    #[error("host key is not loaded: ({0})")]
+    UnknownHostKey(String),
+
+    #[error("updating known hosts failed: ({0})")]
+    KnownHosts(#[from] KnownHostsError),
+}
+
+#[derive(Debug, Error)]
+pub enum AgentError {
+    #[error("agent connection failed: ({0})")]
+    Io(#[from] io::Error),
+
+    #[error("agent is not running, SSH_AUTH_SOCK is not set")]
+    NotConfigured,
+
+    #[error("decoding agent message failed: ({0})")]
+    DecodingFailed(#[from] DecodingError),
+
+    #[error("agent message has an invalid length: ({0})")]
+    InvalidLength(usize),
+
+    #[error("agent signature is invalid: ({0})")]
+    InvalidSignature(#[from] SignatureError),
+
+    #[error("agent refused the request: ({0})")]
+    Refused(&'static str),
+
+    #[error("unexpected message from the agent: ({0})")]
+    UnexpectedMessage(u8),
+}
+
+#[derive(Debug, Error)]
+pub enum AuthError {
+    #[error("transport failed during authentication: ({0})")]
+    Transport(#[from] BppError),
+
+    #[error("decoding authentication message failed: ({0})")]
+    DecodingFailed(#[from] DecodingError),
+
+    #[error("unexpected message during authentication: ({0})")]
+    UnexpectedMessage(u8),
+
+    #[error("invalid authentication message: ({0})")]
+    InvalidMessage(&'static str),
+
+    #[error("service was not accepted: ({0})")]
+    ServiceNotAccepted(String),
+
+    #[error("server disconnected: ({0})")]
+    Disconnected(String),
+
+    #[error("signing authentication request failed: ({0})")]
+    SigningFailed(#[from] SignatureError),
+
+    #[error("password has to be changed: ({0})")]
+    PasswordChangeRequired(String),
+
+    #[error("hostbased authentication was rejected: ({0})")]
+    HostBasedRejected(&'static str),
+
+    #[error("keyboard-interactive prompts were cancelled")]
+    PromptCancelled,
+
+    #[error("authentication failed, methods that can continue: ({})", .0.join(","))]
+    NoMethodsLeft(Vec<String>),
+}
@@ -0,0 +1,537 @@
+use crate::errors::HostKeysError;
+use crate::known_hosts::{HostKeyStatus, HostKeyVerifier, KnownHosts};
+use crate::protocol::message_ids::SshMessageID;
+use crypto::encoding::{Reader, Writer};
+use crypto::registry::AlgorithmPolicy;
+use crypto::signature::certificate;
+use crypto::signature::errors::KeyError;
+use crypto::signature::{Signature, Signer};
+
+// Global requests of the OpenSSH host key rotation extension, described
+// in PROTOCOL, section 2.5.
+pub const HOSTKEYS_REQUEST: &str = "hostkeys-00@openssh.com";
+pub const HOSTKEYS_PROVE_REQUEST: &str = "hostkeys-prove-00@openssh.com";
+
+// Global request sent by the server after authentication, so the client
+// learns all host keys and not only the one of the key exchange.
+pub fn announce_host_keys(host_keys: &[&dyn Signer]) -> Vec<u8> {
+    let blobs: Vec<_> = host_keys.iter().map(|k| k.public_key().to_blob()).collect();
+
+    global_request(HOSTKEYS_REQUEST, false, &blobs)
+}
+
+// Reply to the prove request of a client, with a signature of every
+// requested key. Requests for keys which are not loaded fail and have to
+// be answered with SSH_MSG_REQUEST_FAILURE.
+pub fn prove_host_keys(
+    host_keys: &[&dyn Signer],
+    session_id: &[u8],
+    request: &[u8],
+) -> Result<Vec<u8>, HostKeysError> {
+    let mut writer = Writer::new();
+    writer.put_u8(SshMessageID::RequestSuccess as u8);
+
+    for blob in read_global_request(request, HOSTKEYS_PROVE_REQUEST)? {
+        let key = host_keys
+            .iter()
+            .find(|k| k.public_key().to_blob() == blob)
+            .ok_or_else(|| {
+                HostKeysError::UnknownHostKey(
+                    Reader::new(blob)
+                        .read_utf8()
+                        .unwrap_or_default()
+                        .to_string(),
+                )
+            })?;
+
+        writer.put_string(&key.sign(&proof_data(session_id, blob))?.to_blob());
+    }
+
+    Ok(writer.into_bytes())
+}
+
+// Host keys announced by the server compared with the known hosts, like
+// `UpdateHostKeys` of OpenSSH. Keys which are not known yet have to be
+// proven by the server before the known hosts are updated.
+#[derive(Debug, Clone)]
+pub struct HostKeyRotation {
+    host: String,
+    port: u16,
+    session_id: Vec<u8>,
+    new_keys: Vec<Vec<u8>>,
+    deprecated_keys: Vec<Vec<u8>>,
+    proven: bool,
+}
+
+impl HostKeyRotation {
+    // Compare the announcement with the known hosts. `session_key` is the
+    // host key of the key exchange, nothing is updated unless it is listed
+    // for the host by a plain entry. Entries with wildcards could stand
+    // for other hosts, so they are left alone as well. Returns None when
+    // there is nothing to update.
+    pub fn new(
+        known_hosts: &KnownHosts,
+        host: &str,
+        port: u16,
+        session_key: &[u8],
+        session_id: &[u8],
+        announcement: &[u8],
+        policy: &AlgorithmPolicy,
+    ) -> Result<Option<Self>, HostKeysError> {
+        let announced = read_global_request(announcement, HOSTKEYS_REQUEST)?;
+        if !announced.contains(&session_key) {
+            return Err(HostKeysError::UnexpectedMessage(
+                "host key of the key exchange was not announced",
+            ));
+        }
+
+        let mut new_keys = Vec::new();
+        for (i, blob) in announced.iter().enumerate() {
+            if announced[..i].contains(blob) {
+                return Err(HostKeysError::UnexpectedMessage(
+                    "host key was announced twice",
+                ));
+            }
+
+            // Key types which are not supported or not allowed by the
+            // policy are skipped, certificates are verified by their
+            // authority instead.
+            match policy.parse_public_key(blob) {
+                Ok(key) if certificate::is_certificate(key.algorithm()) => continue,
+                Ok(_) => {}
+                Err(
+                    KeyError::UnsupportedAlgorithm(_)
+                    | KeyError::ModulusTooSmall(_)
+                    | KeyError::UnsupportedModulusSize(_),
+                ) => continue,
+                Err(e) => return Err(e.into()),
+            }
+
+            // Revoked keys are never added back.
+            if known_hosts.check(host, port, blob) == HostKeyStatus::Mismatch {
+                new_keys.push(blob.to_vec());
+            }
+        }
+
+        let entries: Vec<_> = known_hosts
+            .entries()
+            .filter(|e| e.get_marker().is_none() && e.matches(host, port))
+            .collect();
+        if known_hosts.check(host, port, session_key) != HostKeyStatus::Match
+            || entries.iter().any(|e| e.has_wildcards())
+        {
+            return Ok(None);
+        }
+
+        let mut deprecated_keys: Vec<Vec<u8>> = Vec::new();
+        for entry in entries {
+            let blob = entry.get_key().get_blob();
+            if !announced.contains(&blob) && !deprecated_keys.iter().any(|k| k == blob) {
+                deprecated_keys.push(blob.to_vec());
+            }
+        }
+
+        if new_keys.is_empty() && deprecated_keys.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            host: host.to_string(),
+            port,
+            session_id: session_id.to_vec(),
+            proven: new_keys.is_empty(),
+            new_keys,
+            deprecated_keys,
+        }))
+    }
+
+    // Global request asking the server to sign the new keys, None when
+    // only keys are removed.
+    pub fn prove_request(&self) -> Option<Vec<u8>> {
+        match self.new_keys.is_empty() {
+            true => None,
+            false => Some(global_request(HOSTKEYS_PROVE_REQUEST, true, &self.new_keys)),
+        }
+    }
+
+    // Check the reply to the prove request, which has to hold a signature
+    // of every new key in the order of the request.
+    pub fn verify_proof(
+        &mut self,
+        reply: &[u8],
+        policy: &AlgorithmPolicy,
+    ) -> Result<(), HostKeysError> {
+        let mut reader = Reader::new(reply);
+        match reader.read_u8()? {
+            id if id == SshMessageID::RequestSuccess as u8 => {}
+            id if id == SshMessageID::RequestFailure as u8 => {
+                return Err(HostKeysError::ProofFailed(
+                    "server refused to prove its host keys",
+                ))
+            }
+            _ => {
+                return Err(HostKeysError::UnexpectedMessage(
+                    "expected a reply to the prove request",
+                ))
+            }
+        }
+
+        for blob in &self.new_keys {
+            let signature = Signature::from_blob(reader.read_string()?)?;
+            policy
+                .parse_public_key(blob)?
+                .verify(&proof_data(&self.session_id, blob), &signature)?;
+        }
+        reader.finish()?;
+
+        self.proven = true;
+        Ok(())
+    }
+
+    // Keys to add once they are proven.
+    pub fn get_new_keys(&self) -> &[Vec<u8>] {
+        &self.new_keys
+    }
+
+    // Known keys of the host which the server no longer has.
+    pub fn get_deprecated_keys(&self) -> &[Vec<u8>] {
+        &self.deprecated_keys
+    }
+
+    pub fn is_proven(&self) -> bool {
+        self.proven
+    }
+
+    // Update the known hosts of the verifier, after the user agreed to it.
+    pub fn apply(&self, verifier: &mut HostKeyVerifier) -> Result<(), HostKeysError> {
+        if !self.proven {
+            return Err(HostKeysError::ProofFailed("new host keys are not proven"));
+        }
+
+        Ok(verifier.update_host_keys(
+            &self.host,
+            self.port,
+            &self.new_keys,
+            &self.deprecated_keys,
+        )?)
+    }
+}
+
+fn global_request(name: &str, want_reply: bool, blobs: &[Vec<u8>]) -> Vec<u8> {
+    let mut writer = Writer::new();
+    writer
+        .put_u8(SshMessageID::GlobalRequest as u8)
+        .put_string(name.as_bytes())
+        .put_bool(want_reply);
+    for blob in blobs {
+        writer.put_string(blob);
+    }
+
+    writer.into_bytes()
+}
+
+fn read_global_request<'a>(payload: &'a [u8], name: &str) -> Result<Vec<&'a [u8]>, HostKeysError> {
+    let mut reader = Reader::new(payload);
+    if reader.read_u8()? != SshMessageID::GlobalRequest as u8 {
+        return Err(HostKeysError::UnexpectedMessage(
+            "expected a global request",
+        ));
+    }
+    if reader.read_utf8()? != name {
+        return Err(HostKeysError::UnexpectedMessage(
+            "global request has another name",
+        ));
+    }
+    reader.read_bool()?;
+
+    let mut blobs = Vec::new();
+    while !reader.remaining().is_empty() {
+        blobs.push(reader.read_string()?);
+    }
+
+    Ok(blobs)
+}
+
+// Signed data of a proof, bound to the session so it cannot be replayed.
+fn proof_data(session_id: &[u8], blob: &[u8]) -> Vec<u8> {
+    let mut writer = Writer::new();
+    writer
+        .put_string(HOSTKEYS_PROVE_REQUEST.as_bytes())
+        .put_string(session_id)
+        .put_string(blob);
+
+    writer.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SharedRng;
+    use crypto::keys::public::PublicKeyEntry;
+    use crypto::keys::{self, PrivateKey};
+    use crypto::signature::ed25519::Ed25519PrivateKey;
+    use rstest::rstest;
+    use std::fs;
+
+    const ID_RSA: &str = include_str!("../../rsa_keys/id_rsa");
+    const SESSION_ID: &[u8] = b"session identifier";
+
+    struct Server {
+        rsa: PrivateKey,
+        ed25519: PrivateKey,
+    }
+
+    impl Server {
+        fn new() -> Self {
+            let (rsa, _) = keys::decode(ID_RSA, || None).unwrap();
+            let ed25519 = Ed25519PrivateKey::generate(&mut SharedRng::seeded(42));
+
+            Self {
+                rsa,
+                ed25519: PrivateKey::Ed25519(ed25519),
+            }
+        }
+
+        fn rsa_blob(&self) -> Vec<u8> {
+            self.rsa.public_key().to_blob()
+        }
+
+        fn ed25519_blob(&self) -> Vec<u8> {
+            self.ed25519.public_key().to_blob()
+        }
+    }
+
+    fn line(host: &str, blob: &[u8]) -> String {
+        let entry = PublicKeyEntry::new(blob.to_vec(), "").unwrap();
+        format!("{} {}\n", host, entry.to_line())
+    }
+
+    fn rotation(
+        known_hosts: &KnownHosts,
+        session_key: &[u8],
+        announcement: &[u8],
+    ) -> Result<Option<HostKeyRotation>, HostKeysError> {
+        HostKeyRotation::new(
+            known_hosts,
+            "example.com",
+            22,
+            session_key,
+            SESSION_ID,
+            announcement,
+            &AlgorithmPolicy::default(),
+        )
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "parustiko-hostkeys-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_file(&path);
+
+        path
+    }
+
+    #[test]
+    fn test_rotate_to_new_host_key() {
+        let server = Server::new();
+        let host_keys: [&dyn Signer; 2] = [&server.rsa, &server.ed25519];
+        let known_hosts = KnownHosts::parse(&line("example.com", &server.rsa_blob()));
+
+        let announcement = announce_host_keys(&host_keys);
+        let mut rotation = rotation(&known_hosts, &server.rsa_blob(), &announcement)
+            .unwrap()
+            .unwrap();
+        assert_eq!(rotation.get_new_keys(), [server.ed25519_blob()]);
+        assert!(rotation.get_deprecated_keys().is_empty());
+        assert!(!rotation.is_proven());
+
+        let mut verifier = HostKeyVerifier::new(known_hosts);
+        assert_eq!(
+            rotation.apply(&mut verifier).unwrap_err().to_string(),
+            "host key proof failed: (new host keys are not proven)"
+        );
+
+        let request = rotation.prove_request().unwrap();
+        let reply = prove_host_keys(&host_keys, SESSION_ID, &request).unwrap();
+        rotation
+            .verify_proof(&reply, &AlgorithmPolicy::default())
+            .unwrap();
+        rotation.apply(&mut verifier).unwrap();
+
+        let known_hosts = verifier.get_known_hosts();
+        for blob in [server.rsa_blob(), server.ed25519_blob()] {
+            assert_eq!(
+                known_hosts.check("example.com", 22, &blob),
+                HostKeyStatus::Match
+            );
+        }
+    }
+
+    #[test]
+    fn test_remove_deprecated_host_key() {
+        let server = Server::new();
+        let path = temp_path("deprecated");
+        fs::write(
+            &path,
+            format!(
+                "# keys\n{}{}",
+                line("example.com,192.0.2.1", &server.rsa_blob()),
+                line("example.com", &server.ed25519_blob())
+            ),
+        )
+        .unwrap();
+        let mut verifier = HostKeyVerifier::load(&path).unwrap();
+
+        let announcement = announce_host_keys(&[&server.ed25519]);
+        let rotation = rotation(
+            verifier.get_known_hosts(),
+            &server.ed25519_blob(),
+            &announcement,
+        )
+        .unwrap()
+        .unwrap();
+        assert!(rotation.is_proven());
+        assert!(rotation.prove_request().is_none());
+        assert_eq!(rotation.get_deprecated_keys(), [server.rsa_blob()]);
+        rotation.apply(&mut verifier).unwrap();
+
+        let reloaded = KnownHosts::load(&path).unwrap();
+        assert_eq!(
+            reloaded.check("example.com", 22, &server.rsa_blob()),
+            HostKeyStatus::Mismatch
+        );
+        assert_eq!(
+            reloaded.check("192.0.2.1", 22, &server.rsa_blob()),
+            HostKeyStatus::Match
+        );
+        assert!(reloaded
+            .to_string()
+            .starts_with("# keys\n192.0.2.1 ssh-rsa "));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[rstest]
+    #[case("example.com", true)]
+    #[case("*.com", false)]
+    #[case("other.com", false)]
+    fn test_rotation_needs_plain_entry_of_session_key(#[case] host: &str, #[case] expected: bool) {
+        let server = Server::new();
+        let known_hosts = KnownHosts::parse(&line(host, &server.rsa_blob()));
+        let announcement = announce_host_keys(&[&server.rsa, &server.ed25519]);
+
+        let result = rotation(&known_hosts, &server.rsa_blob(), &announcement).unwrap();
+
+        assert_eq!(result.is_some(), expected);
+    }
+
+    #[test]
+    fn test_nothing_to_rotate() {
+        let server = Server::new();
+        let known_hosts = KnownHosts::parse(&line("example.com", &server.ed25519_blob()));
+
+        // Unsupported key types are skipped.
+        let mut dss = Writer::new();
+        dss.put_string(b"ssh-dss").put_string(&[1, 2, 3]);
+        let announcement = global_request(
+            HOSTKEYS_REQUEST,
+            false,
+            &[server.ed25519_blob(), dss.into_bytes()],
+        );
+
+        assert!(
+            rotation(&known_hosts, &server.ed25519_blob(), &announcement)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[rstest]
+    #[case(2048, true)]
+    #[case(4096, false)]
+    fn test_keys_rejected_by_policy_are_skipped(#[case] min_bits: usize, #[case] expected: bool) {
+        let server = Server::new();
+        let known_hosts = KnownHosts::parse(&line("example.com", &server.ed25519_blob()));
+        let announcement = announce_host_keys(&[&server.ed25519, &server.rsa]);
+        let policy = AlgorithmPolicy {
+            min_rsa_modulus_bits: min_bits,
+            ..AlgorithmPolicy::default()
+        };
+
+        let result = HostKeyRotation::new(
+            &known_hosts,
+            "example.com",
+            22,
+            &server.ed25519_blob(),
+            SESSION_ID,
+            &announcement,
+            &policy,
+        )
+        .unwrap();
+
+        assert_eq!(result.is_some(), expected);
+    }
+
+    #[rstest]
+    #[case(true, "unexpected host keys message: (host key was announced twice)")]
+    #[case(
+        false,
+        "unexpected host keys message: (host key of the key exchange was not announced)"
+    )]
+    fn test_invalid_announcement(#[case] twice: bool, #[case] expected: &str) {
+        let server = Server::new();
+        let known_hosts = KnownHosts::parse(&line("example.com", &server.rsa_blob()));
+        let blobs = match twice {
+            true => vec![server.rsa_blob(), server.rsa_blob()],
+            false => vec![server.ed25519_blob()],
+        };
+        let announcement = global_request(HOSTKEYS_REQUEST, false, &blobs);
+
+        let err = rotation(&known_hosts, &server.rsa_blob(), &announcement).unwrap_err();
+
+        assert_eq!(err.to_string(), expected);
+    }
+
+    #[test]
+    fn test_proof_of_other_session_is_rejected() {
+        let server = Server::new();
+        let host_keys: [&dyn Signer; 2] = [&server.rsa, &server.ed25519];
+        let known_hosts = KnownHosts::parse(&line("example.com", &server.rsa_blob()));
+        let mut rotation = rotation(
+            &known_hosts,
+            &server.rsa_blob(),
+            &announce_host_keys(&host_keys),
+        )
+        .unwrap()
+        .unwrap();
+
+        let request = rotation.prove_request().unwrap();
+        let reply = prove_host_keys(&host_keys, b"other session", &request).unwrap();
+        let err = rotation
+            .verify_proof(&reply, &AlgorithmPolicy::default())
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "host key proof signature is invalid: (signature verification failed)"
+        );
+        assert!(!rotation.is_proven());
+        assert!(rotation
+            .verify_proof(
+                &[SshMessageID::RequestFailure as u8],
+                &AlgorithmPolicy::default()
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_prove_key_which_is_not_loaded() {
+        let server = Server::new();
+        let request = global_request(HOSTKEYS_PROVE_REQUEST, true, &[server.ed25519_blob()]);
+
+        let err = prove_host_keys(&[&server.rsa], SESSION_ID, &request).unwrap_err();
+
+        assert_eq!(err.to_string(), "host key is not loaded: (ssh-ed25519)");
+    }
+}
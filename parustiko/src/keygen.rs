@@ -0,0 +1,457 @@
+use crypto::keys::fingerprint::{fingerprint, key_size, randomart, FingerprintHash};
+use crypto::keys::openssh::{self, DEFAULT_KDF_ROUNDS};
+use crypto::keys::public::PublicKeyEntry;
+use crypto::keys::{self, KeyType, PrivateKey, DEFAULT_RSA_BITS};
+use crypto::secret::SecretBytes;
+use crypto::signature::ecdsa::EcdsaCurve;
+use crypto::signature::Signer;
+use parustiko::rng::SharedRng;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "\
+usage: parustiko-keygen [-t ed25519 | ecdsa | rsa] [-b bits] [-C comment]
+                        [-N new_passphrase] [-a rounds] -f file
+       parustiko-keygen -l [-v] [-E sha256 | md5] [-P passphrase] -f file
+       parustiko-keygen -p [-P old_passphrase] -N new_passphrase [-a rounds] -f file";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Generate,
+    Fingerprint,
+    ChangePassphrase,
+}
+
+// Command line options, named after the ones of ssh-keygen. An empty
+// passphrase leaves the key unencrypted, changing the passphrase needs an
+// explicit new one.
+struct Options {
+    mode: Mode,
+    key_type: KeyType,
+    comment: String,
+    passphrase: Option<SecretBytes>,
+    old_passphrase: Option<SecretBytes>,
+    rounds: u32,
+    hash: FingerprintHash,
+    visual: bool,
+    file: PathBuf,
+}
+
+// Passphrases are only shown as present or absent.
+impl fmt::Debug for Options {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redacted = |p: &Option<SecretBytes>| p.as_ref().map(|_| "[REDACTED]");
+
+        f.debug_struct("Options")
+            .field("mode", &self.mode)
+            .field("key_type", &self.key_type)
+            .field("comment", &self.comment)
+            .field("passphrase", &redacted(&self.passphrase))
+            .field("old_passphrase", &redacted(&self.old_passphrase))
+            .field("rounds", &self.rounds)
+            .field("hash", &self.hash)
+            .field("visual", &self.visual)
+            .field("file", &self.file)
+            .finish()
+    }
+}
+
+fn main() {
+    let result = parse_args(std::env::args().skip(1)).and_then(|options| match options.mode {
+        Mode::Generate => generate(&options),
+        Mode::Fingerprint => show_fingerprint(&options),
+        Mode::ChangePassphrase => change_passphrase(&options),
+    });
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn parse_args<I>(args: I) -> Result<Options, Box<dyn std::error::Error>>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter();
+    let mut mode = Mode::Generate;
+    let mut key_type = String::from("ed25519");
+    let mut bits = None;
+    let mut comment = String::new();
+    let mut passphrase = None;
+    let mut old_passphrase = None;
+    let mut rounds = DEFAULT_KDF_ROUNDS;
+    let mut hash = FingerprintHash::Sha256;
+    let mut visual = false;
+    let mut file = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or(format!("missing value for {}\n{}", arg, USAGE))
+        };
+
+        match arg.as_str() {
+            "-l" => mode = Mode::Fingerprint,
+            "-p" => mode = Mode::ChangePassphrase,
+            "-v" => visual = true,
+            "-t" => key_type = value()?,
+            "-b" => bits = Some(value()?.parse::<usize>()?),
+            "-C" => comment = value()?,
+            "-N" => passphrase = Some(SecretBytes::from(value()?.into_bytes())),
+            "-P" => old_passphrase = Some(SecretBytes::from(value()?.into_bytes())),
+            "-a" => rounds = value()?.parse()?,
+            "-E" => {
+                hash = match value()?.to_lowercase().as_str() {
+                    "sha256" => FingerprintHash::Sha256,
+                    "md5" => FingerprintHash::Md5,
+                    other => return Err(format!("unsupported fingerprint hash: {}", other).into()),
+                }
+            }
+            "-f" => file = Some(PathBuf::from(value()?)),
+            other => return Err(format!("unknown option: {}\n{}", other, USAGE).into()),
+        }
+    }
+
+    let key_type = match (key_type.as_str(), bits) {
+        ("ed25519", None | Some(256)) => KeyType::Ed25519,
+        ("ecdsa", None | Some(256)) => KeyType::Ecdsa(EcdsaCurve::NistP256),
+        ("ecdsa", Some(384)) => KeyType::Ecdsa(EcdsaCurve::NistP384),
+        ("ecdsa", Some(521)) => KeyType::Ecdsa(EcdsaCurve::NistP521),
+        ("rsa", bits) => KeyType::Rsa(bits.unwrap_or(DEFAULT_RSA_BITS)),
+        (name, Some(bits)) => {
+            return Err(format!("unsupported key type or size: {} {}", name, bits).into())
+        }
+        (name, None) => return Err(format!("unsupported key type: {}", name).into()),
+    };
+    if mode == Mode::ChangePassphrase && passphrase.is_none() {
+        return Err(format!("-p needs the new passphrase with -N\n{}", USAGE).into());
+    }
+
+    Ok(Options {
+        mode,
+        key_type,
+        comment,
+        passphrase,
+        old_passphrase,
+        rounds,
+        hash,
+        visual,
+        file: file.ok_or(format!("no key file given\n{}", USAGE))?,
+    })
+}
+
+fn generate(options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    let public_file = public_path(&options.file);
+    for path in [&options.file, &public_file] {
+        if path.exists() {
+            return Err(format!("{} already exists", path.display()).into());
+        }
+    }
+
+    let key = PrivateKey::generate(options.key_type, &mut SharedRng::system())?;
+    println!("Generating public/private {} key pair.", key.algorithm());
+
+    let text = encode_private_key(&key, &options.comment, options)?;
+    let entry = PublicKeyEntry::new(key.public_key().to_blob(), &options.comment)?;
+    // The private key is only kept when its `.pub` file could be written.
+    let result = create_private_file(&options.file).and_then(|mut file| {
+        file.write_all(text.as_bytes())?;
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&public_file)?
+            .write_all(format!("{}\n", entry.to_line()).as_bytes())
+    });
+    if let Err(e) = result {
+        let _ = fs::remove_file(&options.file);
+        return Err(e.into());
+    }
+    println!(
+        "Your identification has been saved in {}",
+        options.file.display()
+    );
+    println!(
+        "Your public key has been saved in {}",
+        public_file.display()
+    );
+
+    println!("The key fingerprint is:");
+    println!(
+        "{} {}",
+        fingerprint(entry.get_blob(), options.hash),
+        options.comment
+    );
+    println!("The key's randomart image is:");
+    println!("{}", randomart(entry.get_blob(), options.hash)?);
+
+    Ok(())
+}
+
+// Public key files are read as they are, private keys are only decrypted
+// when they are not accompanied by their `.pub` file.
+fn show_fingerprint(options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    let text = fs::read_to_string(&options.file)?;
+    let entry = match PublicKeyEntry::decode(&text) {
+        Ok(entry) => entry,
+        Err(_) => match fs::read_to_string(public_path(&options.file)) {
+            Ok(text) => PublicKeyEntry::decode(&text)?,
+            Err(_) => {
+                let (key, comment) = keys::decode(&text, || secret(&options.old_passphrase))?;
+                PublicKeyEntry::new(key.public_key().to_blob(), &comment)?
+            }
+        },
+    };
+
+    let (name, bits) = key_size(entry.get_blob())?;
+    let comment = match entry.get_comment() {
+        "" => "no comment",
+        comment => comment,
+    };
+    println!(
+        "{} {} {} ({})",
+        bits,
+        fingerprint(entry.get_blob(), options.hash),
+        comment,
+        name
+    );
+    if options.visual {
+        println!("{}", randomart(entry.get_blob(), options.hash)?);
+    }
+
+    Ok(())
+}
+
+fn change_passphrase(options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    let text = fs::read_to_string(&options.file)?;
+    let (key, comment) = keys::decode(&text, || secret(&options.old_passphrase))?;
+
+    let text = encode_private_key(&key, &comment, options)?;
+    replace_private_file(&options.file, &text)?;
+    println!("Your identification has been saved with the new passphrase.");
+
+    Ok(())
+}
+
+fn encode_private_key(
+    key: &PrivateKey,
+    comment: &str,
+    options: &Options,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let passphrase = secret(&options.passphrase);
+    let text = openssh::encode(
+        key,
+        comment,
+        passphrase.as_ref(),
+        options.rounds,
+        &mut SharedRng::system(),
+    )?;
+
+    Ok(text)
+}
+
+// Private key files are never opened when they already exist, so they
+// always end up readable by their owner only.
+fn create_private_file(path: &Path) -> io::Result<File> {
+    let mut file = OpenOptions::new();
+    file.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut file, 0o600);
+
+    file.open(path)
+}
+
+// The new key is written next to the old one and renamed over it, so
+// the old key stays intact until the new one is completely written.
+fn replace_private_file(path: &Path, text: &str) -> io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+
+    // Leftover from an interrupted run.
+    let _ = fs::remove_file(&temp);
+    let result = create_private_file(Path::new(&temp))
+        .and_then(|mut file| {
+            file.write_all(text.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+
+    result
+}
+
+fn secret(passphrase: &Option<SecretBytes>) -> Option<SecretBytes> {
+    passphrase.clone().filter(|p| !p.is_empty())
+}
+
+fn public_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".pub");
+
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    fn temp_key_file(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("parustiko-keygen-{}-{}", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(public_path(&path));
+
+        path
+    }
+
+    #[rstest]
+    #[case("-f id", KeyType::Ed25519)]
+    #[case("-t ecdsa -f id", KeyType::Ecdsa(EcdsaCurve::NistP256))]
+    #[case("-t ecdsa -b 521 -f id", KeyType::Ecdsa(EcdsaCurve::NistP521))]
+    #[case("-t rsa -f id", KeyType::Rsa(3072))]
+    #[case("-t rsa -b 4096 -f id", KeyType::Rsa(4096))]
+    fn test_parse_key_type(#[case] line: &str, #[case] expected: KeyType) {
+        let options = parse_args(args(line)).unwrap();
+
+        assert_eq!(options.mode, Mode::Generate);
+        assert_eq!(options.key_type, expected);
+        assert_eq!(options.file, PathBuf::from("id"));
+    }
+
+    #[rstest]
+    #[case("-t dsa -f id", "unsupported key type: dsa")]
+    #[case("-t ecdsa -b 512 -f id", "unsupported key type or size: ecdsa 512")]
+    #[case("-l -E sha1 -f id", "unsupported fingerprint hash: sha1")]
+    #[case("-t rsa", "no key file given")]
+    #[case("-t rsa -f", "missing value for -f")]
+    #[case("-x", "unknown option: -x")]
+    #[case("-p -P first -f id", "-p needs the new passphrase with -N")]
+    fn test_parse_invalid_args(#[case] line: &str, #[case] expected: &str) {
+        let err = parse_args(args(line)).unwrap_err().to_string();
+
+        assert_eq!(err.lines().next(), Some(expected));
+    }
+
+    #[test]
+    fn test_parse_fingerprint_options() {
+        let options = parse_args(args("-l -v -E md5 -f id.pub")).unwrap();
+
+        assert_eq!(options.mode, Mode::Fingerprint);
+        assert_eq!(options.hash, FingerprintHash::Md5);
+        assert!(options.visual);
+    }
+
+    #[test]
+    fn test_debug_redacts_passphrases() {
+        let options = parse_args(args("-p -P hunter2 -N swordfish -f id")).unwrap();
+        let debug = format!("{:?}", options);
+
+        assert!(!debug.contains("hunter2"));
+        assert!(!debug.contains("swordfish"));
+        assert!(debug.contains("passphrase: Some(\"[REDACTED]\")"));
+    }
+
+    #[test]
+    fn test_generate_and_change_passphrase() {
+        let path = temp_key_file("ed25519");
+        let file = path.to_str().unwrap();
+        let options = parse_args(args(&format!("-C test -N first -a 1 -f {}", file))).unwrap();
+
+        generate(&options).unwrap();
+        assert!(generate(&options)
+            .unwrap_err()
+            .to_string()
+            .ends_with("already exists"));
+
+        let text = fs::read_to_string(&path).unwrap();
+        let public = PublicKeyEntry::decode(&fs::read_to_string(public_path(&path)).unwrap());
+        let (key, comment) =
+            keys::decode(&text, || secret(&Some(SecretBytes::from(&b"first"[..])))).unwrap();
+        assert_eq!(comment, "test");
+        assert_eq!(public.unwrap().get_blob(), key.public_key().to_blob());
+
+        let options = parse_args(args(&format!("-p -P first -N second -a 1 -f {}", file))).unwrap();
+        change_passphrase(&options).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        let old = keys::decode(&text, || secret(&Some(SecretBytes::from(&b"first"[..]))));
+        let (changed, comment) =
+            keys::decode(&text, || secret(&Some(SecretBytes::from(&b"second"[..])))).unwrap();
+        assert_eq!(old.unwrap_err().to_string(), "incorrect passphrase");
+        assert_eq!(comment, "test");
+        assert_eq!(changed.public_key().to_blob(), key.public_key().to_blob());
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(public_path(&path)).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_generate_removes_private_key_when_public_key_fails() {
+        let path = temp_key_file("orphan");
+        let file = path.to_str().unwrap();
+        // A dangling link is not seen as existing, but cannot be created.
+        std::os::unix::fs::symlink(temp_key_file("missing"), public_path(&path)).unwrap();
+
+        let options = parse_args(args(&format!("-f {}", file))).unwrap();
+        assert!(generate(&options).is_err());
+        assert!(!path.exists());
+
+        fs::remove_file(public_path(&path)).unwrap();
+    }
+
+    #[test]
+    fn test_change_passphrase_to_empty_removes_it() {
+        let path = temp_key_file("remove-passphrase");
+        let file = path.to_str().unwrap();
+        generate(&parse_args(args(&format!("-N first -a 1 -f {}", file))).unwrap()).unwrap();
+
+        let line = ["-p", "-P", "first", "-N", "", "-f", file];
+        let options = parse_args(line.map(str::to_string)).unwrap();
+        change_passphrase(&options).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(keys::decode(&text, || None).is_ok());
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(public_path(&path)).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_change_passphrase_restricts_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_key_file("permissions");
+        let file = path.to_str().unwrap();
+        generate(&parse_args(args(&format!("-N first -a 1 -f {}", file))).unwrap()).unwrap();
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        let options = parse_args(args(&format!("-p -P first -N second -a 1 -f {}", file))).unwrap();
+        change_passphrase(&options).unwrap();
+
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        assert!(!Path::new(&temp).exists());
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(public_path(&path)).unwrap();
+    }
+}
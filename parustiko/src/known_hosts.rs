@@ -0,0 +1,1031 @@
+use crate::errors::KnownHostsError;
+use crate::rng::SharedRng;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crypto::encoding::Reader;
+use crypto::keys::public::PublicKeyEntry;
+use crypto::mac::hmac::hmac;
+use crypto::registry::AlgorithmPolicy;
+use crypto::signature::certificate::{self, Certificate, CertificateType};
+use rand::RngCore;
+use sha1::Sha1;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DEFAULT_PORT: u16 = 22;
+const HASH_MAGIC: &str = "|1|";
+// Salt of hashed host names, as long as the SHA-1 output like in OpenSSH.
+const HASH_SALT_SIZE: usize = 20;
+const CERT_AUTHORITY_MARKER: &str = "@cert-authority";
+const REVOKED_MARKER: &str = "@revoked";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostKeyStatus {
+    // The key is listed for the host.
+    Match,
+    // Other keys are listed for the host, but not this one.
+    Mismatch,
+    // No keys are listed for the host.
+    Unknown,
+    // The key is marked as revoked and must never be accepted.
+    Revoked,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Marker {
+    // The key is a certificate authority for host certificates.
+    CertAuthority,
+    Revoked,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HostPatterns {
+    // `|1|<salt>|<hmac>`: HMAC-SHA1 of the host name keyed with the salt.
+    Hashed { salt: Vec<u8>, hash: Vec<u8> },
+    // Comma separated patterns with `*` and `?` wildcards, negated with `!`.
+    Plain(Vec<String>),
+}
+
+impl HostPatterns {
+    fn parse(field: &str) -> Option<Self> {
+        if let Some(hashed) = field.strip_prefix(HASH_MAGIC) {
+            let (salt, hash) = hashed.split_once('|')?;
+
+            return Some(Self::Hashed {
+                salt: STANDARD.decode(salt).ok()?,
+                hash: STANDARD.decode(hash).ok()?,
+            });
+        }
+
+        Some(Self::Plain(field.split(',').map(str::to_string).collect()))
+    }
+
+    // A matching negated pattern excludes the host, even when another
+    // pattern of the line matches it. Host names are compared in lower
+    // case, hashed ones are lowercased before hashing like in OpenSSH.
+    fn matches(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        match self {
+            Self::Hashed { salt, hash } => hmac::<Sha1>(salt, name.as_bytes()) == *hash,
+            Self::Plain(patterns) => {
+                let mut matched = false;
+
+                for pattern in patterns {
+                    let pattern = pattern.to_lowercase();
+                    match pattern.strip_prefix('!') {
+                        Some(negated) if wildcard_match(negated.as_bytes(), name.as_bytes()) => {
+                            return false
+                        }
+                        Some(_) => {}
+                        None => matched |= wildcard_match(pattern.as_bytes(), name.as_bytes()),
+                    }
+                }
+
+                matched
+            }
+        }
+    }
+}
+
+impl HostPatterns {
+    fn has_wildcards(&self) -> bool {
+        match self {
+            Self::Hashed { .. } => false,
+            Self::Plain(patterns) => patterns.iter().any(|p| p.contains(['*', '?', '!'])),
+        }
+    }
+
+    // Take the name out of the patterns, returns whether anything is left,
+    // or `None` if the name is only matched by a wildcard.
+    fn remove(&mut self, name: &str) -> Option<bool> {
+        match self {
+            Self::Hashed { .. } => Some(false),
+            Self::Plain(patterns) => {
+                let len = patterns.len();
+                patterns.retain(|p| !p.eq_ignore_ascii_case(name));
+
+                (patterns.len() < len).then_some(!patterns.is_empty())
+            }
+        }
+    }
+}
+
+impl fmt::Display for HostPatterns {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Hashed { salt, hash } => write!(
+                f,
+                "{}{}|{}",
+                HASH_MAGIC,
+                STANDARD.encode(salt),
+                STANDARD.encode(hash)
+            ),
+            Self::Plain(patterns) => f.write_str(&patterns.join(",")),
+        }
+    }
+}
+
+// Single `[marker] <host patterns> <key type> <base64 key> [comment]` line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KnownHostsEntry {
+    marker: Option<Marker>,
+    hosts: HostPatterns,
+    key: PublicKeyEntry,
+}
+
+impl KnownHostsEntry {
+    fn parse(line: &str) -> Option<Self> {
+        let (first, rest) = split_field(line);
+        let (marker, hosts, rest) = match first {
+            CERT_AUTHORITY_MARKER => {
+                let (hosts, rest) = split_field(rest);
+                (Some(Marker::CertAuthority), hosts, rest)
+            }
+            REVOKED_MARKER => {
+                let (hosts, rest) = split_field(rest);
+                (Some(Marker::Revoked), hosts, rest)
+            }
+            _ if first.starts_with('@') => return None,
+            _ => (None, first, rest),
+        };
+
+        Some(Self {
+            marker,
+            hosts: HostPatterns::parse(hosts)?,
+            key: PublicKeyEntry::from_line(rest).ok()?,
+        })
+    }
+
+    pub fn get_marker(&self) -> Option<Marker> {
+        self.marker
+    }
+
+    pub fn get_key(&self) -> &PublicKeyEntry {
+        &self.key
+    }
+
+    pub fn is_hashed(&self) -> bool {
+        matches!(self.hosts, HostPatterns::Hashed { .. })
+    }
+
+    pub fn matches(&self, host: &str, port: u16) -> bool {
+        self.hosts.matches(&host_name(host, port))
+    }
+
+    // Whether the host patterns contain wildcards or negations, so the
+    // entry may stand for more hosts than its names.
+    pub fn has_wildcards(&self) -> bool {
+        self.hosts.has_wildcards()
+    }
+}
+
+impl fmt::Display for KnownHostsEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.marker {
+            Some(Marker::CertAuthority) => write!(f, "{} ", CERT_AUTHORITY_MARKER)?,
+            Some(Marker::Revoked) => write!(f, "{} ", REVOKED_MARKER)?,
+            None => {}
+        }
+
+        write!(f, "{} {}", self.hosts, self.key.to_line())
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Line {
+    Entry(KnownHostsEntry),
+    // Comments, empty lines and lines which cannot be parsed are kept as
+    // they are, so writing the file back does not lose them.
+    Other(String),
+}
+
+// Contents of a known_hosts file in the OpenSSH format. Like OpenSSH,
+// lines which cannot be parsed are skipped when looking up hosts.
+#[derive(Debug, Clone, Default)]
+pub struct KnownHosts {
+    lines: Vec<Line>,
+}
+
+impl KnownHosts {
+    pub fn parse(text: &str) -> Self {
+        let lines = text
+            .lines()
+            .map(|line| {
+                let trimmed = line.trim();
+                match trimmed.is_empty() || trimmed.starts_with('#') {
+                    true => None,
+                    false => KnownHostsEntry::parse(trimmed),
+                }
+                .map_or_else(|| Line::Other(line.to_string()), Line::Entry)
+            })
+            .collect();
+
+        Self { lines }
+    }
+
+    // A missing file is the same as an empty one.
+    pub fn load(path: &Path) -> Result<Self, KnownHostsError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &KnownHostsEntry> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Entry(entry) => Some(entry),
+            Line::Other(_) => None,
+        })
+    }
+
+    // Look up the host key blob presented by a server. Keys of other types
+    // count as a mismatch as well, so a server cannot avoid the check by
+    // offering a different host key algorithm.
+    pub fn check(&self, host: &str, port: u16, key: &[u8]) -> HostKeyStatus {
+        self.check_marked(host, port, key, None)
+    }
+
+    // Look up the key which signed a host certificate among the
+    // `@cert-authority` entries of the host.
+    pub fn check_cert_authority(&self, host: &str, port: u16, key: &[u8]) -> HostKeyStatus {
+        self.check_marked(host, port, key, Some(Marker::CertAuthority))
+    }
+
+    fn check_marked(
+        &self,
+        host: &str,
+        port: u16,
+        key: &[u8],
+        marker: Option<Marker>,
+    ) -> HostKeyStatus {
+        let name = host_name(host, port);
+        let mut status = HostKeyStatus::Unknown;
+
+        for entry in self.entries().filter(|e| e.hosts.matches(&name)) {
+            let same_key = entry.key.get_blob() == key;
+
+            if entry.marker == Some(Marker::Revoked) {
+                if same_key {
+                    return HostKeyStatus::Revoked;
+                }
+            } else if entry.marker == marker {
+                status = match (same_key, status) {
+                    (true, _) | (false, HostKeyStatus::Match) => HostKeyStatus::Match,
+                    (false, _) => HostKeyStatus::Mismatch,
+                };
+            }
+        }
+
+        status
+    }
+
+    // Add a key for the host and return the new entry. Hashed entries hide
+    // the host name from anyone reading the file.
+    pub fn add(
+        &mut self,
+        host: &str,
+        port: u16,
+        key: &[u8],
+        hash: Option<&mut SharedRng>,
+    ) -> Result<KnownHostsEntry, KnownHostsError> {
+        let name = host_name(&host.to_lowercase(), port);
+        let hosts = match hash {
+            Some(rng) => {
+                let mut salt = vec![0u8; HASH_SALT_SIZE];
+                rng.fill_bytes(&mut salt);
+                let hash = hmac::<Sha1>(&salt, name.as_bytes());
+
+                HostPatterns::Hashed { salt, hash }
+            }
+            None => HostPatterns::Plain(vec![name]),
+        };
+
+        let key = PublicKeyEntry::new(key.to_vec(), "")
+            .map_err(|_| KnownHostsError::InvalidKey("host key does not start with its type"))?;
+        let entry = KnownHostsEntry {
+            marker: None,
+            hosts,
+            key,
+        };
+        self.lines.push(Line::Entry(entry.clone()));
+
+        Ok(entry)
+    }
+
+    // Remove the host key of the host. Entries for several hosts only lose
+    // the name of the host, returns whether any entry was changed.
+    pub fn remove(&mut self, host: &str, port: u16, key: &[u8]) -> bool {
+        let name = host_name(host, port);
+        let mut removed = false;
+
+        self.lines.retain_mut(|line| match line {
+            Line::Entry(entry)
+                if entry.marker.is_none()
+                    && entry.key.get_blob() == key
+                    && entry.hosts.matches(&name) =>
+            {
+                match entry.hosts.remove(&name) {
+                    Some(keep) => {
+                        removed = true;
+                        keep
+                    }
+                    None => true,
+                }
+            }
+            _ => true,
+        });
+
+        removed
+    }
+
+    // Write the file through a temporary one next to it, so it is never
+    // left partially written and keeps its permissions.
+    pub fn save(&self, path: &Path) -> Result<(), KnownHostsError> {
+        let (temp, mut file) = create_temp_file(path)?;
+        let result = fs::metadata(path)
+            .map(|metadata| Some(metadata.permissions()))
+            .or_else(|e| match e.kind() {
+                io::ErrorKind::NotFound => Ok(None),
+                _ => Err(e),
+            })
+            .and_then(|permissions| match permissions {
+                Some(permissions) => file.set_permissions(permissions),
+                None => Ok(()),
+            })
+            .and_then(|_| file.write_all(self.to_string().as_bytes()))
+            .and_then(|_| file.sync_all())
+            .and_then(|_| fs::rename(&temp, path));
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+
+        Ok(result?)
+    }
+}
+
+impl fmt::Display for KnownHosts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            match line {
+                Line::Entry(entry) => writeln!(f, "{}", entry)?,
+                Line::Other(text) => writeln!(f, "{}", text)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Host key, with its status in the known hosts, passed to the
+// verification callback.
+#[derive(Debug)]
+pub struct HostKeyCheck<'a> {
+    pub host: &'a str,
+    pub port: u16,
+    pub key: &'a [u8],
+    pub status: HostKeyStatus,
+}
+
+type HostKeyCallback = Box<dyn FnMut(&HostKeyCheck) -> bool + Send>;
+
+// Decides whether to trust the host key of a server. Without a callback
+// only listed keys are accepted, and with trust on first use also the keys
+// of unknown hosts, which are then appended to the file. The callback, e.g.
+// a prompt, decides about every key that is not revoked instead.
+pub struct HostKeyVerifier {
+    known_hosts: KnownHosts,
+    path: Option<PathBuf>,
+    trust_on_first_use: bool,
+    hash_hostnames: bool,
+    rng: SharedRng,
+    policy: AlgorithmPolicy,
+    callback: Option<HostKeyCallback>,
+}
+
+impl HostKeyVerifier {
+    pub fn new(known_hosts: KnownHosts) -> Self {
+        Self {
+            known_hosts,
+            path: None,
+            trust_on_first_use: false,
+            hash_hostnames: false,
+            rng: SharedRng::default(),
+            policy: AlgorithmPolicy::default(),
+            callback: None,
+        }
+    }
+
+    // Verifier for the known_hosts file at the path, which is also where
+    // trusted keys are appended to.
+    pub fn load(path: &Path) -> Result<Self, KnownHostsError> {
+        let mut verifier = Self::new(KnownHosts::load(path)?);
+        verifier.path = Some(path.to_path_buf());
+
+        Ok(verifier)
+    }
+
+    pub fn with_trust_on_first_use(mut self, enabled: bool) -> Self {
+        self.trust_on_first_use = enabled;
+        self
+    }
+
+    pub fn with_hashed_hostnames(mut self, enabled: bool) -> Self {
+        self.hash_hostnames = enabled;
+        self
+    }
+
+    pub fn with_rng(mut self, rng: SharedRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    // Policy for the keys of host certificates and their authorities.
+    pub fn with_policy(mut self, policy: AlgorithmPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_callback<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&HostKeyCheck) -> bool + Send + 'static,
+    {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    pub fn get_known_hosts(&self) -> &KnownHosts {
+        &self.known_hosts
+    }
+
+    pub fn verify(
+        &mut self,
+        host: &str,
+        port: u16,
+        key: &[u8],
+    ) -> Result<HostKeyStatus, KnownHostsError> {
+        if Reader::new(key)
+            .read_utf8()
+            .is_ok_and(certificate::is_certificate)
+        {
+            return self.verify_certificate(host, port, key);
+        }
+
+        let check = HostKeyCheck {
+            host,
+            port,
+            key,
+            status: self.known_hosts.check(host, port, key),
+        };
+        let name = host_name(host, port);
+
+        let accepted = match (&mut self.callback, check.status) {
+            (_, HostKeyStatus::Revoked) => return Err(KnownHostsError::RevokedHostKey(name)),
+            (Some(callback), _) => callback(&check),
+            (None, HostKeyStatus::Match) => true,
+            (None, HostKeyStatus::Unknown) => self.trust_on_first_use,
+            (None, HostKeyStatus::Mismatch) => false,
+        };
+
+        match (accepted, check.status) {
+            (true, HostKeyStatus::Unknown) if self.trust_on_first_use => {
+                self.append(host, port, key)?
+            }
+            (true, _) => {}
+            (false, HostKeyStatus::Unknown) => return Err(KnownHostsError::UnknownHost(name)),
+            (false, HostKeyStatus::Mismatch) => return Err(KnownHostsError::HostKeyMismatch(name)),
+            (false, _) => return Err(KnownHostsError::HostKeyRejected(name)),
+        }
+
+        Ok(check.status)
+    }
+
+    // Host certificates are accepted when they are signed by an authority
+    // listed for the host. Without one, the certified key is verified like
+    // a plain host key.
+    fn verify_certificate(
+        &mut self,
+        host: &str,
+        port: u16,
+        blob: &[u8],
+    ) -> Result<HostKeyStatus, KnownHostsError> {
+        let cert = Certificate::from_blob(blob, &self.policy)?;
+        let key = cert.get_key().to_blob();
+        let name = host_name(host, port);
+
+        match self
+            .known_hosts
+            .check_cert_authority(host, port, cert.get_signature_key())
+        {
+            HostKeyStatus::Revoked => Err(KnownHostsError::RevokedHostKey(name)),
+            HostKeyStatus::Match => {
+                if self.known_hosts.check(host, port, &key) == HostKeyStatus::Revoked {
+                    return Err(KnownHostsError::RevokedHostKey(name));
+                }
+                cert.validate(CertificateType::Host, host, unix_time(), None)?;
+
+                Ok(HostKeyStatus::Match)
+            }
+            _ => self.verify(host, port, &key),
+        }
+    }
+
+    // Replace the host keys of the host, e.g. after the server announced
+    // new ones. The file is rewritten when keys are removed.
+    pub fn update_host_keys(
+        &mut self,
+        host: &str,
+        port: u16,
+        add: &[Vec<u8>],
+        remove: &[Vec<u8>],
+    ) -> Result<(), KnownHostsError> {
+        for key in add {
+            self.append(host, port, key)?;
+        }
+
+        let mut removed = false;
+        for key in remove {
+            removed |= self.known_hosts.remove(host, port, key);
+        }
+
+        match (&self.path, removed) {
+            (Some(path), true) => self.known_hosts.save(path),
+            _ => Ok(()),
+        }
+    }
+
+    fn append(&mut self, host: &str, port: u16, key: &[u8]) -> Result<(), KnownHostsError> {
+        let rng = self.hash_hostnames.then_some(&mut self.rng);
+        let line = self.known_hosts.add(host, port, key, rng)?.to_string();
+
+        if let Some(path) = &self.path {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?
+                .write_all(format!("{}\n", line).as_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for HostKeyVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HostKeyVerifier")
+            .field("path", &self.path)
+            .field("trust_on_first_use", &self.trust_on_first_use)
+            .field("hash_hostnames", &self.hash_hostnames)
+            .finish_non_exhaustive()
+    }
+}
+
+// Host names on other than the default port are written as `[host]:port`.
+fn host_name(host: &str, port: u16) -> String {
+    match port {
+        DEFAULT_PORT => host.to_string(),
+        _ => format!("[{}]:{}", host, port),
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+fn split_field(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+
+    match text.find(char::is_whitespace) {
+        Some(i) => (&text[..i], text[i..].trim_start()),
+        None => (text, ""),
+    }
+}
+
+// Create a file with an unused name in the directory of the path.
+fn create_temp_file(path: &Path) -> io::Result<(PathBuf, File)> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+    let mut attempt = 0u32;
+
+    loop {
+        let temp = path.with_file_name(format!(".{}.{}.{}.tmp", name, std::process::id(), attempt));
+        match OpenOptions::new().write(true).create_new(true).open(&temp) {
+            Ok(file) => return Ok((temp, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// `*` matches any number of characters and `?` exactly one.
+fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|i| wildcard_match(rest, &name[i..])),
+        Some((b'?', rest)) => !name.is_empty() && wildcard_match(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && wildcard_match(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::signature::certificate::CertificateBuilder;
+    use crypto::signature::ed25519::Ed25519PrivateKey;
+    use crypto::signature::PublicKey;
+    use rstest::rstest;
+    use std::sync::{Arc, Mutex};
+
+    const ED25519_KEY: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINfg1xNoKmOiosPf/16ApWhaadVwYDfkUF04ucIafUNo";
+    const NISTP256_KEY: &str = concat!(
+        "ecdsa-sha2-nistp256 AAAAE2VjZHNhLXNoYTItbmlzdHAyNTYAAAAIbmlzdHAyNTYAAABBBDybXZ8iMOOa+z",
+        "QajeoxaCQd3THW6O6NS9h/hJ7szvHD0eOwT7ELYB2Wl8VgKXQkGTVboPHA5z1WiwBITSVrz9g=",
+    );
+    // Public key of RFC 8032, section 7.1, test 1.
+    const OTHER_KEY: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINdamAGCsQq31Uv+08lkBzoO4XLz2qYjJa8CGmj3B1Ea";
+    // Hashed with `ssh-keygen -H` from entries for example.com and
+    // [git.example.com]:2222.
+    const HASHED: &str = concat!(
+        "|1|mvEa/xLzR3BAJ+saBotYJWmTZYE=|MWiNq+oy5S3+eAw6VKwJM/58Lfc= ssh-ed25519 AAAAC3NzaC1lZDI1NTE5",
+        "AAAAINfg1xNoKmOiosPf/16ApWhaadVwYDfkUF04ucIafUNo\n",
+        "|1|B2qsHXiOgx+8l6r21bT9pjNZWS8=|/UR86HVmWk9d0V2NROVrlT57B/I= ecdsa-sha2-nistp256 AAAAE2VjZHNh",
+        "LXNoYTItbmlzdHAyNTYAAAAIbmlzdHAyNTYAAABBBDybXZ8iMOOa+zQajeoxaCQd3THW6O6NS9h/hJ7szvHD0eOwT7EL",
+        "YB2Wl8VgKXQkGTVboPHA5z1WiwBITSVrz9g=\n",
+    );
+
+    // Signed by CA_KEY with `ssh-keygen -s ca -h -I web01 -z 7
+    // -n web01.example.com,web01 -V 20240101000000Z:20340101000000Z` for
+    // NISTP256_KEY.
+    const HOST_CERT: &str = concat!(
+        "ecdsa-sha2-nistp256-cert-v01@openssh.com AAAAKGVjZHNhLXNoYTItbmlzdHAyNTYtY2VydC12MDFAb3Bl",
+        "bnNzaC5jb20AAAAglqKaxFnZqcekZjmyVPFTK1/lDyxy0lkH5UHj7vFU+MEAAAAIbmlzdHAyNTYAAABBBDybXZ8iMOOa",
+        "+zQajeoxaCQd3THW6O6NS9h/hJ7szvHD0eOwT7ELYB2Wl8VgKXQkGTVboPHA5z1WiwBITSVrz9gAAAAAAAAABwAAAAIA",
+        "AAAFd2ViMDEAAAAeAAAAEXdlYjAxLmV4YW1wbGUuY29tAAAABXdlYjAxAAAAAGWSAIAAAAAAeGH4AAAAAAAAAAAAAAAA",
+        "AAAAADMAAAALc3NoLWVkMjU1MTkAAAAg5t3fsXC4t98sZETmy82ko3jSig89hE7n+KtpcU5oDogAAABTAAAAC3NzaC1l",
+        "ZDI1NTE5AAAAQEkU5qclbzvnk2v+jcq/hiAFcbXJx3I8nuHwQH+16e1JMU0X+M/OArPd+C+91iUrD5VUMwALj28AXR7F",
+        "L9ODDQg=",
+    );
+    const CA_KEY: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIObd37FwuLffLGRE5svNpKN40ooPPYRO5/iraXFOaA6I";
+
+    fn blob(key: &str) -> Vec<u8> {
+        PublicKeyEntry::from_line(key).unwrap().get_blob().to_vec()
+    }
+
+    fn known_hosts() -> KnownHosts {
+        KnownHosts::parse(&format!(
+            "# servers\n\
+             example.com,192.0.2.1 {ed25519}\n\
+             [git.example.com]:2222 {nistp256}\n\
+             *.internal,!db.internal {ed25519} wildcard\n\
+             node-?.example.org {nistp256}\n\
+             @cert-authority *.example.net {other}\n\
+             @revoked * {other}\n\
+             not a valid line\n",
+            ed25519 = ED25519_KEY,
+            nistp256 = NISTP256_KEY,
+            other = OTHER_KEY,
+        ))
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "parustiko-known-hosts-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_file(&path);
+
+        path
+    }
+
+    #[rstest]
+    #[case("example.com", 22, ED25519_KEY, HostKeyStatus::Match)]
+    #[case("EXAMPLE.com", 22, ED25519_KEY, HostKeyStatus::Match)]
+    #[case("192.0.2.1", 22, ED25519_KEY, HostKeyStatus::Match)]
+    #[case("example.com", 22, NISTP256_KEY, HostKeyStatus::Mismatch)]
+    #[case("example.com", 2222, ED25519_KEY, HostKeyStatus::Unknown)]
+    #[case("git.example.com", 2222, NISTP256_KEY, HostKeyStatus::Match)]
+    #[case("git.example.com", 22, NISTP256_KEY, HostKeyStatus::Unknown)]
+    #[case("web.internal", 22, ED25519_KEY, HostKeyStatus::Match)]
+    #[case("db.internal", 22, ED25519_KEY, HostKeyStatus::Unknown)]
+    #[case("node-1.example.org", 22, NISTP256_KEY, HostKeyStatus::Match)]
+    #[case("node-10.example.org", 22, NISTP256_KEY, HostKeyStatus::Unknown)]
+    #[case("example.com", 22, OTHER_KEY, HostKeyStatus::Revoked)]
+    fn test_check(
+        #[case] host: &str,
+        #[case] port: u16,
+        #[case] key: &str,
+        #[case] expected: HostKeyStatus,
+    ) {
+        assert_eq!(known_hosts().check(host, port, &blob(key)), expected);
+    }
+
+    #[test]
+    fn test_check_hashed_hosts() {
+        let known_hosts = KnownHosts::parse(HASHED);
+
+        assert!(known_hosts.entries().all(KnownHostsEntry::is_hashed));
+        assert_eq!(
+            known_hosts.check("example.com", 22, &blob(ED25519_KEY)),
+            HostKeyStatus::Match
+        );
+        assert_eq!(
+            known_hosts.check("git.example.com", 2222, &blob(NISTP256_KEY)),
+            HostKeyStatus::Match
+        );
+        assert_eq!(
+            known_hosts.check("git.example.com", 2222, &blob(ED25519_KEY)),
+            HostKeyStatus::Mismatch
+        );
+        assert_eq!(
+            known_hosts.check("example.org", 22, &blob(ED25519_KEY)),
+            HostKeyStatus::Unknown
+        );
+    }
+
+    #[test]
+    fn test_check_hashed_hosts_ignores_case() {
+        let known_hosts = KnownHosts::parse(HASHED);
+
+        assert_eq!(
+            known_hosts.check("Example.COM", 22, &blob(ED25519_KEY)),
+            HostKeyStatus::Match
+        );
+        assert_eq!(
+            known_hosts.check("GIT.example.com", 2222, &blob(NISTP256_KEY)),
+            HostKeyStatus::Match
+        );
+    }
+
+    #[test]
+    fn test_check_cert_authority() {
+        let authorities = KnownHosts::parse(&format!(
+            "@cert-authority *.example.net {}\n{} {}\n",
+            NISTP256_KEY, "host.example.net", ED25519_KEY
+        ));
+
+        assert_eq!(
+            authorities.check_cert_authority("host.example.net", 22, &blob(NISTP256_KEY)),
+            HostKeyStatus::Match
+        );
+        assert_eq!(
+            authorities.check("host.example.net", 22, &blob(NISTP256_KEY)),
+            HostKeyStatus::Mismatch
+        );
+        assert_eq!(
+            authorities.check_cert_authority("example.org", 22, &blob(NISTP256_KEY)),
+            HostKeyStatus::Unknown
+        );
+        assert_eq!(
+            known_hosts().check_cert_authority("ca.example.net", 22, &blob(OTHER_KEY)),
+            HostKeyStatus::Revoked
+        );
+    }
+
+    #[test]
+    fn test_write_back_keeps_all_lines() {
+        let text = known_hosts().to_string();
+
+        assert!(text.starts_with("# servers\nexample.com,192.0.2.1 ssh-ed25519 "));
+        assert!(text.contains("\n@cert-authority *.example.net ssh-ed25519 "));
+        assert!(text.ends_with("\nnot a valid line\n"));
+        assert_eq!(KnownHosts::parse(HASHED).to_string(), HASHED);
+    }
+
+    #[test]
+    fn test_add_hashed_entry() {
+        let mut known_hosts = KnownHosts::default();
+        let mut rng = SharedRng::seeded(42);
+        let entry = known_hosts
+            .add("git.example.com", 2222, &blob(NISTP256_KEY), Some(&mut rng))
+            .unwrap();
+
+        assert!(entry.is_hashed());
+        assert!(entry.matches("git.example.com", 2222));
+        assert!(entry.matches("Git.Example.com", 2222));
+        assert!(!entry.to_string().contains("example"));
+        assert_eq!(
+            known_hosts.check("git.example.com", 2222, &blob(NISTP256_KEY)),
+            HostKeyStatus::Match
+        );
+    }
+
+    #[rstest]
+    #[case(ED25519_KEY, None)]
+    #[case(
+        NISTP256_KEY,
+        Some("host key of example.com does not match the known hosts")
+    )]
+    #[case(OTHER_KEY, Some("host key of example.com is revoked"))]
+    fn test_verify(#[case] key: &str, #[case] expected: Option<&str>) {
+        let mut verifier = HostKeyVerifier::new(known_hosts()).with_trust_on_first_use(true);
+        let result = verifier.verify("example.com", 22, &blob(key));
+
+        assert_eq!(result.err().map(|e| e.to_string()).as_deref(), expected);
+    }
+
+    #[test]
+    fn test_verify_unknown_host_without_trust_on_first_use() {
+        let mut verifier = HostKeyVerifier::new(known_hosts());
+
+        assert_eq!(
+            verifier
+                .verify("new.example.com", 22, &blob(ED25519_KEY))
+                .unwrap_err()
+                .to_string(),
+            "host key of new.example.com is not known"
+        );
+    }
+
+    #[test]
+    fn test_verify_trust_on_first_use_appends() {
+        let path = temp_path("tofu");
+        fs::write(&path, format!("example.com {}\n", ED25519_KEY)).unwrap();
+        let mut verifier = HostKeyVerifier::load(&path)
+            .unwrap()
+            .with_trust_on_first_use(true)
+            .with_hashed_hostnames(true)
+            .with_rng(SharedRng::seeded(42));
+
+        let first = verifier.verify("new.example.com", 2222, &blob(NISTP256_KEY));
+        let second = verifier.verify("new.example.com", 2222, &blob(NISTP256_KEY));
+        let reloaded = KnownHosts::load(&path).unwrap();
+
+        assert_eq!(first.unwrap(), HostKeyStatus::Unknown);
+        assert_eq!(second.unwrap(), HostKeyStatus::Match);
+        assert_eq!(reloaded.entries().count(), 2);
+        assert_eq!(
+            reloaded.check("new.example.com", 2222, &blob(NISTP256_KEY)),
+            HostKeyStatus::Match
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_verify_callback() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let statuses = Arc::clone(&seen);
+        let mut verifier = HostKeyVerifier::new(known_hosts()).with_callback(move |check| {
+            statuses.lock().unwrap().push(check.status);
+            check.status == HostKeyStatus::Mismatch
+        });
+
+        assert!(verifier
+            .verify("example.com", 22, &blob(NISTP256_KEY))
+            .is_ok());
+        assert_eq!(
+            verifier
+                .verify("example.com", 22, &blob(ED25519_KEY))
+                .unwrap_err()
+                .to_string(),
+            "host key of example.com was rejected"
+        );
+        assert!(verifier
+            .verify("example.com", 22, &blob(OTHER_KEY))
+            .is_err());
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [HostKeyStatus::Mismatch, HostKeyStatus::Match]
+        );
+    }
+
+    #[rstest]
+    #[case("web01.example.com", 22, None)]
+    #[case("web01", 2222, None)]
+    #[case(
+        "web02.example.com",
+        22,
+        Some("host certificate is invalid: (principal is not allowed by the certificate: (web02.example.com))")
+    )]
+    #[case(
+        "web01.example.org",
+        22,
+        Some("host key of web01.example.org is not known")
+    )]
+    fn test_verify_host_certificate(
+        #[case] host: &str,
+        #[case] port: u16,
+        #[case] expected: Option<&str>,
+    ) {
+        let known_hosts = KnownHosts::parse(&format!(
+            "@cert-authority *.example.com,[web01]:2222 {}\n",
+            CA_KEY
+        ));
+        let mut verifier = HostKeyVerifier::new(known_hosts);
+        let result = verifier.verify(host, port, &blob(HOST_CERT));
+
+        assert_eq!(result.err().map(|e| e.to_string()).as_deref(), expected);
+    }
+
+    #[test]
+    fn test_verify_host_certificate_falls_back_to_key() {
+        let known_hosts = KnownHosts::parse(&format!(
+            "web01.example.com {}\n@revoked web01 {}\n",
+            NISTP256_KEY, NISTP256_KEY
+        ));
+        let mut verifier = HostKeyVerifier::new(known_hosts);
+
+        assert_eq!(
+            verifier
+                .verify("web01.example.com", 22, &blob(HOST_CERT))
+                .unwrap(),
+            HostKeyStatus::Match
+        );
+        assert_eq!(
+            verifier
+                .verify("web01", 22, &blob(HOST_CERT))
+                .unwrap_err()
+                .to_string(),
+            "host key of web01 is revoked"
+        );
+    }
+
+    #[test]
+    fn test_verify_expired_host_certificate() {
+        let mut rng = SharedRng::seeded(42);
+        let authority = Ed25519PrivateKey::generate(&mut rng);
+        let key = Ed25519PrivateKey::generate(&mut rng);
+        let cert = CertificateBuilder::new(&key.public(), CertificateType::Host)
+            .with_principal("example.com")
+            .with_validity(0, 1)
+            .sign(&authority, &mut rng)
+            .unwrap();
+        let known_hosts = KnownHosts::parse(&format!(
+            "@cert-authority example.com {}\n",
+            PublicKeyEntry::new(authority.public().to_blob(), "")
+                .unwrap()
+                .to_line()
+        ));
+
+        assert_eq!(
+            HostKeyVerifier::new(known_hosts)
+                .verify("example.com", 22, &cert.to_blob())
+                .unwrap_err()
+                .to_string(),
+            "host certificate is invalid: (certificate has expired)"
+        );
+    }
+
+    #[rstest]
+    #[case("example.com", 22, ED25519_KEY, true, HostKeyStatus::Unknown)]
+    #[case("git.example.com", 2222, NISTP256_KEY, true, HostKeyStatus::Unknown)]
+    #[case("web.internal", 22, ED25519_KEY, false, HostKeyStatus::Match)]
+    #[case("node-1.example.org", 22, NISTP256_KEY, false, HostKeyStatus::Match)]
+    #[case("example.com", 22, NISTP256_KEY, false, HostKeyStatus::Mismatch)]
+    fn test_remove(
+        #[case] host: &str,
+        #[case] port: u16,
+        #[case] key: &str,
+        #[case] expected: bool,
+        #[case] status: HostKeyStatus,
+    ) {
+        let mut known_hosts = known_hosts();
+
+        assert_eq!(known_hosts.remove(host, port, &blob(key)), expected);
+        assert_eq!(known_hosts.check(host, port, &blob(key)), status);
+        assert_eq!(
+            known_hosts.check("192.0.2.1", 22, &blob(ED25519_KEY)),
+            HostKeyStatus::Match
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_save_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_path("permissions");
+        fs::write(&path, "").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        let stale = path.with_file_name(format!(
+            ".{}.{}.0.tmp",
+            path.file_name().unwrap().to_string_lossy(),
+            std::process::id()
+        ));
+        fs::write(&stale, "stale").unwrap();
+
+        known_hosts().save(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(KnownHosts::load(&path).unwrap().entries().count(), 6);
+        assert_eq!(fs::read_to_string(&stale).unwrap(), "stale");
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&stale).unwrap();
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let known_hosts = KnownHosts::load(&temp_path("missing")).unwrap();
+
+        assert_eq!(known_hosts.entries().count(), 0);
+    }
+}
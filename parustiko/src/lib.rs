@@ -1,9 +1,15 @@
-mod errors;
-mod protocol;
+pub mod agent;
+pub mod auth;
+pub mod config;
+pub mod errors;
+pub mod hostkeys;
+pub mod known_hosts;
+pub mod protocol;
+pub mod rng;
+pub mod transport;
 mod version_exchange;
 
 use crypto::encryption::aes::{GenericArray, AES};
-use crypto::encryption::Encryption;
 use errors::VersionExchangeError;
 use protocol::DecodeRaw;
 use protocol::{BinaryProtocolPacket, Decode};
@@ -5,11 +5,11 @@ use crate::protocol::message_ids::SshMessageID;
 use crate::protocol::{Decode, DecodeRaw, Encode};
 
 use num_traits::FromPrimitive;
-use rand::Rng;
+use rand::RngCore;
 use std::io::Read;
 use std::mem::size_of;
 
-const MAX_BINARY_PROTOCOL_PAYLOAD_SIZE_BYTES: usize = 32_768;
+pub(super) const MAX_BINARY_PROTOCOL_PAYLOAD_SIZE_BYTES: usize = 32_768;
 const MIN_PADDING_SIZE_BYTES: u8 = 4;
 
 impl BinaryProtocolPacket {
@@ -20,6 +20,10 @@ impl BinaryProtocolPacket {
             ));
         }
 
+        if payload.is_empty() {
+            return Err(BppError::InvalidEntity("payload of SSH message is empty"));
+        }
+
         if padding_length < MIN_PADDING_SIZE_BYTES {
             return Err(BppError::InvalidEntity(
                 "padding is too short for SSH message",
@@ -41,9 +45,10 @@ impl BinaryProtocolPacket {
         })
     }
 
-    fn build_random_padding(&self) -> Vec<u8> {
-        let mut rng = rand::thread_rng();
-        (0..self.padding_length).map(|_| rng.gen()).collect()
+    fn build_random_padding<R: RngCore + ?Sized>(&self, rng: &mut R) -> Vec<u8> {
+        let mut padding = vec![0_u8; self.padding_length as usize];
+        rng.fill_bytes(&mut padding);
+        padding
     }
 
     pub fn get_payload(&self) -> &Vec<u8> {
@@ -53,8 +58,9 @@ impl BinaryProtocolPacket {
 
 impl Encode for BinaryProtocolPacket {
     // SSH protocol utilizes the network endianness, so the packets
-    // should be encoded with big endian.
-    fn to_be_bytes(mut self) -> Result<Vec<u8>, BppError> {
+    // should be encoded with big endian. The random padding is taken
+    // from the given generator.
+    fn to_be_bytes<R: RngCore + ?Sized>(mut self, rng: &mut R) -> Result<Vec<u8>, BppError> {
         let expected_buff_size = self.size();
         let mut buff = Vec::with_capacity(expected_buff_size);
 
@@ -66,7 +72,7 @@ impl Encode for BinaryProtocolPacket {
 
         buff.append(&mut self.payload);
 
-        let mut random_padding = self.build_random_padding();
+        let mut random_padding = self.build_random_padding(rng);
         buff.append(&mut random_padding);
 
         buff.append(&mut self.mac);
@@ -103,7 +109,11 @@ impl DecodeRaw for BinaryProtocolPacket {
         buffer.read_exact(&mut padding_length)?;
 
         let padding_length = u8::from_be_bytes(padding_length);
-        let payload_length = packet_length - padding_length as usize - 1;
+        let payload_length = packet_length
+            .checked_sub(padding_length as usize + 1)
+            .ok_or(BppError::InvalidEntity(
+                "padding length does not fit into the packet",
+            ))?;
 
         let mut payload = vec![0_u8; payload_length];
         buffer.read_exact(&mut payload)?;
@@ -135,6 +145,7 @@ impl Decode for BinaryProtocolPacket {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rng::SharedRng;
     use rstest::{fixture, rstest};
 
     #[fixture]
@@ -164,6 +175,7 @@ mod tests {
     #[case(vec![0_u8; 33_000], 25, "payload is too long for SSH message")]
     #[case(vec![0_u8; 5], 3, "padding is too short for SSH message")]
     #[case(vec![0_u8; 5], 25, "unknown SSH message ID")]
+    #[case(vec![], 25, "payload of SSH message is empty")]
     fn build_protocol_message_errors(
         #[case] payload: Vec<u8>,
         #[case] padding_length: u8,
@@ -192,7 +204,7 @@ mod tests {
 
     #[rstest]
     fn build_random_padding_size(message: BinaryProtocolPacket) {
-        let pad = message.build_random_padding();
+        let pad = message.build_random_padding(&mut SharedRng::seeded(0));
 
         assert_eq!(message.padding_length as usize, pad.len());
     }
@@ -201,7 +213,10 @@ mod tests {
     fn encode_to_bytes_wrong_final_length(mut message: BinaryProtocolPacket) {
         message.mac_length = 13;
 
-        let err = message.to_be_bytes().unwrap_err().to_string();
+        let err = message
+            .to_be_bytes(&mut SharedRng::seeded(0))
+            .unwrap_err()
+            .to_string();
 
         assert_eq!(
             err,
@@ -214,7 +229,7 @@ mod tests {
         let original_payload = message.payload.clone();
         let original_mac = message.mac.clone();
 
-        let buff = message.to_be_bytes().unwrap();
+        let buff = message.to_be_bytes(&mut SharedRng::seeded(0)).unwrap();
 
         assert_eq!(buff[..4], [0, 0, 0, 50]);
         assert_eq!(buff[4..5], [25]);
@@ -222,6 +237,14 @@ mod tests {
         assert_eq!(buff[54..], original_mac);
     }
 
+    #[test]
+    fn encode_with_seeded_rng_is_deterministic() {
+        let first = message().to_be_bytes(&mut SharedRng::seeded(7)).unwrap();
+        let second = message().to_be_bytes(&mut SharedRng::seeded(7)).unwrap();
+
+        assert_eq!(first, second);
+    }
+
     #[rstest]
     fn calculate_encoded_length(message: BinaryProtocolPacket) {
         let length = message.size();
@@ -0,0 +1,630 @@
+use super::binary_packet::MAX_BINARY_PROTOCOL_PAYLOAD_SIZE_BYTES;
+
+use crate::errors::BppError;
+use crate::rng::SharedRng;
+
+use crypto::encryption::{AeadCipher, Cipher, CipherMode};
+use crypto::mac::Mac;
+use rand::RngCore;
+use std::io::Read;
+use std::mem::size_of;
+
+const MIN_BLOCK_SIZE_BYTES: usize = 8;
+const MIN_PADDING_SIZE_BYTES: usize = 4;
+const MAX_PACKET_LENGTH_BYTES: usize = 35_000;
+
+pub enum PacketCipher {
+    None,
+    Cipher(Box<dyn Cipher>),
+    Aead(Box<dyn AeadCipher>),
+}
+
+impl From<CipherMode> for PacketCipher {
+    fn from(mode: CipherMode) -> Self {
+        match mode {
+            CipherMode::Cipher(cipher) => Self::Cipher(cipher),
+            CipherMode::Aead(cipher) => Self::Aead(cipher),
+        }
+    }
+}
+
+impl PacketCipher {
+    fn block_size(&self) -> usize {
+        match self {
+            Self::None => MIN_BLOCK_SIZE_BYTES,
+            Self::Cipher(cipher) => cipher.block_size().max(MIN_BLOCK_SIZE_BYTES),
+            Self::Aead(cipher) => cipher.block_size().max(MIN_BLOCK_SIZE_BYTES),
+        }
+    }
+
+    // AEAD ciphers process the whole packet at once, so they never reach
+    // the plain encryption path.
+    fn encrypt(&mut self, buff: &mut [u8]) -> Result<(), BppError> {
+        match self {
+            Self::None => Ok(()),
+            Self::Cipher(cipher) => Ok(cipher.encrypt(buff)?),
+            Self::Aead(_) => Err(BppError::CipherModeMismatch(
+                "AEAD ciphers are sealed with the whole packet",
+            )),
+        }
+    }
+
+    fn decrypt(&mut self, buff: &mut [u8]) -> Result<(), BppError> {
+        match self {
+            Self::None => Ok(()),
+            Self::Cipher(cipher) => Ok(cipher.decrypt(buff)?),
+            Self::Aead(_) => Err(BppError::CipherModeMismatch(
+                "AEAD ciphers are opened with the whole packet",
+            )),
+        }
+    }
+}
+
+// Turns payloads into packets on the wire and back for a single direction
+// of the connection. Every packet bumps the sequence number, which is
+// never reset, even after NEWKEYS. The padding is taken from the RNG of
+// the transport configuration. Payloads are passed through as they are,
+// dispatching on the message ID is left to the caller.
+pub struct PacketCodec {
+    cipher: PacketCipher,
+    mac: Option<Box<dyn Mac>>,
+    sequence_number: u32,
+    rng: SharedRng,
+}
+
+impl PacketCodec {
+    pub fn new(cipher: PacketCipher, rng: SharedRng) -> Self {
+        Self {
+            cipher,
+            mac: None,
+            sequence_number: 0,
+            rng,
+        }
+    }
+
+    // Switch to the keys derived during the key exchange, keeping the
+    // sequence number intact.
+    pub fn set_cipher(&mut self, cipher: PacketCipher) {
+        self.cipher = cipher;
+    }
+
+    // The MAC is ignored when an AEAD cipher is in use.
+    pub fn set_mac(&mut self, mac: Option<Box<dyn Mac>>) {
+        self.mac = mac;
+    }
+
+    pub fn get_sequence_number(&self) -> u32 {
+        self.sequence_number
+    }
+
+    pub fn encode(&mut self, payload: Vec<u8>) -> Result<Vec<u8>, BppError> {
+        let mut buff = self.frame(&payload)?;
+        let sequence_number = self.sequence_number;
+
+        let tag = match (&mut self.cipher, &mut self.mac) {
+            (PacketCipher::Aead(cipher), _) => cipher.seal(sequence_number, &mut buff)?,
+            (cipher, Some(mac)) if mac.is_etm() => {
+                cipher.encrypt(&mut buff[size_of::<u32>()..])?;
+                mac.compute(sequence_number, &buff)
+            }
+            (cipher, Some(mac)) => {
+                let tag = mac.compute(sequence_number, &buff);
+                cipher.encrypt(&mut buff)?;
+                tag
+            }
+            (cipher, None) => {
+                cipher.encrypt(&mut buff)?;
+                vec![]
+            }
+        };
+
+        buff.extend_from_slice(&tag);
+        self.sequence_number = self.sequence_number.wrapping_add(1);
+
+        Ok(buff)
+    }
+
+    pub fn decode<R: Read>(&mut self, buffer: &mut R) -> Result<Vec<u8>, BppError> {
+        let sequence_number = self.sequence_number;
+        let block_size = self.cipher.block_size();
+
+        let plaintext = match (&mut self.cipher, &mut self.mac) {
+            (PacketCipher::Aead(cipher), _) => {
+                let mut packet = vec![0_u8; size_of::<u32>()];
+                buffer.read_exact(&mut packet)?;
+
+                let length = cipher.decrypt_length(sequence_number, &packet)?;
+                let remaining = Self::remaining_length(&length, size_of::<u32>())?;
+                Self::check_alignment(remaining, block_size)?;
+                Self::read_more(buffer, &mut packet, remaining)?;
+
+                let mut tag = vec![0_u8; cipher.tag_len()];
+                buffer.read_exact(&mut tag)?;
+
+                cipher.open(sequence_number, &mut packet, &tag)?;
+                packet
+            }
+            (cipher, Some(mac)) if mac.is_etm() => {
+                // The length is sent in the clear and the MAC covers the
+                // ciphertext, so the packet is verified before decryption.
+                let mut packet = vec![0_u8; size_of::<u32>()];
+                buffer.read_exact(&mut packet)?;
+
+                let remaining = Self::remaining_length(&packet, size_of::<u32>())?;
+                Self::check_alignment(remaining, block_size)?;
+                Self::read_more(buffer, &mut packet, remaining)?;
+
+                let mut tag = vec![0_u8; mac.tag_len()];
+                buffer.read_exact(&mut tag)?;
+
+                mac.verify(sequence_number, &packet, &tag)
+                    .map_err(|_| BppError::MacMismatch(sequence_number))?;
+
+                cipher.decrypt(&mut packet[size_of::<u32>()..])?;
+                packet
+            }
+            (cipher, mac) => {
+                // The length is encrypted, so the first block has to be
+                // decrypted before the rest of the packet can be read.
+                let mut packet = vec![0_u8; block_size];
+                buffer.read_exact(&mut packet)?;
+                cipher.decrypt(&mut packet)?;
+
+                let remaining = Self::remaining_length(&packet, block_size)?;
+                Self::check_alignment(block_size + remaining, block_size)?;
+                let mut rest = vec![0_u8; remaining];
+                buffer.read_exact(&mut rest)?;
+                cipher.decrypt(&mut rest)?;
+                packet.append(&mut rest);
+
+                if let Some(mac) = mac {
+                    let mut tag = vec![0_u8; mac.tag_len()];
+                    buffer.read_exact(&mut tag)?;
+
+                    mac.verify(sequence_number, &packet, &tag)
+                        .map_err(|_| BppError::MacMismatch(sequence_number))?;
+                }
+
+                packet
+            }
+        };
+
+        self.sequence_number = self.sequence_number.wrapping_add(1);
+
+        Self::extract_payload(plaintext)
+    }
+
+    fn frame(&mut self, payload: &[u8]) -> Result<Vec<u8>, BppError> {
+        if payload.is_empty() {
+            return Err(BppError::InvalidEntity("payload of SSH message is empty"));
+        }
+
+        if payload.len() > MAX_BINARY_PROTOCOL_PAYLOAD_SIZE_BYTES {
+            return Err(BppError::InvalidEntity(
+                "payload is too long for SSH message",
+            ));
+        }
+
+        let padding_length = self.padding_length(payload.len());
+        let packet_length = 1 + payload.len() + padding_length as usize;
+        let mut buff = Vec::with_capacity(size_of::<u32>() + packet_length);
+        buff.extend_from_slice(&(packet_length as u32).to_be_bytes());
+        buff.push(padding_length);
+        buff.extend_from_slice(payload);
+
+        let padding_start = buff.len();
+        buff.resize(padding_start + padding_length as usize, 0);
+        self.rng.fill_bytes(&mut buff[padding_start..]);
+
+        Ok(buff)
+    }
+
+    // The packet length field is excluded from the padding calculation only
+    // when it is not a part of the encrypted data stream.
+    fn padding_length(&self, payload_length: usize) -> u8 {
+        let block_size = self.cipher.block_size();
+        let length_in_clear = match (&self.cipher, &self.mac) {
+            (PacketCipher::Aead(_), _) => true,
+            (_, Some(mac)) => mac.is_etm(),
+            (_, None) => false,
+        };
+        let aligned_length = if length_in_clear {
+            1 + payload_length
+        } else {
+            size_of::<u32>() + 1 + payload_length
+        };
+
+        let mut padding = block_size - aligned_length % block_size;
+        if padding < MIN_PADDING_SIZE_BYTES {
+            padding += block_size;
+        }
+
+        padding as u8
+    }
+
+    // Calculate how many bytes of the packet are left to be read, given
+    // the decrypted packet length and the number of bytes already read.
+    fn remaining_length(packet: &[u8], already_read: usize) -> Result<usize, BppError> {
+        let length_bytes: [u8; 4] = packet[..size_of::<u32>()]
+            .try_into()
+            .expect("Should not be reachable");
+        let total_length = size_of::<u32>() + u32::from_be_bytes(length_bytes) as usize;
+
+        if total_length > MAX_PACKET_LENGTH_BYTES {
+            return Err(BppError::InvalidEntity(
+                "packet length exceeds the maximum size",
+            ));
+        }
+
+        if total_length < already_read {
+            return Err(BppError::InvalidEntity(
+                "packet length is shorter than a single block",
+            ));
+        }
+
+        Ok(total_length - already_read)
+    }
+
+    // The padding length comes from the peer, it has to leave room for at
+    // least the message ID in the packet.
+    fn extract_payload(mut packet: Vec<u8>) -> Result<Vec<u8>, BppError> {
+        let length_bytes: [u8; 4] = packet[..size_of::<u32>()]
+            .try_into()
+            .expect("Should not be reachable");
+        let packet_length = u32::from_be_bytes(length_bytes) as usize;
+
+        let padding_length = match packet.get(size_of::<u32>()) {
+            Some(&padding_length) if padding_length as usize + 1 < packet_length => {
+                padding_length as usize
+            }
+            _ => {
+                return Err(BppError::InvalidEntity(
+                    "padding length does not fit into the packet",
+                ))
+            }
+        };
+
+        packet.truncate(size_of::<u32>() + packet_length - padding_length);
+        packet.drain(..size_of::<u32>() + 1);
+
+        Ok(packet)
+    }
+
+    fn check_alignment(length: usize, block_size: usize) -> Result<(), BppError> {
+        if !length.is_multiple_of(block_size) {
+            return Err(BppError::InvalidEntity(
+                "packet length is not a multiple of the block size",
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn read_more<R: Read>(
+        buffer: &mut R,
+        packet: &mut Vec<u8>,
+        length: usize,
+    ) -> Result<(), BppError> {
+        let start = packet.len();
+        packet.resize(start + length, 0);
+        buffer.read_exact(&mut packet[start..])?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TransportConfig;
+    use crate::protocol::message_ids::SshMessageID;
+    use crypto::registry::{build_cipher, build_mac};
+    use rstest::rstest;
+
+    fn chacha() -> PacketCipher {
+        let key: Vec<u8> = (0..64).collect();
+        build_cipher("chacha20-poly1305@openssh.com", &key, &[])
+            .unwrap()
+            .into()
+    }
+
+    fn gcm() -> PacketCipher {
+        build_cipher("aes128-gcm@openssh.com", &[3_u8; 16], &[5_u8; 12])
+            .unwrap()
+            .into()
+    }
+
+    fn ctr() -> PacketCipher {
+        build_cipher("aes128-ctr", &[7_u8; 16], &[9_u8; 16])
+            .unwrap()
+            .into()
+    }
+
+    #[cfg(feature = "legacy-ciphers")]
+    fn cbc(name: &str) -> PacketCipher {
+        let policy = crypto::registry::AlgorithmPolicy {
+            allow_legacy_ciphers: true,
+            ..Default::default()
+        };
+        let algorithm = policy.find_cipher(name).unwrap();
+
+        algorithm
+            .build(
+                &vec![7_u8; algorithm.key_len],
+                &vec![9_u8; algorithm.iv_len],
+            )
+            .unwrap()
+            .into()
+    }
+
+    fn codec(cipher: PacketCipher, mac: Option<&str>) -> PacketCodec {
+        let mut codec = PacketCodec::new(cipher, SharedRng::seeded(0));
+        codec.set_mac(mac.map(|name| build_mac(name, &vec![11_u8; 64][..key_len(name)]).unwrap()));
+        codec
+    }
+
+    fn key_len(name: &str) -> usize {
+        crypto::registry::find_mac(name).unwrap().key_len
+    }
+
+    #[rstest]
+    #[case(PacketCipher::None, 8, 0)]
+    #[case(ctr(), 16, 0)]
+    #[case(chacha(), 8, 4)]
+    #[case(gcm(), 16, 4)]
+    fn encode_aligns_packet_to_block_size(
+        #[case] cipher: PacketCipher,
+        #[case] block_size: usize,
+        #[case] unaligned_prefix: usize,
+    ) {
+        let mut codec = PacketCodec::new(cipher, SharedRng::seeded(0));
+        let tag_size = match &codec.cipher {
+            PacketCipher::Aead(cipher) => cipher.tag_len(),
+            _ => 0,
+        };
+
+        for payload_length in 1..40 {
+            let mut payload = vec![0_u8; payload_length];
+            payload[0] = SshMessageID::KexInit as u8;
+
+            let buff = codec.encode(payload).unwrap();
+
+            assert_eq!((buff.len() - tag_size - unaligned_prefix) % block_size, 0);
+        }
+    }
+
+    #[rstest]
+    #[case(PacketCipher::None, PacketCipher::None)]
+    #[case(ctr(), ctr())]
+    #[case(chacha(), chacha())]
+    #[case(gcm(), gcm())]
+    fn encode_and_decode_roundtrip(#[case] sender: PacketCipher, #[case] receiver: PacketCipher) {
+        let mut sender = PacketCodec::new(sender, SharedRng::seeded(0));
+        let mut receiver = PacketCodec::new(receiver, SharedRng::seeded(0));
+        let mut wire = Vec::new();
+
+        wire.extend(sender.encode(vec![20_u8, 1, 2, 3]).unwrap());
+        wire.extend(sender.encode(vec![20_u8; 50]).unwrap());
+
+        let mut reader = &wire[..];
+        let first = receiver.decode(&mut reader).unwrap();
+        let second = receiver.decode(&mut reader).unwrap();
+
+        assert_eq!(first, [20_u8, 1, 2, 3]);
+        assert_eq!(second, [20_u8; 50]);
+        assert_eq!(sender.get_sequence_number(), 2);
+        assert_eq!(receiver.get_sequence_number(), 2);
+        assert!(reader.is_empty());
+    }
+
+    #[rstest]
+    #[case(chacha(), chacha())]
+    #[case(gcm(), gcm())]
+    fn decode_aead_rejects_tampered_packet(
+        #[case] sender: PacketCipher,
+        #[case] receiver: PacketCipher,
+    ) {
+        let mut sender = PacketCodec::new(sender, SharedRng::seeded(0));
+        let mut receiver = PacketCodec::new(receiver, SharedRng::seeded(0));
+
+        let mut wire = sender.encode(vec![20_u8, 1, 2, 3]).unwrap();
+        wire[6] ^= 0x01;
+
+        let err = receiver.decode(&mut &wire[..]).unwrap_err().to_string();
+
+        assert_eq!(
+            err,
+            "decrypting packet failed: (authentication tag mismatch)"
+        );
+    }
+
+    #[rstest]
+    #[case(ctr(), "hmac-sha2-256", 16, 0)]
+    #[case(ctr(), "hmac-sha2-512-etm@openssh.com", 16, 4)]
+    #[case(PacketCipher::None, "hmac-sha1-etm@openssh.com", 8, 4)]
+    fn encode_with_mac_aligns_packet_to_block_size(
+        #[case] cipher: PacketCipher,
+        #[case] mac: &str,
+        #[case] block_size: usize,
+        #[case] unaligned_prefix: usize,
+    ) {
+        let mut codec = codec(cipher, Some(mac));
+        let tag_size = codec.mac.as_ref().unwrap().tag_len();
+
+        for payload_length in 1..40 {
+            let buff = codec.encode(vec![20_u8; payload_length]).unwrap();
+
+            assert_eq!((buff.len() - tag_size - unaligned_prefix) % block_size, 0);
+        }
+    }
+
+    #[rstest]
+    #[case("hmac-sha2-256")]
+    #[case("hmac-sha2-512")]
+    #[case("hmac-sha1")]
+    #[case("hmac-sha2-256-etm@openssh.com")]
+    #[case("hmac-sha2-512-etm@openssh.com")]
+    #[case("hmac-sha1-etm@openssh.com")]
+    #[case("umac-64@openssh.com")]
+    #[case("umac-128-etm@openssh.com")]
+    fn encode_and_decode_roundtrip_with_mac(#[case] mac: &str) {
+        let mut sender = codec(ctr(), Some(mac));
+        let mut receiver = codec(ctr(), Some(mac));
+        let mut wire = Vec::new();
+
+        wire.extend(sender.encode(vec![20_u8, 1, 2, 3]).unwrap());
+        wire.extend(sender.encode(vec![20_u8; 50]).unwrap());
+
+        let mut reader = &wire[..];
+        let first = receiver.decode(&mut reader).unwrap();
+        let second = receiver.decode(&mut reader).unwrap();
+
+        assert_eq!(first, [20_u8, 1, 2, 3]);
+        assert_eq!(second, [20_u8; 50]);
+        assert!(reader.is_empty());
+    }
+
+    #[rstest]
+    #[case::ext_info(vec![7_u8, 0, 0, 0, 0])]
+    #[case::newkeys(vec![21_u8])]
+    #[case::kex_ecdh_reply(vec![31_u8, 0, 0, 0, 1, 2])]
+    #[case::channel_data(vec![94_u8, 0, 0, 0, 0, 0, 0, 0, 1, 42])]
+    fn decode_passes_through_any_message_id(#[case] payload: Vec<u8>) {
+        let mut sender = codec(ctr(), Some("hmac-sha2-256"));
+        let mut receiver = codec(ctr(), Some("hmac-sha2-256"));
+
+        let wire = sender.encode(payload.clone()).unwrap();
+        let decoded = receiver.decode(&mut &wire[..]).unwrap();
+
+        assert_eq!(decoded, payload);
+    }
+
+    #[test]
+    fn decode_unencrypted_packet_with_unknown_message_id() {
+        let wire = [0_u8, 0, 0, 12, 4, 94, 0, 0, 0, 0, 42, 0, 0, 0, 0, 0];
+        let mut codec = PacketCodec::new(PacketCipher::None, SharedRng::seeded(0));
+
+        let payload = codec.decode(&mut &wire[..]).unwrap();
+
+        assert_eq!(payload, [94_u8, 0, 0, 0, 0, 42, 0]);
+    }
+
+    #[test]
+    fn encode_etm_leaves_length_unencrypted() {
+        let mut codec = codec(ctr(), Some("hmac-sha2-256-etm@openssh.com"));
+
+        let buff = codec.encode(vec![20_u8; 10]).unwrap();
+
+        assert_eq!(buff[..4], [0, 0, 0, 16]);
+    }
+
+    #[rstest]
+    #[case("hmac-sha2-256", 8)]
+    #[case("hmac-sha2-256", 40)]
+    #[case("hmac-sha2-256-etm@openssh.com", 8)]
+    #[case("hmac-sha2-256-etm@openssh.com", 40)]
+    fn decode_rejects_tampered_packet_with_mac(#[case] mac: &str, #[case] position: usize) {
+        let mut sender = codec(ctr(), Some(mac));
+        let mut receiver = codec(ctr(), Some(mac));
+
+        let mut wire = sender.encode(vec![20_u8, 1, 2, 3]).unwrap();
+        wire[position] ^= 0x01;
+
+        let err = receiver.decode(&mut &wire[..]).unwrap_err().to_string();
+
+        assert_eq!(err, "message authentication code mismatch in packet 0");
+    }
+
+    #[test]
+    fn decode_rejects_unaligned_packet_length() {
+        let mut sender = codec(ctr(), Some("hmac-sha2-256-etm@openssh.com"));
+        let mut receiver = codec(ctr(), Some("hmac-sha2-256-etm@openssh.com"));
+
+        let mut wire = sender.encode(vec![20_u8, 1, 2, 3]).unwrap();
+        wire[3] -= 1;
+
+        let err = receiver.decode(&mut &wire[..]).unwrap_err().to_string();
+
+        assert_eq!(
+            err,
+            "entity preconditions are not met: (packet length is not a multiple of the block size)"
+        );
+    }
+
+    #[test]
+    fn decode_chacha_rejects_out_of_order_packet() {
+        let mut sender = PacketCodec::new(chacha(), SharedRng::seeded(0));
+        let mut receiver = PacketCodec::new(chacha(), SharedRng::seeded(0));
+
+        let _ = sender.encode(vec![20_u8, 1]).unwrap();
+        let wire = sender.encode(vec![20_u8, 2]).unwrap();
+
+        assert!(receiver.decode(&mut &wire[..]).is_err());
+    }
+
+    #[test]
+    fn encode_is_byte_exact_with_seeded_rng() {
+        let config = TransportConfig::default().with_rng(SharedRng::seeded(1));
+        let mut codec = PacketCodec::new(ctr(), config.rng.clone());
+        codec.set_mac(Some(build_mac("hmac-sha2-256", &[11_u8; 32]).unwrap()));
+
+        let wire = codec.encode(vec![20_u8, 1, 2, 3]).unwrap();
+
+        // 16 bytes of aes128-ctr ciphertext followed by the HMAC tag.
+        let expected = [
+            0x91, 0x4f, 0x12, 0x8c, 0xae, 0xb1, 0x2e, 0x69, 0x45, 0xa3, 0x66, 0x39, 0xdc, 0x88,
+            0xb2, 0xdf, 0xa8, 0x84, 0xeb, 0xe9, 0xf7, 0x69, 0x2b, 0x0f, 0x24, 0x43, 0xd4, 0x86,
+            0x1b, 0xd7, 0x60, 0x49, 0x42, 0xeb, 0x7e, 0x5d, 0x5e, 0x58, 0x73, 0xc2, 0xfb, 0xff,
+            0x3f, 0x1d, 0xda, 0xc0, 0x37, 0xb5,
+        ];
+
+        assert_eq!(wire, expected);
+    }
+
+    #[test]
+    fn decode_rejects_too_long_packet() {
+        let wire = [0_u8, 1, 0, 0, 4, 20, 0, 0];
+        let mut codec = PacketCodec::new(PacketCipher::None, SharedRng::seeded(0));
+
+        let err = codec.decode(&mut &wire[..]).unwrap_err().to_string();
+
+        assert_eq!(
+            err,
+            "entity preconditions are not met: (packet length exceeds the maximum size)"
+        );
+    }
+
+    #[rstest]
+    #[case::padding_longer_than_packet([0_u8, 0, 0, 4, 10, 20, 0, 0])]
+    #[case::empty_payload([0_u8, 0, 0, 4, 3, 0, 0, 0])]
+    fn decode_rejects_invalid_padding_length(#[case] wire: [u8; 8]) {
+        let mut codec = PacketCodec::new(PacketCipher::None, SharedRng::seeded(0));
+
+        let err = codec.decode(&mut &wire[..]).unwrap_err().to_string();
+
+        assert_eq!(
+            err,
+            "entity preconditions are not met: (padding length does not fit into the packet)"
+        );
+    }
+
+    #[cfg(feature = "legacy-ciphers")]
+    #[rstest]
+    #[case("aes128-cbc")]
+    #[case("3des-cbc")]
+    fn encode_and_decode_roundtrip_with_legacy_cipher(#[case] name: &str) {
+        let mut sender = codec(cbc(name), Some("hmac-sha1"));
+        let mut receiver = codec(cbc(name), Some("hmac-sha1"));
+        let mut wire = Vec::new();
+
+        wire.extend(sender.encode(vec![20_u8, 1, 2, 3]).unwrap());
+        wire.extend(sender.encode(vec![20_u8; 50]).unwrap());
+
+        let mut reader = &wire[..];
+        let first = receiver.decode(&mut reader).unwrap();
+        let second = receiver.decode(&mut reader).unwrap();
+
+        assert_eq!(first, [20_u8, 1, 2, 3]);
+        assert_eq!(second, [20_u8; 50]);
+    }
+}
@@ -3,5 +3,22 @@ use num_derive::FromPrimitive;
 #[repr(u8)]
 #[derive(Debug, Eq, FromPrimitive, PartialEq, Clone)]
 pub enum SshMessageID {
+    Disconnect = 1,
+    Ignore = 2,
+    Unimplemented = 3,
+    Debug = 4,
+    ServiceRequest = 5,
+    ServiceAccept = 6,
     KexInit = 20,
+    UserauthRequest = 50,
+    UserauthFailure = 51,
+    UserauthSuccess = 52,
+    UserauthBanner = 53,
+    // Shared by the method specific SSH_MSG_USERAUTH_PK_OK,
+    // SSH_MSG_USERAUTH_PASSWD_CHANGEREQ and SSH_MSG_USERAUTH_INFO_REQUEST.
+    UserauthMethodSpecific = 60,
+    UserauthInfoResponse = 61,
+    GlobalRequest = 80,
+    RequestSuccess = 81,
+    RequestFailure = 82,
 }
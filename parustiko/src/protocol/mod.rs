@@ -1,9 +1,13 @@
 mod binary_packet;
+mod codec;
 pub mod message_ids;
 
+pub use codec::{PacketCipher, PacketCodec};
+
 use crate::errors::BppError;
 use message_ids::SshMessageID;
 
+use rand::RngCore;
 use std::io::Read;
 
 #[derive(Debug)]
@@ -17,8 +21,10 @@ pub struct BinaryProtocolPacket {
 }
 
 pub trait Encode {
-    // Consume the entity and return it's BE byte representation.
-    fn to_be_bytes(self) -> Result<Vec<u8>, BppError>;
+    // Consume the entity and return it's BE byte representation. Random
+    // bytes are taken from the given generator, usually the one of the
+    // transport configuration.
+    fn to_be_bytes<R: RngCore + ?Sized>(self, rng: &mut R) -> Result<Vec<u8>, BppError>;
 
     // Calculate the size of BE byte representation.
     fn size(&self) -> usize;
@@ -0,0 +1,98 @@
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+// Any cryptographically secure generator which can be moved between threads.
+pub trait SecureRng: RngCore + CryptoRng + Send {}
+
+impl<R: RngCore + CryptoRng + Send> SecureRng for R {}
+
+// Handle to the single generator used for every random value of a
+// connection: packet padding, KEXINIT cookies and ephemeral keys. Clones
+// share the same generator, so a seeded one gives byte-exact output across
+// the whole handshake.
+#[derive(Clone)]
+pub struct SharedRng(Arc<Mutex<dyn SecureRng>>);
+
+impl SharedRng {
+    pub fn new<R: SecureRng + 'static>(rng: R) -> Self {
+        Self(Arc::new(Mutex::new(rng)))
+    }
+
+    // Generator backed by the operating system, used in production.
+    pub fn system() -> Self {
+        Self::new(OsRng)
+    }
+
+    // Deterministic generator for tests, never use it for real connections.
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn seeded(seed: u64) -> Self {
+        use rand::SeedableRng;
+
+        Self::new(rand_chacha::ChaCha20Rng::seed_from_u64(seed))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, dyn SecureRng + 'static> {
+        // The generator state stays valid even if another thread panicked
+        // while holding the lock.
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for SharedRng {
+    fn default() -> Self {
+        Self::system()
+    }
+}
+
+impl fmt::Debug for SharedRng {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedRng")
+    }
+}
+
+impl RngCore for SharedRng {
+    fn next_u32(&mut self) -> u32 {
+        self.lock().next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.lock().next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.lock().fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.lock().try_fill_bytes(dest)
+    }
+}
+
+impl CryptoRng for SharedRng {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_rng_is_deterministic() {
+        let mut first = SharedRng::seeded(42);
+        let mut second = SharedRng::seeded(42);
+
+        assert_eq!(first.next_u64(), second.next_u64());
+        assert_ne!(first.next_u64(), SharedRng::seeded(43).next_u64());
+    }
+
+    #[test]
+    fn clones_share_the_generator() {
+        let mut rng = SharedRng::seeded(42);
+        let mut clone = rng.clone();
+        let mut reference = SharedRng::seeded(42);
+
+        let values = [rng.next_u64(), clone.next_u64()];
+
+        assert_eq!(values, [reference.next_u64(), reference.next_u64()]);
+    }
+}
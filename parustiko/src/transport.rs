@@ -0,0 +1,86 @@
+use crate::errors::BppError;
+
+// Payloads of a transport connection after the key exchange, already
+// decrypted and checked. Services on top of the transport, like user
+// authentication, only depend on this trait.
+pub trait Transport {
+    fn send(&mut self, payload: &[u8]) -> Result<(), BppError>;
+
+    fn receive(&mut self) -> Result<Vec<u8>, BppError>;
+
+    // Exchange hash of the first key exchange, which identifies the
+    // session (RFC 4253, section 7.2).
+    fn session_id(&self) -> &[u8];
+}
+
+impl<T: Transport + ?Sized> Transport for &mut T {
+    fn send(&mut self, payload: &[u8]) -> Result<(), BppError> {
+        (**self).send(payload)
+    }
+
+    fn receive(&mut self) -> Result<Vec<u8>, BppError> {
+        (**self).receive()
+    }
+
+    fn session_id(&self) -> &[u8] {
+        (**self).session_id()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::io;
+
+    pub(crate) const SESSION_ID: &[u8] = b"mock session identifier";
+
+    type Server = Box<dyn FnMut(&[u8]) -> Vec<Vec<u8>>>;
+
+    // Transport to a fake server, which answers every sent payload with
+    // any number of messages. All sent payloads are kept for inspection.
+    pub(crate) struct MockTransport {
+        server: Server,
+        incoming: VecDeque<Vec<u8>>,
+        pub(crate) sent: Vec<Vec<u8>>,
+    }
+
+    impl MockTransport {
+        pub(crate) fn new<F>(server: F) -> Self
+        where
+            F: FnMut(&[u8]) -> Vec<Vec<u8>> + 'static,
+        {
+            Self {
+                server: Box::new(server),
+                incoming: VecDeque::new(),
+                sent: Vec::new(),
+            }
+        }
+    }
+
+    impl std::fmt::Debug for MockTransport {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("MockTransport")
+                .field("sent", &self.sent)
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl Transport for MockTransport {
+        fn send(&mut self, payload: &[u8]) -> Result<(), BppError> {
+            self.incoming.extend((self.server)(payload));
+            self.sent.push(payload.to_vec());
+            Ok(())
+        }
+
+        fn receive(&mut self) -> Result<Vec<u8>, BppError> {
+            self.incoming
+                .pop_front()
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof).into())
+        }
+
+        fn session_id(&self) -> &[u8] {
+            SESSION_ID
+        }
+    }
+}